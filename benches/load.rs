@@ -0,0 +1,73 @@
+//! Compares loading a large `OBJ` file from a reader and from memory.
+//!
+//! Run with `--features parallel` to include the multi-threaded loader.
+
+use std::{fmt::Write, io::Cursor};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+/// A grid of `n` × `n` quads with texture coordinates and normals, similar
+/// to a scan.
+fn grid(n: usize) -> String {
+    let mut obj = String::new();
+    for y in 0..=n {
+        for x in 0..=n {
+            let (u, v) = (x as f64 / n as f64, y as f64 / n as f64);
+            writeln!(obj, "v {} {} {}", u, v, (u * v).sin()).unwrap();
+            writeln!(obj, "vt {} {}", u, v).unwrap();
+            writeln!(obj, "vn 0 0 1").unwrap();
+        }
+    }
+    for y in 0..n {
+        for x in 0..n {
+            let a = y * (n + 1) + x + 1;
+            let (b, c, d) = (a + 1, a + n + 2, a + n + 1);
+            writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c} {d}/{d}/{d}").unwrap();
+        }
+    }
+    obj
+}
+
+fn load(c: &mut Criterion) {
+    let obj = grid(300);
+    let mut group = c.benchmark_group("load");
+    group.throughput(Throughput::Bytes(obj.len() as u64));
+
+    for (name, options) in [
+        ("gpu", tobj64::GPU_LOAD_OPTIONS),
+        ("offline", tobj64::OFFLINE_RENDERING_LOAD_OPTIONS),
+    ] {
+        group.bench_function(format!("buf/{}", name), |b| {
+            b.iter(|| {
+                tobj64::load_obj_buf::<_, _, f32>(
+                    &mut Cursor::new(&obj),
+                    &options,
+                    |_| unreachable!(),
+                )
+                .unwrap()
+            })
+        });
+        group.bench_function(format!("bytes/{}", name), |b| {
+            b.iter(|| {
+                tobj64::load_obj_bytes::<_, f32>(obj.as_bytes(), &options, |_| unreachable!())
+                    .unwrap()
+            })
+        });
+        #[cfg(feature = "parallel")]
+        group.bench_function(format!("parallel/{}", name), |b| {
+            b.iter(|| {
+                tobj64::load_obj_bytes_parallel::<_, f32>(
+                    obj.as_bytes(),
+                    &options,
+                    |_| unreachable!(),
+                )
+                .unwrap()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, load);
+criterion_main!(benches);
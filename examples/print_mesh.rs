@@ -15,7 +15,7 @@ fn main() {
 
     for (i, m) in models.iter().enumerate() {
         let mesh = &m.mesh;
-        println!("");
+        println!();
         println!("model[{}].name             = \'{}\'", i, m.name);
         println!("model[{}].mesh.material_id = {:?}", i, mesh.material_id);
 
@@ -0,0 +1,92 @@
+//! The forms in which `MTL` files can specify colors.
+
+use std::{fmt, str::FromStr};
+
+use crate::{LoadError, LoadErrorKind};
+
+/// How a color statement of a [`Material`](crate::Material), such as `Kd`,
+/// specifies its color.
+///
+/// The [`Display`](fmt::Display) implementation writes the color back the way
+/// it is specified in an `MTL` file, without the keyword.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorSource {
+    /// `Kd r g b`.
+    Rgb([f32; 3]),
+    /// CIE XYZ tristimulus values, `Kd xyz x y z`. If only `x` is given `y`
+    /// and `z` are the same.
+    Xyz([f32; 3]),
+    /// A spectral curve read from an `.rfl` file, scaled by a factor,
+    /// `Kd spectral file.rfl factor`. The factor defaults to `1`.
+    Spectral { file_name: String, factor: f32 },
+}
+
+impl ColorSource {
+    /// The color in linear RGB, converting XYZ values assuming the sRGB
+    /// primaries and a D65 white point. `None` for spectral curves, which
+    /// would require reading their file.
+    pub fn to_rgb(&self) -> Option<[f32; 3]> {
+        match *self {
+            ColorSource::Rgb(rgb) => Some(rgb),
+            ColorSource::Xyz([x, y, z]) => Some([
+                3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+                -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z,
+                0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+            ]),
+            ColorSource::Spectral { .. } => None,
+        }
+    }
+}
+
+impl FromStr for ColorSource {
+    type Err = LoadError;
+
+    /// Parse the arguments of a color statement, e.g. `xyz 0.5 0.5 0.5` for
+    /// `Kd xyz 0.5 0.5 0.5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |word: Option<&str>| -> Result<f32, LoadError> {
+            word.and_then(|word| word.parse().ok())
+                .ok_or_else(|| LoadErrorKind::MaterialParseError.into())
+        };
+
+        let mut words = s.split_whitespace();
+        let color = match words.next() {
+            Some("spectral") => {
+                let file_name = words.next().ok_or(LoadErrorKind::MaterialParseError)?;
+                let factor = match words.next() {
+                    Some(factor) => parse(Some(factor))?,
+                    None => 1.0,
+                };
+                ColorSource::Spectral {
+                    file_name: file_name.to_owned(),
+                    factor,
+                }
+            }
+            Some("xyz") => {
+                let x = parse(words.next())?;
+                match words.next() {
+                    Some(y) => ColorSource::Xyz([x, parse(Some(y))?, parse(words.next())?]),
+                    None => ColorSource::Xyz([x; 3]),
+                }
+            }
+            r => ColorSource::Rgb([parse(r)?, parse(words.next())?, parse(words.next())?]),
+        };
+
+        match words.next() {
+            Some(_) => Err(LoadErrorKind::MaterialParseError.into()),
+            None => Ok(color),
+        }
+    }
+}
+
+impl fmt::Display for ColorSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ColorSource::Rgb([r, g, b]) => write!(f, "{} {} {}", r, g, b),
+            ColorSource::Xyz([x, y, z]) => write!(f, "xyz {} {} {}", x, y, z),
+            ColorSource::Spectral { file_name, factor } => {
+                write!(f, "spectral {} {}", file_name, factor)
+            }
+        }
+    }
+}
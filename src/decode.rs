@@ -0,0 +1,117 @@
+//! Decoding of the textures referenced by [`Material`]s via the
+//! [`image`](https://crates.io/crates/image) crate.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{HashMap, Material, MissingTexture, NewHashMap, Texture, TextureResolver};
+
+/// A texture decoded into 8-bit RGBA pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedTexture {
+    /// The file the texture was decoded from.
+    pub path: PathBuf,
+    /// Width of the texture in pixels.
+    pub width: u32,
+    /// Height of the texture in pixels.
+    pub height: u32,
+    /// The pixels row by row, four bytes `[r, g, b, a]` each.
+    pub pixels: Vec<u8>,
+}
+
+/// The decoded textures of a single [`Material`].
+#[derive(Debug, Clone, Default)]
+pub struct MaterialTextures {
+    textures: HashMap<String, Arc<DecodedTexture>>,
+}
+
+impl MaterialTextures {
+    /// The decoded image of one of the textures of the material, e.g. its
+    /// [`diffuse_texture`](Material::diffuse_texture). `None` if the texture
+    /// is not set or could not be loaded.
+    pub fn get(&self, texture: &Texture) -> Option<&Arc<DecodedTexture>> {
+        self.textures.get(&texture.file_name)
+    }
+}
+
+/// A texture file that exists but could not be decoded.
+#[derive(Debug)]
+pub struct TextureDecodeError {
+    /// The file that could not be decoded.
+    pub path: PathBuf,
+    /// Why it could not be decoded.
+    pub error: image::ImageError,
+}
+
+/// The textures of a list of materials, as returned by
+/// [`TextureResolver::decode()`].
+#[derive(Debug, Default)]
+pub struct DecodedTextures {
+    /// The textures of each material, in the same order as the materials.
+    pub materials: Vec<MaterialTextures>,
+    /// Textures whose file does not exist.
+    pub missing: Vec<MissingTexture>,
+    /// Texture files that could not be decoded, once each.
+    pub errors: Vec<TextureDecodeError>,
+}
+
+impl TextureResolver {
+    /// Find and decode the textures of `materials`.
+    ///
+    /// Each file is decoded once. Materials using the same file share the
+    /// decoded image.
+    pub fn decode(&self, materials: &[Material]) -> DecodedTextures {
+        let mut decoded = DecodedTextures::default();
+        // `None` for files that failed to decode
+        let mut cache = HashMap::<PathBuf, Option<Arc<DecodedTexture>>>::new_map();
+
+        for (index, material) in materials.iter().enumerate() {
+            let mut textures = MaterialTextures::default();
+            for texture in material.textures() {
+                let path = match self.resolve(material, texture) {
+                    Some(path) => path,
+                    None => {
+                        decoded.missing.push(MissingTexture {
+                            material: index,
+                            file_name: texture.file_name.clone(),
+                        });
+                        continue;
+                    }
+                };
+                let image = cache.entry(path).or_insert_with_key(|path| {
+                    decode_file(path)
+                        .map_err(|error| {
+                            #[cfg(feature = "log")]
+                            log::error!("failed to decode texture {:?} due to {}", path, error);
+                            decoded.errors.push(TextureDecodeError {
+                                path: path.clone(),
+                                error,
+                            })
+                        })
+                        .ok()
+                });
+                if let Some(image) = image {
+                    textures
+                        .textures
+                        .insert(texture.file_name.clone(), image.clone());
+                }
+            }
+            decoded.materials.push(textures);
+        }
+
+        decoded
+    }
+}
+
+/// Decode an image file into 8-bit RGBA.
+fn decode_file(path: &Path) -> Result<Arc<DecodedTexture>, image::ImageError> {
+    let image = image::open(path)?.into_rgba8();
+    Ok(Arc::new(DecodedTexture {
+        path: path.to_owned(),
+        width: image.width(),
+        height: image.height(),
+        pixels: image.into_raw(),
+    }))
+}
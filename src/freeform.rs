@@ -0,0 +1,632 @@
+//! Free-form curves and surfaces, as specified via the `vp`, `cstype`, `deg`,
+//! `curv`, `curv2`, `surf`, `parm`, `trim`, `hole`, `scrv`, `end`, `ctech`
+//! and `stech` statements of an `OBJ` file.
+
+use std::{mem, str::SplitWhitespace};
+
+use crate::{
+    HashMap, LoadError, LoadErrorKind, NewHashMap, ParseableV, VertexIndices, MISSING_INDEX,
+};
+
+/// The type of a free-form curve or surface, as set by the `cstype`
+/// statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Basis {
+    /// Arbitrary basis matrix, `bmatrix`.
+    BasisMatrix,
+    /// Bezier, `bezier`.
+    Bezier,
+    /// Non-uniform B-spline, `bspline`. A NURBS if it is rational.
+    BSpline,
+    /// Cardinal spline, `cardinal`.
+    Cardinal,
+    /// Taylor polynomial, `taylor`.
+    Taylor,
+}
+
+/// How a curve is approximated by line segments, as set by the `ctech`
+/// statement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CurveTechnique {
+    /// `cparm res` – each polynomial segment of the curve is split into
+    /// `res` times its degree line segments of equal parameter length.
+    ConstantParameter(f32),
+    /// `cspace maxlength` – line segments are at most `maxlength` long.
+    ConstantSpace(f32),
+    /// `curv maxdist maxangle` – line segments deviate at most `maxdist` from
+    /// the curve and turn by at most `maxangle` degrees.
+    Curvature {
+        /// Maximum distance between the line segments and the curve.
+        max_distance: f32,
+        /// Maximum angle in degrees between two adjacent line segments.
+        max_angle: f32,
+    },
+}
+
+/// How a surface is approximated by triangles, as set by the `stech`
+/// statement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SurfaceTechnique {
+    /// `cparma ures vres` – each polynomial patch is split into `ures` and
+    /// `vres` times its degree steps of equal parameter length in `u` and
+    /// `v` direction.
+    ConstantParameterA {
+        /// Resolution in `u` direction.
+        resolution_u: f32,
+        /// Resolution in `v` direction.
+        resolution_v: f32,
+    },
+    /// `cparmb uvres` – like [`ConstantParameterA`](Self::ConstantParameterA)
+    /// with the same resolution in both directions, also used for the
+    /// trimming curves.
+    ConstantParameterB(f32),
+    /// `cspace maxlength` – triangle edges are at most `maxlength` long.
+    ConstantSpace(f32),
+    /// `curv maxdist maxangle` – triangles deviate at most `maxdist` from the
+    /// surface and the angle between adjacent ones is at most `maxangle`
+    /// degrees.
+    Curvature {
+        /// Maximum distance between the triangles and the surface.
+        max_distance: f32,
+        /// Maximum angle in degrees between adjacent triangles.
+        max_angle: f32,
+    },
+}
+
+/// The free-form geometry of a [`Model`](crate::Model).
+///
+/// Like with a [`Mesh`](crate::Mesh), the vertex data used by the curves and
+/// surfaces is copied out of the file and the curves and surfaces index into
+/// these copies. All `Vec`s are empty if the model has no free-form geometry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FreeForm<T: ParseableV> {
+    /// Flattened 3 component vectors, storing the control points of the
+    /// [`curves`](FreeForm::curves) and [`surfaces`](FreeForm::surfaces).
+    pub positions: Vec<T>,
+    /// The weights `w` of the [`positions`](FreeForm::positions), used by
+    /// rational curves and surfaces. Empty unless a `v` statement specifies a
+    /// weight, see [`Mesh::weights`](crate::Mesh::weights).
+    pub weights: Vec<T>,
+    /// Flattened 2 component vectors, storing the texture coordinates of the
+    /// control points of the [`surfaces`](FreeForm::surfaces).
+    pub texcoords: Vec<T>,
+    /// Flattened 3 component vectors, storing the normals of the control
+    /// points of the [`surfaces`](FreeForm::surfaces).
+    pub normals: Vec<T>,
+    /// Flattened 3 component vectors, storing the parameter space vertices
+    /// (`vp u v w`) used by the [`curves_2d`](FreeForm::curves_2d). `v`
+    /// defaults to `0` and the weight `w` to `1` if left out.
+    pub parameter_vertices: Vec<T>,
+    /// Curves in 3D space (`curv`).
+    pub curves: Vec<Curve<T>>,
+    /// Curves in the parameter space of a surface (`curv2`), either defined
+    /// within the object or used to trim its surfaces.
+    pub curves_2d: Vec<Curve2D<T>>,
+    /// Surfaces (`surf`).
+    pub surfaces: Vec<Surface<T>>,
+}
+
+/// A free-form curve in 3D space, specified by a `curv` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve<T: ParseableV> {
+    /// Type of the curve.
+    pub basis: Basis,
+    /// Whether the curve is rational, i.e. its control points are weighted.
+    pub rational: bool,
+    /// Degree of the curve.
+    pub degree: u32,
+    /// Start and end of the curve in its parameter space.
+    pub range: [T; 2],
+    /// Indices of the control points into [`FreeForm::positions`].
+    pub control_points: Vec<u32>,
+    /// Global parameter values (`parm u`), e.g. the knot vector of a
+    /// B-spline.
+    pub knots: Vec<T>,
+    /// Approximation technique set by the last `ctech` statement before the
+    /// curve, if any.
+    pub technique: Option<CurveTechnique>,
+}
+
+/// A free-form curve in the parameter space of a surface, specified by a
+/// `curv2` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve2D<T: ParseableV> {
+    /// Type of the curve.
+    pub basis: Basis,
+    /// Whether the curve is rational, i.e. its control points are weighted.
+    pub rational: bool,
+    /// Degree of the curve.
+    pub degree: u32,
+    /// Indices of the control points into
+    /// [`FreeForm::parameter_vertices`].
+    pub control_points: Vec<u32>,
+    /// Global parameter values (`parm u`), e.g. the knot vector of a
+    /// B-spline.
+    pub knots: Vec<T>,
+}
+
+/// A free-form surface, specified by a `surf` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Surface<T: ParseableV> {
+    /// Type of the surface.
+    pub basis: Basis,
+    /// Whether the surface is rational, i.e. its control points are
+    /// weighted.
+    pub rational: bool,
+    /// Degree of the surface in `u` and `v` direction.
+    pub degree: [u32; 2],
+    /// Start and end of the surface in `u` direction.
+    pub range_u: [T; 2],
+    /// Start and end of the surface in `v` direction.
+    pub range_v: [T; 2],
+    /// Indices of the control points into [`FreeForm::positions`].
+    pub control_points: Vec<u32>,
+    /// Indices of the texture coordinates of the control points into
+    /// [`FreeForm::texcoords`]. Empty unless specified for all control
+    /// points.
+    pub texcoord_indices: Vec<u32>,
+    /// Indices of the normals of the control points into
+    /// [`FreeForm::normals`]. Empty unless specified for all control points.
+    pub normal_indices: Vec<u32>,
+    /// Global parameter values in `u` direction (`parm u`), e.g. the knot
+    /// vector of a B-spline.
+    pub knots_u: Vec<T>,
+    /// Global parameter values in `v` direction (`parm v`).
+    pub knots_v: Vec<T>,
+    /// Outer trimming loops (`trim`).
+    pub trims: Vec<Vec<TrimCurve<T>>>,
+    /// Inner trimming loops (`hole`).
+    pub holes: Vec<Vec<TrimCurve<T>>>,
+    /// Special curves that have to be part of a triangulation of the surface
+    /// (`scrv`).
+    pub special_curves: Vec<Vec<TrimCurve<T>>>,
+    /// Approximation technique set by the last `stech` statement before the
+    /// surface, if any.
+    pub technique: Option<SurfaceTechnique>,
+}
+
+/// A segment of a trimming loop or special curve of a [`Surface`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimCurve<T: ParseableV> {
+    /// Start and end of the segment in the parameter space of the curve.
+    pub range: [T; 2],
+    /// Index of the curve into [`FreeForm::curves_2d`].
+    pub curve: usize,
+}
+
+impl<T: ParseableV> Default for FreeForm<T> {
+    /// Create an empty set of free-form geometry.
+    fn default() -> Self {
+        Self {
+            positions: Vec::new(),
+            weights: Vec::new(),
+            texcoords: Vec::new(),
+            normals: Vec::new(),
+            parameter_vertices: Vec::new(),
+            curves: Vec::new(),
+            curves_2d: Vec::new(),
+            surfaces: Vec::new(),
+        }
+    }
+}
+
+impl<T: ParseableV> FreeForm<T> {
+    /// Whether there are no curves or surfaces.
+    pub fn is_empty(&self) -> bool {
+        self.curves.is_empty() && self.curves_2d.is_empty() && self.surfaces.is_empty()
+    }
+}
+
+/// The free-form element statements like `parm` apply to.
+#[derive(Debug, Clone, Copy)]
+enum Element {
+    Curve,
+    Curve2D,
+    Surface,
+}
+
+/// The state of the free-form geometry of an `OBJ` file being parsed.
+///
+/// Vertex indices of the curves and surfaces stay indices into the whole file
+/// until the object is complete.
+pub(crate) struct FreeFormParser<T: ParseableV> {
+    // set by `cstype`
+    basis: Option<(Basis, bool)>,
+    // set by `deg`
+    degree: Option<[u32; 2]>,
+    // set by `ctech` and `stech`
+    curve_technique: Option<CurveTechnique>,
+    surface_technique: Option<SurfaceTechnique>,
+    // all `vp` of the file
+    parameter_vertices: Vec<T>,
+    // all `curv2` of the file
+    curves_2d: Vec<Curve2D<T>>,
+    // elements of the current object
+    curves: Vec<Curve<T>>,
+    surfaces: Vec<Surface<T>>,
+    object_curves_2d: Vec<usize>,
+    // element between the last `curv`/`curv2`/`surf` and `end`
+    current: Option<Element>,
+}
+
+impl<T: ParseableV> FreeFormParser<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            basis: None,
+            degree: None,
+            curve_technique: None,
+            surface_technique: None,
+            parameter_vertices: Vec::new(),
+            curves_2d: Vec::new(),
+            curves: Vec::new(),
+            surfaces: Vec::new(),
+            object_curves_2d: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// Whether the current object has any free-form geometry.
+    pub(crate) fn has_object_data(&self) -> bool {
+        !self.curves.is_empty() || !self.surfaces.is_empty() || !self.object_curves_2d.is_empty()
+    }
+
+    /// Parse a free-form statement. `pos_sz`, `tex_sz` and `norm_sz` are the
+    /// numbers of vertices parsed so far, to resolve relative indices.
+    pub(crate) fn parse_statement(
+        &mut self,
+        keyword: &str,
+        mut words: SplitWhitespace,
+        pos_sz: usize,
+        tex_sz: usize,
+        norm_sz: usize,
+    ) -> Result<(), LoadError> {
+        match keyword {
+            "vp" => {
+                let mut vertex = [T::zero(), T::zero(), T::one()];
+                let mut count = 0;
+                for (component, word) in vertex.iter_mut().zip(&mut words) {
+                    *component = parse(word)?;
+                    count += 1;
+                }
+                if count == 0 || words.next().is_some() {
+                    return Err(LoadErrorKind::FreeFormParseError.into());
+                }
+                self.parameter_vertices.extend_from_slice(&vertex);
+            }
+            "cstype" => {
+                let mut basis = words.next();
+                let rational = basis == Some("rat");
+                if rational {
+                    basis = words.next();
+                }
+                let basis = match basis {
+                    Some("bmatrix") => Basis::BasisMatrix,
+                    Some("bezier") => Basis::Bezier,
+                    Some("bspline") => Basis::BSpline,
+                    Some("cardinal") => Basis::Cardinal,
+                    Some("taylor") => Basis::Taylor,
+                    _ => return Err(LoadErrorKind::FreeFormParseError.into()),
+                };
+                self.basis = Some((basis, rational));
+            }
+            "deg" => {
+                let degree_u = parse(words.next().unwrap_or_default())?;
+                let degree_v = words.next().map_or(Ok(0), parse)?;
+                self.degree = Some([degree_u, degree_v]);
+            }
+            "curv" => {
+                let (basis, rational, [degree, _]) = self.curve_type()?;
+                let range = [next(&mut words)?, next(&mut words)?];
+                let control_points = words
+                    .map(|word| resolve(word, pos_sz))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.curves.push(Curve {
+                    basis,
+                    rational,
+                    degree,
+                    range,
+                    control_points,
+                    knots: Vec::new(),
+                    technique: self.curve_technique,
+                });
+                self.current = Some(Element::Curve);
+            }
+            "curv2" => {
+                let (basis, rational, [degree, _]) = self.curve_type()?;
+                let control_points = words
+                    .map(|word| resolve(word, self.parameter_vertices.len() / 3))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.object_curves_2d.push(self.curves_2d.len());
+                self.curves_2d.push(Curve2D {
+                    basis,
+                    rational,
+                    degree,
+                    control_points,
+                    knots: Vec::new(),
+                });
+                self.current = Some(Element::Curve2D);
+            }
+            "surf" => {
+                let (basis, rational, degree) = self.curve_type()?;
+                let range_u = [next(&mut words)?, next(&mut words)?];
+                let range_v = [next(&mut words)?, next(&mut words)?];
+                let mut surface = Surface {
+                    basis,
+                    rational,
+                    degree,
+                    range_u,
+                    range_v,
+                    control_points: Vec::new(),
+                    texcoord_indices: Vec::new(),
+                    normal_indices: Vec::new(),
+                    knots_u: Vec::new(),
+                    knots_v: Vec::new(),
+                    trims: Vec::new(),
+                    holes: Vec::new(),
+                    special_curves: Vec::new(),
+                    technique: self.surface_technique,
+                };
+                for word in words {
+                    let vertex = VertexIndices::parse(word, pos_sz, tex_sz, norm_sz)
+                        .ok_or(LoadErrorKind::FreeFormParseError)?;
+                    surface.control_points.push(vertex.v as u32);
+                    surface.texcoord_indices.push(vertex.vt as u32);
+                    surface.normal_indices.push(vertex.vn as u32);
+                }
+                // Texture coordinates and normals are only kept if specified
+                // for all control points.
+                for indices in [&mut surface.texcoord_indices, &mut surface.normal_indices] {
+                    if indices.contains(&(MISSING_INDEX as u32)) {
+                        indices.clear();
+                    }
+                }
+                self.surfaces.push(surface);
+                self.current = Some(Element::Surface);
+            }
+            "parm" => {
+                let direction = words.next();
+                let values = words.map(parse).collect::<Result<Vec<T>, _>>()?;
+                let knots = match (self.current, direction) {
+                    (Some(Element::Curve), Some("u")) => &mut self.curves.last_mut().unwrap().knots,
+                    (Some(Element::Curve2D), Some("u")) => {
+                        &mut self.curves_2d.last_mut().unwrap().knots
+                    }
+                    (Some(Element::Surface), Some("u")) => {
+                        &mut self.surfaces.last_mut().unwrap().knots_u
+                    }
+                    (Some(Element::Surface), Some("v")) => {
+                        &mut self.surfaces.last_mut().unwrap().knots_v
+                    }
+                    _ => return Err(LoadErrorKind::FreeFormParseError.into()),
+                };
+                knots.extend(values);
+            }
+            "trim" | "hole" | "scrv" => {
+                let mut segments = Vec::new();
+                while let Some(start) = words.next() {
+                    let range = [parse(start)?, next(&mut words)?];
+                    let curve = words.next().ok_or(LoadErrorKind::FreeFormParseError)?;
+                    let curve = resolve(curve, self.curves_2d.len())? as usize;
+                    segments.push(TrimCurve { range, curve });
+                }
+                let surface = match self.current {
+                    Some(Element::Surface) if !segments.is_empty() => {
+                        self.surfaces.last_mut().unwrap()
+                    }
+                    _ => return Err(LoadErrorKind::FreeFormParseError.into()),
+                };
+                match keyword {
+                    "trim" => surface.trims.push(segments),
+                    "hole" => surface.holes.push(segments),
+                    _ => surface.special_curves.push(segments),
+                }
+            }
+            "end" => self.current = None,
+            "ctech" => {
+                let technique = match words.next() {
+                    Some("cparm") => CurveTechnique::ConstantParameter(next(&mut words)?),
+                    Some("cspace") => CurveTechnique::ConstantSpace(next(&mut words)?),
+                    Some("curv") => CurveTechnique::Curvature {
+                        max_distance: next(&mut words)?,
+                        max_angle: next(&mut words)?,
+                    },
+                    _ => return Err(LoadErrorKind::FreeFormParseError.into()),
+                };
+                self.curve_technique = Some(technique);
+            }
+            "stech" => {
+                let technique = match words.next() {
+                    Some("cparma") => SurfaceTechnique::ConstantParameterA {
+                        resolution_u: next(&mut words)?,
+                        resolution_v: next(&mut words)?,
+                    },
+                    Some("cparmb") => SurfaceTechnique::ConstantParameterB(next(&mut words)?),
+                    Some("cspace") => SurfaceTechnique::ConstantSpace(next(&mut words)?),
+                    Some("curv") => SurfaceTechnique::Curvature {
+                        max_distance: next(&mut words)?,
+                        max_angle: next(&mut words)?,
+                    },
+                    _ => return Err(LoadErrorKind::FreeFormParseError.into()),
+                };
+                self.surface_technique = Some(technique);
+            }
+            _ => unreachable!("not a free-form statement"),
+        }
+
+        Ok(())
+    }
+
+    /// The type of curve or surface set by the last `cstype` and `deg`
+    /// statements, which are required before any curve or surface.
+    fn curve_type(&self) -> Result<(Basis, bool, [u32; 2]), LoadError> {
+        match (self.basis, self.degree) {
+            (Some((basis, rational)), Some(degree)) => Ok((basis, rational, degree)),
+            _ => Err(LoadErrorKind::FreeFormParseError.into()),
+        }
+    }
+
+    /// Take the free-form geometry of the current object, copying the vertex
+    /// data it uses.
+    pub(crate) fn take_object(
+        &mut self,
+        pos: &[T],
+        weights: &[T],
+        texcoord: &[T],
+        normal: &[T],
+    ) -> Result<FreeForm<T>, LoadError> {
+        let mut free_form = FreeForm::default();
+        let mut pos_map = HashMap::new_map();
+        let mut texcoord_map = HashMap::new_map();
+        let mut normal_map = HashMap::new_map();
+        let mut parameter_map = HashMap::new_map();
+        let mut curve_2d_map = HashMap::new_map();
+
+        let curves = mem::take(&mut self.curves);
+        let surfaces = mem::take(&mut self.surfaces);
+        let object_curves_2d = mem::take(&mut self.object_curves_2d);
+        self.current = None;
+
+        for mut curve in curves {
+            for index in &mut curve.control_points {
+                *index = copy(&mut pos_map, *index, pos, [3, 3], &mut free_form.positions)?;
+            }
+            free_form.curves.push(curve);
+        }
+
+        for &curve in &object_curves_2d {
+            self.add_curve_2d(&mut free_form, &mut curve_2d_map, &mut parameter_map, curve)?;
+        }
+
+        for mut surface in surfaces {
+            for index in &mut surface.control_points {
+                *index = copy(&mut pos_map, *index, pos, [3, 3], &mut free_form.positions)?;
+            }
+            // Texture coordinates are padded to 3 components while parsing.
+            for index in &mut surface.texcoord_indices {
+                *index = copy(
+                    &mut texcoord_map,
+                    *index,
+                    texcoord,
+                    [3, 2],
+                    &mut free_form.texcoords,
+                )?;
+            }
+            for index in &mut surface.normal_indices {
+                *index = copy(
+                    &mut normal_map,
+                    *index,
+                    normal,
+                    [3, 3],
+                    &mut free_form.normals,
+                )?;
+            }
+            for segment in surface
+                .trims
+                .iter_mut()
+                .chain(&mut surface.holes)
+                .chain(&mut surface.special_curves)
+                .flatten()
+            {
+                segment.curve = self.add_curve_2d(
+                    &mut free_form,
+                    &mut curve_2d_map,
+                    &mut parameter_map,
+                    segment.curve,
+                )?;
+            }
+            free_form.surfaces.push(surface);
+        }
+
+        if !weights.is_empty() {
+            free_form.weights = vec![T::one(); free_form.positions.len() / 3];
+            for (&index, &copied) in &pos_map {
+                free_form.weights[copied as usize] = weights[index as usize];
+            }
+        }
+
+        Ok(free_form)
+    }
+
+    /// Add a `curv2` of the file to the free-form geometry of an object unless
+    /// it is already part of it, returning its index there.
+    fn add_curve_2d(
+        &self,
+        free_form: &mut FreeForm<T>,
+        curve_2d_map: &mut HashMap<usize, usize>,
+        parameter_map: &mut HashMap<u32, u32>,
+        curve: usize,
+    ) -> Result<usize, LoadError> {
+        if let Some(&index) = curve_2d_map.get(&curve) {
+            return Ok(index);
+        }
+
+        let mut curve_2d = self
+            .curves_2d
+            .get(curve)
+            .ok_or(LoadErrorKind::FreeFormIndexOutOfBounds)?
+            .clone();
+        for index in &mut curve_2d.control_points {
+            *index = copy(
+                parameter_map,
+                *index,
+                &self.parameter_vertices,
+                [3, 3],
+                &mut free_form.parameter_vertices,
+            )?;
+        }
+
+        let index = free_form.curves_2d.len();
+        free_form.curves_2d.push(curve_2d);
+        curve_2d_map.insert(curve, index);
+        Ok(index)
+    }
+}
+
+/// Parse a single number.
+fn parse<V: std::str::FromStr>(word: &str) -> Result<V, LoadError> {
+    word.parse()
+        .map_err(|_| LoadErrorKind::FreeFormParseError.into())
+}
+
+/// Parse the next word as a number.
+fn next<V: std::str::FromStr>(words: &mut SplitWhitespace) -> Result<V, LoadError> {
+    parse(words.next().unwrap_or_default())
+}
+
+/// Turn a 1-based, possibly relative index into a 0-based one, given the
+/// number of elements defined so far.
+fn resolve(word: &str, len: usize) -> Result<u32, LoadError> {
+    let index: isize = parse(word)?;
+    let index = if index < 0 {
+        len as isize + index
+    } else {
+        index - 1
+    };
+    if index < 0 {
+        return Err(LoadErrorKind::FreeFormIndexOutOfBounds.into());
+    }
+    Ok(index as u32)
+}
+
+/// Copy the first `n` components of the element at `index` of `source`, where
+/// each has `stride` components, to `target` unless already done, returning
+/// its index in `target`.
+fn copy<T: Copy>(
+    map: &mut HashMap<u32, u32>,
+    index: u32,
+    source: &[T],
+    [stride, n]: [usize; 2],
+    target: &mut Vec<T>,
+) -> Result<u32, LoadError> {
+    if let Some(&copied) = map.get(&index) {
+        return Ok(copied);
+    }
+    let start = index as usize * stride;
+    let element = source
+        .get(start..start + n)
+        .ok_or(LoadErrorKind::FreeFormIndexOutOfBounds)?;
+    let copied = (target.len() / n) as u32;
+    target.extend_from_slice(element);
+    map.insert(index, copied);
+    Ok(copied)
+}
@@ -9,9 +9,16 @@
 //!
 //! Meshes can be triangulated on the fly or left as-is.
 //!
-//! Only polygons that are trivially convertible to triangle fans are supported.
-//! Arbitrary polygons may not behave as expected. The best solution would be to
-//! convert your mesh to solely consist of triangles in your modeling software.
+//! By default polygons are split into triangle fans. This is fast but only works
+//! for polygons that are trivially convertible to triangle fans. Concave and
+//! non-planar polygons can be handled by selecting
+//! [`TriangulationMethod::EarClipping`] via
+//! [`triangulation_method`](LoadOptions::triangulation_method).
+//!
+//! Lines (`l`) are loaded as faces with two or more vertices and points (`p`)
+//! are skipped. With
+//! [`separate_lines_and_points`](LoadOptions::separate_lines_and_points) set,
+//! both are kept in their own index buffers instead and never triangulated.
 //!
 //! ## Optional – Normals & Texture Coordinates
 //!
@@ -21,6 +28,24 @@
 //! If no normals or texture coordinates are found then the corresponding
 //! [`Vec`](Mesh::normals)s for the [`Mesh`] will be empty.
 //!
+//! Missing normals can be generated during loading via the
+//! [`generate_normals`](LoadOptions::generate_normals) option or afterwards
+//! with [`Mesh::compute_normals()`].
+//!
+//! A `v` statement may also carry a weight (`v x y z w`), an RGB color
+//! (`v x y z r g b`) or an RGBA one. These end up in [`Mesh::weights`] and
+//! [`Mesh::vertex_color`], one per vertex as soon as any vertex has one. A `#`
+//! comment ends the values. Any other number of values is a
+//! [`VertexColorParseError`](LoadErrorKind::VertexColorParseError), or ignored
+//! with a warning when [loading leniently](LoadOptions::lenient). Colors are
+//! stored as RGBA in the precision of the mesh. The per-vertex colors and
+//! masks of ZBrush `#MRGB` comment blocks are decoded into
+//! [`Mesh::vertex_color`] and [`Mesh::vertex_mask`].
+//!
+//! Vertices that no face refers to are dropped unless the
+//! [`keep_unreferenced_vertices`](LoadOptions::keep_unreferenced_vertices)
+//! option is set. Set it to load point clouds made up only of `v` statements.
+//!
 //! ## Flat Data
 //!
 //! Values are stored packed as [`T`]s in flat `Vec`s, where T .
@@ -28,6 +53,9 @@
 //! For example, the `positions` member of a `Mesh` will contain `[x, y, z, x,
 //! y, z, ...]` which you can then use however you like.
 //!
+//! Texture coordinates have one to three components (`vt u`, `vt u v` or
+//! `vt u v w`), as recorded in [`Mesh::texcoord_dimension`].
+//!
 //! ## Indices
 //!
 //! Indices are also loaded and may re-use vertices already existing in the
@@ -43,12 +71,78 @@
 //! This also guarantees that the topology of the `Mesh` does *not* change when
 //! either of the latter are specified *per vertex per face*.
 //!
+//! [`load_obj_with_attributes()`] and [`load_obj_buf_with_attributes()`]
+//! return the vertex data of the whole file once, in file order, as
+//! [`Attributes`] instead of copying it into each `Mesh`. The indices of every
+//! mesh then refer directly to the `v`, `vt` and `vn` statements of the file.
+//!
 //! ## Materials
 //!
 //! Standard `MTL` attributes are supported too. Any unrecognized parameters
 //! will be stored in a `HashMap` containing the key-value pairs of the
 //! unrecognized parameter and its value.
 //!
+//! This includes the transmission filter `Tf`, `Tr` as the inverse of `d`
+//! (which wins if both are given), `d -halo`, `sharpness`, `map_aat` and the
+//! `disp`, `decal` and `refl` maps, the latter as a sphere or [`CubeMap`].
+//! Colors given as CIE XYZ values or spectral curves, such as
+//! `Kd xyz x y z` or `Ka spectral file.rfl factor`, are kept as a
+//! [`ColorSource`], which converts XYZ values to linear RGB on request.
+//!
+//! The PBR extension of the format (`Pr`, `Pm`, `Ps`, `Pc`, `Pcr`, `Ke`,
+//! `aniso`, `anisor`, `norm` and the matching `map_*` textures) is parsed into
+//! typed fields of [`Material`].
+//!
+//! Texture options such as `-bm`, `-o`/`-s`/`-t` or `-clamp` are parsed into
+//! [`TextureOptions`] alongside the file name of each [`Texture`].
+//! A [`TextureResolver`] finds the files of textures: relative to the `MTL`
+//! file of the material or in fallback directories, with Windows separators
+//! normalized. It also reports the textures that do not exist on disk.
+//!
+//! [`load_obj_with_loader()`] loads the libraries referenced by `mtllib`
+//! statements with a [`MaterialLoader`]: [`SearchPathLoader`] looks through a
+//! list of directories, matching names case-insensitively if need be,
+//! [`CachingLoader`] parses a library shared by many files once and
+//! [`MemoryLoader`] serves libraries held in memory.
+//!
+//! Each material used within an object normally results in a separate
+//! [`Model`]. With [`LoadOptions::per_face_materials`] set there is one
+//! `Model` per object instead, which records the material of every face.
+//! [`Mesh::material_ranges()`] turns these into runs of faces suitable for
+//! draw calls.
+//!
+//! ## Free-Form Geometry
+//!
+//! Free-form curves and surfaces – Bezier, B-splines and NURBS with their
+//! parameter space vertices, knot vectors and trimming loops – are loaded into
+//! the [`free_form`](Model::free_form) member of each [`Model`].
+//!
+//! Any statement but a comment may continue on the next line after a
+//! backslash, not just free-form ones, see [`load_obj_buf()`].
+//!
+//! [`FreeForm::tessellate()`] turns them into polylines and triangles, honoring
+//! trimming loops and the `ctech`/`stech` approximation techniques of the
+//! file. With the [`tessellate`](LoadOptions::tessellate) load option this
+//! happens while loading, resulting in regular meshes.
+//!
+//! ## Malformed Files
+//!
+//! By default the first malformed statement aborts loading. With
+//! [`LoadOptions::lenient`] set bad vertex data is repaired and other broken
+//! statements are skipped instead. [`load_obj_with_diagnostics()`] and
+//! [`load_obj_buf_with_diagnostics()`] return every such problem with its line
+//! number and kind.
+//!
+//! ## Loading from Memory
+//!
+//! [`load_obj_bytes()`] and [`load_mtl_bytes()`] parse files that are already
+//! in memory, e.g. memory-mapped ones, in place without copying each line.
+//!
+//! ## Saving
+//!
+//! Models and materials can be written back to `OBJ` and `MTL` files with
+//! [`save_obj()`]/[`save_obj_buf()`] and [`save_mtl()`]/[`save_mtl_buf()`].
+//!
 //! ## Example
 //!
 //! In this simple example we load the classic Cornell Box model that only
@@ -171,7 +265,7 @@
 //! * [`ahash`](https://crates.io/crates/ahash) – On by default. Use [`AHashMap`](https://docs.rs/ahash/latest/ahash/struct.AHashMap.html)
 //!   for hashing when reading files and merging vertices. To disable and use
 //!   the potentially slower [`FnvHashMap`](https://docs.rs/fnv) instead, unset default
-//! features in `Cargo.toml`:
+//!   features in `Cargo.toml`:
 //!
 //!   ```toml
 //!   [dependencies.tobj]
@@ -188,8 +282,18 @@
 //!   the normal- and texture coordinate indices.
 //!
 //! * [`async`](load_obj_buf_async) – Adds support for async loading of obj
-//!   files from a buffer, with an async material loader. Useful in environments
-//!   that do not support blocking IO (e.g. WebAssembly).
+//!   files from any [Tokio](https://crates.io/crates/tokio) `AsyncBufRead`,
+//!   with an async material loader, and of files via [`load_obj_async()`].
+//!   Useful in environments that do not support blocking IO (e.g.
+//!   WebAssembly).
+//!
+//! * [`parallel`](load_obj_bytes_parallel) – Adds support for loading `OBJ`
+//!   files held in memory using multiple threads via
+//!   [`rayon`](https://crates.io/crates/rayon).
+//!
+//! * [`image`](TextureResolver::decode) – Adds support for decoding the PNG
+//!   and JPEG textures referenced by materials into RGBA buffers via
+//!   [`image`](https://crates.io/crates/image), each file once.
 #![cfg_attr(feature = "merging", allow(incomplete_features))]
 #![cfg_attr(feature = "merging", feature(generic_const_exprs))]
 #![allow(clippy::derive_partial_eq_without_eq)]
@@ -197,17 +301,59 @@
 #[cfg(test)]
 mod tests;
 
+mod color;
+#[cfg(feature = "image")]
+mod decode;
+mod freeform;
+mod loader;
+mod normals;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod tessellation;
+mod texture;
+mod triangulation;
+mod writer;
+
+pub use color::ColorSource;
+#[cfg(feature = "image")]
+pub use decode::{DecodedTexture, DecodedTextures, MaterialTextures, TextureDecodeError};
+pub use freeform::{
+    Basis, Curve, Curve2D, CurveTechnique, FreeForm, Surface, SurfaceTechnique, TrimCurve,
+};
+pub use loader::{CachingLoader, MaterialLoader, MemoryLoader, SearchPathLoader};
+pub use normals::{NormalMode, NormalOptions};
+#[cfg(feature = "parallel")]
+pub use parallel::{load_obj_bytes_parallel, load_obj_bytes_parallel_with_diagnostics};
+pub use tessellation::TessellationOptions;
+pub use texture::{
+    CubeMap, MissingTexture, Texture, TextureChannel, TextureOptions, TextureResolver,
+};
+pub use triangulation::TriangulationMethod;
+pub use writer::{save_mtl, save_mtl_buf, save_obj, save_obj_buf};
+
+use freeform::FreeFormParser;
+
 use std::{
     error::Error,
     fmt,
     fs::File,
-    io::{prelude::*, BufReader},
-    path::Path,
-    str::{FromStr, SplitWhitespace},
+    io::{self, prelude::*, BufReader},
+    ops::Range,
+    path::{Path, PathBuf},
+    slice,
+    str::{self, FromStr, SplitWhitespace},
+    sync::Arc,
+};
+
+#[cfg(feature = "async")]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
 };
 
 #[cfg(feature = "async")]
-use std::future::Future;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
 
 #[cfg(feature = "ahash")]
 type HashMap<K, V> = ahash::AHashMap<K, V>;
@@ -247,8 +393,15 @@ pub const GPU_LOAD_OPTIONS: LoadOptions = LoadOptions {
     reorder_data: false,
     single_index: true,
     triangulate: true,
+    triangulation_method: TriangulationMethod::Fan,
     ignore_points: true,
     ignore_lines: true,
+    generate_normals: None,
+    per_face_materials: false,
+    keep_unreferenced_vertices: false,
+    separate_lines_and_points: false,
+    tessellate: None,
+    lenient: false,
 };
 
 /// Typical [`LoadOptions`] for using meshes with an offline rendeder.
@@ -264,15 +417,29 @@ pub const OFFLINE_RENDERING_LOAD_OPTIONS: LoadOptions = LoadOptions {
     reorder_data: true,
     single_index: false,
     triangulate: false,
+    triangulation_method: TriangulationMethod::Fan,
     ignore_points: true,
     ignore_lines: true,
+    generate_normals: None,
+    per_face_materials: false,
+    keep_unreferenced_vertices: false,
+    separate_lines_and_points: false,
+    tessellate: None,
+    lenient: false,
 };
 
 /// A simplified trait for parseable values;
 pub trait ParseableV:
-    Sized + num::Num + FromStr + Copy + core::fmt::Debug + core::fmt::Display
+    Sized + num::Num + num::NumCast + FromStr + Copy + core::fmt::Debug + core::fmt::Display
 {
     type Hasheable: Copy + std::hash::Hash + std::cmp::Eq;
+
+    /// Whether the type holds whole numbers only. Color bytes of ZBrush
+    /// `#MRGB` blocks are then kept as `0` to `255` instead of being scaled to
+    /// `0` to `1`.
+    fn is_integer() -> bool {
+        false
+    }
 }
 
 impl ParseableV for f64 {
@@ -284,27 +451,59 @@ impl ParseableV for f32 {
 
 impl ParseableV for i64 {
     type Hasheable = i64;
+
+    fn is_integer() -> bool {
+        true
+    }
 }
 impl ParseableV for u64 {
     type Hasheable = u64;
+
+    fn is_integer() -> bool {
+        true
+    }
 }
 impl ParseableV for i32 {
     type Hasheable = i32;
+
+    fn is_integer() -> bool {
+        true
+    }
 }
 impl ParseableV for u32 {
     type Hasheable = u32;
+
+    fn is_integer() -> bool {
+        true
+    }
 }
 impl ParseableV for i16 {
     type Hasheable = i16;
+
+    fn is_integer() -> bool {
+        true
+    }
 }
 impl ParseableV for u16 {
     type Hasheable = u16;
+
+    fn is_integer() -> bool {
+        true
+    }
 }
 impl ParseableV for i8 {
     type Hasheable = i8;
+
+    fn is_integer() -> bool {
+        true
+    }
 }
 impl ParseableV for u8 {
     type Hasheable = u8;
+
+    fn is_integer() -> bool {
+        true
+    }
 }
 /// A mesh made up of triangles loaded from some `OBJ` file.
 ///
@@ -359,24 +558,48 @@ pub struct Mesh<T: ParseableV> {
     /// Flattened 3 component floating point vectors, storing positions of
     /// vertices in the mesh.
     pub positions: Vec<T>,
-    /// Flattened 3 component floating point vectors, storing the color
-    /// associated with the vertices in the mesh.
+    /// Flattened 4 component RGBA vectors, storing the color associated with
+    /// the vertices in the mesh.
     ///
     /// Most meshes do not have vertex colors. If no vertex colors are specified
-    /// this will be empty.
-    pub vertex_color: Vec<f32>,
+    /// this will be empty. Otherwise there is a color for every vertex, opaque
+    /// white for those whose `v` statement specifies none. RGB colors have an
+    /// alpha of `1`.
+    pub vertex_color: Vec<T>,
+    /// The weights `w` of the vertices in the mesh, one per position.
+    ///
+    /// Empty unless a `v` statement specifies a weight. Vertices without one
+    /// have a weight of `1`.
+    pub weights: Vec<T>,
+    /// The masks of the vertices in the mesh, one per position, scaled from
+    /// `0..=255` to `0..=1`.
+    ///
+    /// Empty unless the file has ZBrush `#MRGB` blocks, which store a mask
+    /// and a color for each vertex. Vertices without one have a mask of `0`.
+    /// Meshes of integer precision keep the masks and colors of `#MRGB` blocks
+    /// in `0..=255`.
+    pub vertex_mask: Vec<T>,
     /// Flattened 3 component floating point vectors, storing normals of
     /// vertices in the mesh.
     ///
     /// Not all meshes have normals. If no normals are specified this will
     /// be empty.
     pub normals: Vec<T>,
-    /// Flattened 2 component floating point vectors, storing texture
-    /// coordinates of vertices in the mesh.
+    /// Flattened [`texcoord_dimension`](Mesh::texcoord_dimension) component
+    /// floating point vectors, storing texture coordinates of vertices in the
+    /// mesh.
     ///
     /// Not all meshes have texture coordinates. If no texture coordinates are
     /// specified this will be empty.
     pub texcoords: Vec<T>,
+    /// The number of components of each texture coordinate in
+    /// [`texcoords`](Mesh::texcoords), `1` to `3`.
+    ///
+    /// This is the largest number of components of the `vt` statements read
+    /// before the mesh was complete, usually `2`. Texture coordinates with
+    /// fewer components are padded with `0`. Also `2` if there are no texture
+    /// coordinates at all.
+    pub texcoord_dimension: usize,
     /// Indices for vertices of each face. If loaded with
     /// [`triangulate`](LoadOptions::triangulate) set to `true` each face in the
     /// mesh is a triangle.
@@ -398,6 +621,47 @@ pub struct Mesh<T: ParseableV> {
     /// through the `face_arities` until reaching the desired face, accumulating
     /// the number of vertices used so far.
     pub face_arities: Vec<u32>,
+    /// The smoothing group of each face, as set by the `s` statement preceding
+    /// it in the `OBJ` file. A value of `0` means the face is not part of any
+    /// smoothing group (`s off`).
+    ///
+    /// If loaded with [`triangulate`](LoadOptions::triangulate) set to `true`
+    /// there is one entry per *triangle*, otherwise one entry per face.
+    ///
+    /// *Empty* if the faces of the mesh are not preceded by any `s` statement.
+    /// Faces in group `0` are flat shaded when
+    /// [generating normals](LoadOptions::generate_normals), while a mesh
+    /// without smoothing groups is smoothed all over.
+    pub smoothing_groups: Vec<u32>,
+    /// The material id of each face, as set by the `usemtl` statement
+    /// preceding it in the `OBJ` file. Only filled in if loaded with
+    /// [`per_face_materials`](LoadOptions::per_face_materials) set to `true`.
+    ///
+    /// If loaded with [`triangulate`](LoadOptions::triangulate) set to `true`
+    /// there is one entry per *triangle*, otherwise one entry per face.
+    ///
+    /// See [`material_ranges()`](Mesh::material_ranges) for turning these into
+    /// draw calls.
+    pub face_materials: Vec<Option<usize>>,
+    /// Indices of the vertices of all polylines, one after the other. Only
+    /// filled in if loaded with
+    /// [`separate_lines_and_points`](LoadOptions::separate_lines_and_points)
+    /// set to `true`.
+    ///
+    /// Like [`indices`](Mesh::indices) they index into the
+    /// [`positions`](Mesh::positions), and with
+    /// [`single_index`](LoadOptions::single_index) set to `true` into all of
+    /// the data in the mesh. Polylines are never triangulated.
+    pub line_indices: Vec<u32>,
+    /// The number of vertices of each polyline in
+    /// [`line_indices`](Mesh::line_indices).
+    pub line_arities: Vec<u32>,
+    /// Indices of the vertices of all points. Only filled in if loaded with
+    /// [`separate_lines_and_points`](LoadOptions::separate_lines_and_points)
+    /// set to `true`.
+    ///
+    /// Index into the same data as [`line_indices`](Mesh::line_indices).
+    pub point_indices: Vec<u32>,
     /// The indices for vertex colors. Only present when the
     /// [`merging`](LoadOptions::merge_identical_points) feature is enabled, and
     /// empty unless the corresponding load option is set to `true`.
@@ -411,6 +675,9 @@ pub struct Mesh<T: ParseableV> {
     pub normal_indices: Vec<u32>,
     /// Optional material id associated with this mesh. The material id indexes
     /// into the Vec of Materials loaded from the associated `MTL` file
+    ///
+    /// With [`per_face_materials`](LoadOptions::per_face_materials) set to
+    /// `true` this is only set if all faces of the mesh use the same material.
     pub material_id: Option<usize>,
 }
 
@@ -420,10 +687,18 @@ impl<T: ParseableV> Default for Mesh<T> {
         Self {
             positions: Vec::new(),
             vertex_color: Vec::new(),
+            weights: Vec::new(),
+            vertex_mask: Vec::new(),
             normals: Vec::new(),
             texcoords: Vec::new(),
+            texcoord_dimension: 2,
             indices: Vec::new(),
             face_arities: Vec::new(),
+            smoothing_groups: Vec::new(),
+            face_materials: Vec::new(),
+            line_indices: Vec::new(),
+            line_arities: Vec::new(),
+            point_indices: Vec::new(),
             #[cfg(feature = "merging")]
             vertex_color_indices: Vec::new(),
             normal_indices: Vec::new(),
@@ -433,6 +708,69 @@ impl<T: ParseableV> Default for Mesh<T> {
     }
 }
 
+impl<T: ParseableV> Mesh<T> {
+    /// Split the mesh into runs of consecutive faces that use the same
+    /// material, e.g. to issue one draw call per run.
+    ///
+    /// Returns the material id of each run with the range of
+    /// [`indices`](Mesh::indices) it covers. Meshes without
+    /// [`face_materials`](Mesh::face_materials) form a single run using
+    /// [`material_id`](Mesh::material_id).
+    pub fn material_ranges(&self) -> Vec<(Option<usize>, Range<usize>)> {
+        if self.face_materials.is_empty() {
+            return vec![(self.material_id, 0..self.indices.len())];
+        }
+
+        let mut ranges: Vec<(Option<usize>, Range<usize>)> = Vec::new();
+        let mut start = 0;
+        for (face, &material_id) in self.face_materials.iter().enumerate() {
+            let end = start
+                + self
+                    .face_arities
+                    .get(face)
+                    .map_or(3, |&arity| arity as usize);
+            match ranges.last_mut() {
+                Some((last_id, range)) if *last_id == material_id => range.end = end,
+                _ => ranges.push((material_id, start..end)),
+            }
+            start = end;
+        }
+        ranges
+    }
+}
+
+/// The vertex data of an `OBJ` file, in the order it is specified in the file.
+///
+/// Returned by [`load_obj_with_attributes()`] and
+/// [`load_obj_buf_with_attributes()`]. The [`Mesh`]es loaded alongside do not
+/// hold any vertex data of their own. Their [`indices`](Mesh::indices),
+/// [`texcoord_indices`](Mesh::texcoord_indices) and
+/// [`normal_indices`](Mesh::normal_indices) index into these `Vec`s instead,
+/// e.g. index `i` refers to the `i + 1`-th `v` statement of the file.
+#[derive(Debug, Clone)]
+pub struct Attributes<T: ParseableV> {
+    /// Flattened 3 component vectors, storing the positions of all `v`
+    /// statements.
+    pub positions: Vec<T>,
+    /// Flattened 4 component RGBA vectors, storing the colors of all `v`
+    /// statements.
+    ///
+    /// Empty if no vertex colors are specified, see [`Mesh::vertex_color`].
+    pub vertex_color: Vec<T>,
+    /// The weights of all `v` statements, see [`Mesh::weights`].
+    pub weights: Vec<T>,
+    /// The masks of all `v` statements, see [`Mesh::vertex_mask`].
+    pub vertex_mask: Vec<T>,
+    /// Flattened 3 component vectors, storing all `vn` statements.
+    pub normals: Vec<T>,
+    /// Flattened [`texcoord_dimension`](Attributes::texcoord_dimension)
+    /// component vectors, storing all `vt` statements.
+    pub texcoords: Vec<T>,
+    /// The number of components of each texture coordinate, see
+    /// [`Mesh::texcoord_dimension`].
+    pub texcoord_dimension: usize,
+}
+
 /// Options for processing the mesh during loading.
 ///
 /// Passed to [`load_obj()`], [`load_obj_buf()`] and [`load_obj_buf_async()`].
@@ -466,7 +804,7 @@ pub struct LoadOptions {
     ///
     /// * This flag is *mutually exclusive* with
     ///   [`single_index`](LoadOptions::single_index) and will lead to a
-    ///   [`InvalidLoadOptionConfig`](LoadError::InvalidLoadOptionConfig) error
+    ///   [`InvalidLoadOptionConfig`](LoadErrorKind::InvalidLoadOptionConfig) error
     ///   if both are set to `true`.
     ///
     /// * If adjacent faces share vertices that have separate `indices` but the
@@ -482,7 +820,7 @@ pub struct LoadOptions {
     ///
     /// * This flag is *mutually exclusive* with
     ///   [`single_index`](LoadOptions::single_index) and will lead to an
-    ///   [`InvalidLoadOptionConfig`](LoadError::InvalidLoadOptionConfig) error
+    ///   [`InvalidLoadOptionConfig`](LoadErrorKind::InvalidLoadOptionConfig) error
     ///   if both are set to `true`.
     ///
     /// * The resulting [`Mesh`]'s `normal_indices` and/or `texcoord_indices`
@@ -516,7 +854,7 @@ pub struct LoadOptions {
     /// * This flag is *mutually exclusive* with both
     ///   [`merge_identical_points`](LoadOptions::merge_identical_points) and
     ///   [`reorder_data`](LoadOptions::reorder_data) resp. and will lead to a
-    ///   [`InvalidLoadOptionConfig`](LoadError::InvalidLoadOptionConfig) error
+    ///   [`InvalidLoadOptionConfig`](LoadErrorKind::InvalidLoadOptionConfig) error
     ///   if both it and either of the two other are set to `true`.
     ///
     /// * Vertices may get duplicated to match the granularity
@@ -537,11 +875,17 @@ pub struct LoadOptions {
     /// * The resulting `Mesh`'s [`face_arities`](Mesh::face_arities) will be
     ///   empty as all faces are guranteed to have arity `3`.
     ///
-    /// * Only polygons that are trivially convertible to triangle fans are
-    ///   supported. Arbitrary polygons may not behave as expected. The best
-    ///   solution would be to convert your mesh to solely consist of triangles
-    ///   in your modeling software.
+    /// * Polygons are split according to
+    ///   [`triangulation_method`](LoadOptions::triangulation_method). With the
+    ///   default, [`Fan`](TriangulationMethod::Fan), only polygons that are
+    ///   trivially convertible to triangle fans are supported.
     pub triangulate: bool,
+    /// The algorithm used to split polygons into triangles.
+    ///
+    /// Only has an effect if [`triangulate`](LoadOptions::triangulate) is set
+    /// to `true`. Use [`EarClipping`](TriangulationMethod::EarClipping) for
+    /// meshes containing concave polygons.
+    pub triangulation_method: TriangulationMethod,
     /// Ignore faces containing only a single vertex (points).
     ///
     /// This is usually what you want if you do *not* intend to make special use
@@ -558,6 +902,98 @@ pub struct LoadOptions {
     /// Polygon meshes that contains faces with two vertices only usually do so
     /// because of bad topology.
     pub ignore_lines: bool,
+    /// Generate normals for meshes that do not have any.
+    ///
+    /// * If the `OBJ` file specifies normals for a mesh, they are kept as they
+    ///   are.
+    ///
+    /// * With [`single_index`](LoadOptions::single_index) set to `true` this
+    ///   calls [`Mesh::compute_normals()`], vertices may get duplicated where
+    ///   faces need different normals.
+    ///
+    /// * Otherwise this calls [`Mesh::compute_normals_multi_index()`] and the
+    ///   [`normal_indices`](Mesh::normal_indices) are filled in.
+    pub generate_normals: Option<NormalOptions>,
+    /// Keep a single mesh per object or group, recording the material of each
+    /// face in [`Mesh::face_materials`].
+    ///
+    /// By default every `usemtl` statement within an object starts a new
+    /// [`Model`] with the same name, duplicating any vertices shared with the
+    /// faces before it.
+    pub per_face_materials: bool,
+    /// Keep vertices that no face refers to.
+    ///
+    /// By default a [`Mesh`] only contains the vertices used by its faces, so
+    /// files consisting only of `v` statements, such as point clouds, load
+    /// without any positions.
+    ///
+    /// * Every vertex not used by any face is appended to the
+    ///   [`positions`](Mesh::positions) and other per-vertex data of the mesh
+    ///   of the object it is defined in. No indices refer to it. Normals and
+    ///   texture coordinates that share the indices of the positions are
+    ///   zero for it, including [generated](LoadOptions::generate_normals)
+    ///   ones.
+    ///
+    /// * Objects without faces but with vertices result in a [`Model`], too.
+    ///
+    /// * Vertices of faces skipped due to
+    ///   [`ignore_points`](LoadOptions::ignore_points) or
+    ///   [`ignore_lines`](LoadOptions::ignore_lines) count as unused.
+    ///
+    /// Has no effect on [`load_obj_with_attributes()`], which keeps all
+    /// vertices anyway.
+    pub keep_unreferenced_vertices: bool,
+    /// Keep polylines and points apart from the faces of a mesh.
+    ///
+    /// By default `l` statements are loaded as faces with the arity of their
+    /// vertex count – degenerate triangles if
+    /// [`triangulate`](LoadOptions::triangulate) is set – and `p` statements
+    /// are skipped.
+    ///
+    /// * `l` statements are loaded into [`Mesh::line_indices`] and
+    ///   [`Mesh::line_arities`], `p` statements into
+    ///   [`Mesh::point_indices`]. Texture coordinates and normals of their
+    ///   vertices are not kept in separate indices.
+    ///
+    /// * [`indices`](Mesh::indices) contains the faces specified via `f`
+    ///   statements only.
+    ///
+    /// * [`ignore_lines`](LoadOptions::ignore_lines) and
+    ///   [`ignore_points`](LoadOptions::ignore_points) skip `l` and `p`
+    ///   statements, resp.
+    pub separate_lines_and_points: bool,
+    /// Tessellate free-form curves and surfaces.
+    ///
+    /// The [`free_form`](Model::free_form) geometry of each object is turned
+    /// into polylines and triangles via [`FreeForm::tessellate()`].
+    ///
+    /// * The result becomes the mesh of the object's [`Model`]. If the object
+    ///   has a mesh of its own already, a separate [`Model`] with the same
+    ///   name is added after it instead.
+    ///
+    /// * The mesh is laid out like the other meshes according to
+    ///   [`single_index`](LoadOptions::single_index),
+    ///   [`generate_normals`](LoadOptions::generate_normals) and
+    ///   [`per_face_materials`](LoadOptions::per_face_materials). Curves are
+    ///   always kept in [`Mesh::line_indices`].
+    ///
+    /// * The [`free_form`](Model::free_form) geometry itself is kept in the
+    ///   [`Model`] with the tessellated mesh.
+    ///
+    /// Not supported by [`load_obj_with_attributes()`].
+    pub tessellate: Option<TessellationOptions>,
+    /// Keep loading when encountering malformed statements.
+    ///
+    /// * Vertex positions, texture coordinates and normals with missing or
+    ///   unparsable components are repaired by substituting `0` for them.
+    ///
+    /// * Other malformed statements and faces referring to vertices that do
+    ///   not exist are skipped.
+    ///
+    /// Each problem is reported as a [`Diagnostic`]. Use
+    /// [`load_obj_with_diagnostics()`]/[`load_obj_buf_with_diagnostics()`] to
+    /// get hold of them. Errors reading the input itself still abort loading.
+    pub lenient: bool,
 }
 
 impl LoadOptions {
@@ -594,6 +1030,8 @@ impl LoadOptions {
 pub struct Model<T: ParseableV> {
     /// [`Mesh`] used by the model containing its geometry.
     pub mesh: Mesh<T>,
+    /// Free-form curves and surfaces of the model. Empty for most files.
+    pub free_form: FreeForm<T>,
     /// Name assigned to this `Mesh`.
     pub name: String,
 }
@@ -604,7 +1042,11 @@ where
 {
     /// Create a new model, associating a name with a [`Mesh`].
     pub fn new(mesh: Mesh<T>, name: String) -> Model<T> {
-        Model { mesh, name }
+        Model {
+            mesh,
+            free_form: FreeForm::default(),
+            name,
+        }
     }
 }
 
@@ -616,6 +1058,7 @@ where
 /// the value set for it.
 ///
 /// No path is pre-pended to the texture file names specified in the `MTL` file.
+/// Use a [`TextureResolver`] to find the files they refer to.
 #[derive(Clone, Debug)]
 pub struct Material {
     /// Material name as specified in the `MTL` file.
@@ -626,36 +1069,132 @@ pub struct Material {
     pub diffuse: [f32; 3],
     /// Specular color of the material.
     pub specular: [f32; 3],
+    /// The ambient color if given as XYZ values or a spectral curve instead of
+    /// RGB, which leaves [`ambient`](Material::ambient) unchanged.
+    ///
+    /// Use [`ColorSource::to_rgb()`] to convert it.
+    pub ambient_source: Option<ColorSource>,
+    /// The diffuse color if given as XYZ values or a spectral curve, see
+    /// [`ambient_source`](Material::ambient_source).
+    pub diffuse_source: Option<ColorSource>,
+    /// The specular color if given as XYZ values or a spectral curve, see
+    /// [`ambient_source`](Material::ambient_source).
+    pub specular_source: Option<ColorSource>,
     /// Material shininess attribute. Also called `glossiness`.
     pub shininess: f32,
     /// Dissolve attribute is the alpha term for the material. Referred to as
     /// dissolve since that's what the `MTL` file format docs refer to it as.
+    ///
+    /// Also set by `Tr`, the transparency `1 - d`, unless the material has a
+    /// `d` statement, which takes precedence.
     pub dissolve: f32,
+    /// Whether the dissolve depends on the surface orientation relative to
+    /// the viewer, `d -halo`.
+    pub dissolve_halo: bool,
+    /// Transmission filter of the material, `Tf`. Only the light of these
+    /// colors passes through the object.
+    pub transmission_filter: Option<[f32; 3]>,
+    /// The transmission filter if given as XYZ values or a spectral curve, see
+    /// [`ambient_source`](Material::ambient_source).
+    pub transmission_filter_source: Option<ColorSource>,
+    /// Sharpness of the reflections of a reflection map, `sharpness`.
+    pub sharpness: Option<f32>,
     /// Optical density also known as index of refraction. Called
     /// `optical_density` in the `MTL` specc. Takes on a value between 0.001
     /// and 10.0. 1.0 means light does not bend as it passes through
     /// the object.
     pub optical_density: f32,
-    /// Name of the ambient texture file for the material.
-    pub ambient_texture: String,
-    /// Name of the diffuse texture file for the material.
-    pub diffuse_texture: String,
-    /// Name of the specular texture file for the material.
-    pub specular_texture: String,
-    /// Name of the normal map texture file for the material.
-    pub normal_texture: String,
-    /// Name of the shininess map texture file for the material.
-    pub shininess_texture: String,
-    /// Name of the alpha/opacity map texture file for the material.
+    /// Ambient texture of the material.
+    pub ambient_texture: Texture,
+    /// Diffuse texture of the material.
+    pub diffuse_texture: Texture,
+    /// Specular texture of the material.
+    pub specular_texture: Texture,
+    /// Normal map texture of the material, `map_Bump` or `bump`.
+    pub normal_texture: Texture,
+    /// Shininess map texture of the material.
+    pub shininess_texture: Texture,
+    /// Alpha/opacity map texture of the material.
     ///
     /// Referred to as `dissolve` to match the `MTL` file format specification.
-    pub dissolve_texture: String,
+    pub dissolve_texture: Texture,
+    /// Displacement map texture of the material, `disp`.
+    pub displacement_texture: Texture,
+    /// Decal texture of the material, `decal`. Selectively replaces the
+    /// material color with the texture color.
+    pub decal_texture: Texture,
+    /// Spherical reflection map of the material, `refl -type sphere`.
+    pub reflection_texture: Texture,
+    /// Cube reflection map of the material, `refl -type cube_*`.
+    pub reflection_cube: CubeMap,
+    /// Whether textures are anti-aliased, `map_aat on`.
+    pub texture_anti_aliasing: bool,
     /// The illumnination model to use for this material. The different
     /// illumnination models are specified in the [`MTL` spec](http://paulbourke.net/dataformats/mtl/).
     pub illumination_model: Option<u8>,
+    /// Roughness of the material, `Pr` in the PBR extension of the `MTL`
+    /// format.
+    pub roughness: Option<f32>,
+    /// Metallicity of the material, `Pm` in the PBR extension.
+    pub metallic: Option<f32>,
+    /// Sheen of the material, `Ps` in the PBR extension.
+    pub sheen: Option<f32>,
+    /// Thickness of the clearcoat layer, `Pc` in the PBR extension.
+    pub clearcoat_thickness: Option<f32>,
+    /// Roughness of the clearcoat layer, `Pcr` in the PBR extension.
+    pub clearcoat_roughness: Option<f32>,
+    /// Emissive color of the material, `Ke` in the PBR extension.
+    pub emissive: Option<[f32; 3]>,
+    /// The emissive color if given as XYZ values or a spectral curve, see
+    /// [`ambient_source`](Material::ambient_source).
+    pub emissive_source: Option<ColorSource>,
+    /// Anisotropy of the material, `aniso` in the PBR extension.
+    pub anisotropy: Option<f32>,
+    /// Rotation of the anisotropy, `anisor` in the PBR extension.
+    pub anisotropy_rotation: Option<f32>,
+    /// Roughness map texture of the material.
+    pub roughness_texture: Texture,
+    /// Metallic map texture of the material.
+    pub metallic_texture: Texture,
+    /// Sheen map texture of the material.
+    pub sheen_texture: Texture,
+    /// Emissive map texture of the material.
+    pub emissive_texture: Texture,
+    /// Normal map texture of the material, `norm` in the PBR extension. Kept
+    /// apart from [`normal_texture`](Material::normal_texture) so a material
+    /// can have both.
+    pub pbr_normal_texture: Texture,
     /// Key value pairs of any unrecognized parameters encountered while parsing
     /// the material.
     pub unknown_param: HashMap<String, String>,
+    /// The `MTL` file the material was loaded from. Only set by [`load_mtl()`]
+    /// and the functions using it, such as [`load_obj()`].
+    pub mtl_file: Option<PathBuf>,
+}
+
+impl Material {
+    /// All textures the material uses, i.e. those whose file name is not
+    /// empty.
+    pub fn textures(&self) -> impl Iterator<Item = &Texture> {
+        IntoIterator::into_iter([
+            &self.ambient_texture,
+            &self.diffuse_texture,
+            &self.specular_texture,
+            &self.normal_texture,
+            &self.shininess_texture,
+            &self.dissolve_texture,
+            &self.displacement_texture,
+            &self.decal_texture,
+            &self.reflection_texture,
+            &self.roughness_texture,
+            &self.metallic_texture,
+            &self.sheen_texture,
+            &self.emissive_texture,
+            &self.pbr_normal_texture,
+        ])
+        .chain(self.reflection_cube.faces())
+        .filter(|texture| !texture.is_empty())
+    }
 }
 
 impl Default for Material {
@@ -665,74 +1204,318 @@ impl Default for Material {
             ambient: [0.0; 3],
             diffuse: [0.0; 3],
             specular: [0.0; 3],
+            ambient_source: None,
+            diffuse_source: None,
+            specular_source: None,
             shininess: 0.0,
             dissolve: 1.0,
+            dissolve_halo: false,
+            transmission_filter: None,
+            transmission_filter_source: None,
+            sharpness: None,
             optical_density: 1.0,
-            ambient_texture: String::new(),
-            diffuse_texture: String::new(),
-            specular_texture: String::new(),
-            normal_texture: String::new(),
-            shininess_texture: String::new(),
-            dissolve_texture: String::new(),
+            ambient_texture: Texture::default(),
+            diffuse_texture: Texture::default(),
+            specular_texture: Texture::default(),
+            normal_texture: Texture::default(),
+            shininess_texture: Texture::default(),
+            dissolve_texture: Texture::default(),
+            displacement_texture: Texture::default(),
+            decal_texture: Texture::default(),
+            reflection_texture: Texture::default(),
+            reflection_cube: CubeMap::default(),
+            texture_anti_aliasing: false,
             illumination_model: None,
+            roughness: None,
+            metallic: None,
+            sheen: None,
+            clearcoat_thickness: None,
+            clearcoat_roughness: None,
+            emissive: None,
+            emissive_source: None,
+            anisotropy: None,
+            anisotropy_rotation: None,
+            roughness_texture: Texture::default(),
+            metallic_texture: Texture::default(),
+            sheen_texture: Texture::default(),
+            emissive_texture: Texture::default(),
+            pbr_normal_texture: Texture::default(),
             unknown_param: HashMap::new_map(),
+            mtl_file: None,
         }
     }
 }
 
-/// Possible errors that may occur while loading `OBJ` and `MTL` files.
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum LoadError {
+/// The kinds of errors that may occur while loading `OBJ` and `MTL` files.
+///
+/// Returned by [`LoadError::kind()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoadErrorKind {
     OpenFileFailed,
     ReadError,
     UnrecognizedCharacter,
     PositionParseError,
+    VertexColorParseError,
     NormalParseError,
     TexcoordParseError,
     FaceParseError,
+    SmoothingGroupParseError,
+    FreeFormParseError,
     MaterialParseError,
+    MaterialNotFound,
     InvalidObjectName,
     InvalidPolygon,
     FaceVertexOutOfBounds,
     FaceTexCoordOutOfBounds,
     FaceNormalOutOfBounds,
     FaceColorOutOfBounds,
+    FreeFormIndexOutOfBounds,
     InvalidLoadOptionConfig,
     GenericFailure,
 }
 
-impl fmt::Display for LoadError {
+impl fmt::Display for LoadErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         let msg = match *self {
-            LoadError::OpenFileFailed => "open file failed",
-            LoadError::ReadError => "read error",
-            LoadError::UnrecognizedCharacter => "unrecognized character",
-            LoadError::PositionParseError => "position parse error",
-            LoadError::NormalParseError => "normal parse error",
-            LoadError::TexcoordParseError => "texcoord parse error",
-            LoadError::FaceParseError => "face parse error",
-            LoadError::MaterialParseError => "material parse error",
-            LoadError::InvalidObjectName => "invalid object name",
-            LoadError::InvalidPolygon => "invalid polygon",
-            LoadError::FaceVertexOutOfBounds => "face vertex index out of bounds",
-            LoadError::FaceTexCoordOutOfBounds => "face texcoord index out of bounds",
-            LoadError::FaceNormalOutOfBounds => "face normal index out of bounds",
-            LoadError::FaceColorOutOfBounds => "face vertex color index out of bounds",
-            LoadError::InvalidLoadOptionConfig => "mutually exclusive load options",
-            LoadError::GenericFailure => "generic failure",
+            LoadErrorKind::OpenFileFailed => "open file failed",
+            LoadErrorKind::ReadError => "read error",
+            LoadErrorKind::UnrecognizedCharacter => "unrecognized character",
+            LoadErrorKind::PositionParseError => "position parse error",
+            LoadErrorKind::VertexColorParseError => "vertex color parse error",
+            LoadErrorKind::NormalParseError => "normal parse error",
+            LoadErrorKind::TexcoordParseError => "texcoord parse error",
+            LoadErrorKind::FaceParseError => "face parse error",
+            LoadErrorKind::SmoothingGroupParseError => "smoothing group parse error",
+            LoadErrorKind::FreeFormParseError => "free-form geometry parse error",
+            LoadErrorKind::MaterialParseError => "material parse error",
+            LoadErrorKind::MaterialNotFound => "material not found",
+            LoadErrorKind::InvalidObjectName => "invalid object name",
+            LoadErrorKind::InvalidPolygon => "invalid polygon",
+            LoadErrorKind::FaceVertexOutOfBounds => "face vertex index out of bounds",
+            LoadErrorKind::FaceTexCoordOutOfBounds => "face texcoord index out of bounds",
+            LoadErrorKind::FaceNormalOutOfBounds => "face normal index out of bounds",
+            LoadErrorKind::FaceColorOutOfBounds => "face vertex color index out of bounds",
+            LoadErrorKind::FreeFormIndexOutOfBounds => {
+                "free-form geometry vertex or curve index out of bounds"
+            }
+            LoadErrorKind::InvalidLoadOptionConfig => "mutually exclusive load options",
+            LoadErrorKind::GenericFailure => "generic failure",
         };
 
         f.write_str(msg)
     }
 }
 
-impl Error for LoadError {}
+/// An error that occured while loading an `OBJ` or `MTL` file.
+///
+/// Besides its [`kind`](LoadError::kind) the error carries as much context as
+/// was available: the number and text of the offending line, the file that
+/// was being read and the underlying I/O error.
+///
+/// The [`Display`](fmt::Display) implementation combines these, e.g.
+/// `scan.obj:1203: face parse error: "f 1/1 2/2 x"`.
+#[derive(Debug, Clone)]
+pub struct LoadError {
+    kind: LoadErrorKind,
+    line: Option<usize>,
+    statement: Option<String>,
+    path: Option<PathBuf>,
+    source: Option<Arc<io::Error>>,
+}
+
+impl LoadError {
+    /// Create an error of the given kind without any further context.
+    pub fn new(kind: LoadErrorKind) -> Self {
+        Self {
+            kind,
+            line: None,
+            statement: None,
+            path: None,
+            source: None,
+        }
+    }
+
+    /// Create an error of the given kind caused by an I/O error.
+    pub fn with_io_error(kind: LoadErrorKind, source: io::Error) -> Self {
+        Self {
+            source: Some(Arc::new(source)),
+            ..Self::new(kind)
+        }
+    }
+
+    /// The kind of error. Use this to match on the cause of the error.
+    pub fn kind(&self) -> LoadErrorKind {
+        self.kind
+    }
+
+    /// The 1-based number of the line the error occured on, if known.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// The text of the statement that caused the error, if known.
+    pub fn statement(&self) -> Option<&str> {
+        self.statement.as_deref()
+    }
+
+    /// The path of the `OBJ` or `MTL` file the error occured in, if known.
+    ///
+    /// Errors from loading from buffers only carry a path if the material
+    /// loader attached one.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The I/O error that caused this error, if any.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.source.as_deref()
+    }
+
+    /// Attach the line the error occured on. Keeps any line already set as
+    /// that is the more specific one.
+    pub(crate) fn at_line(mut self, line: usize, statement: Option<&str>) -> Self {
+        if self.line.is_none() {
+            self.line = Some(line);
+            self.statement = statement.map(|s| s.trim().to_owned());
+        }
+        self
+    }
+
+    /// Attach the path of the file the error occured in. Keeps any path
+    /// already set, e.g. that of an `MTL` file referenced by an `OBJ` file.
+    pub(crate) fn in_file(mut self, path: &Path) -> Self {
+        if self.path.is_none() {
+            self.path = Some(path.to_owned());
+        }
+        self
+    }
+}
+
+impl From<LoadErrorKind> for LoadError {
+    fn from(kind: LoadErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl PartialEq for LoadError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.line == other.line
+            && self.statement == other.statement
+            && self.path == other.path
+            && self.source.as_ref().map(|e| e.kind()) == other.source.as_ref().map(|e| e.kind())
+    }
+}
+
+impl PartialEq<LoadErrorKind> for LoadError {
+    fn eq(&self, kind: &LoadErrorKind) -> bool {
+        self.kind == *kind
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match (&self.path, self.line) {
+            (Some(path), Some(line)) => write!(f, "{}:{}: ", path.display(), line)?,
+            (Some(path), None) => write!(f, "{}: ", path.display())?,
+            (None, Some(line)) => write!(f, "line {}: ", line)?,
+            (None, None) => {}
+        }
+        write!(f, "{}", self.kind)?;
+        if let Some(statement) = &self.statement {
+            write!(f, ": {:?}", statement)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|e| e as _)
+    }
+}
+
+/// How severe a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// The statement was repaired or does not affect the loaded geometry.
+    Warning,
+    /// The statement was skipped, the loaded models lack the data it
+    /// described.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(match *self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A problem found while loading an `OBJ` file that did not abort loading.
+///
+/// Returned by [`load_obj_with_diagnostics()`] and
+/// [`load_obj_buf_with_diagnostics()`]. Malformed statements are only
+/// recovered from with [`lenient`](LoadOptions::lenient) set to `true`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Whether the problem was repaired or the statement skipped.
+    pub severity: Severity,
+    /// The problem itself, including the line it occured on.
+    pub error: LoadError,
+}
+
+impl Diagnostic {
+    /// The kind of problem. Shorthand for `self.error.kind()`.
+    pub fn kind(&self) -> LoadErrorKind {
+        self.error.kind()
+    }
+
+    /// The 1-based number of the line the problem occured on. Shorthand for
+    /// `self.error.line()`.
+    pub fn line(&self) -> Option<usize> {
+        self.error.line()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}: {}", self.severity, self.error)
+    }
+}
 
 /// A [`Result`] containing all the models loaded from the file and any
 /// materials from referenced material libraries. Or an error that occured while
 /// loading.
 pub type LoadResult<T> = Result<(Vec<Model<T>>, Result<Vec<Material>, LoadError>), LoadError>;
 
+/// A [`LoadResult`] that additionally contains the [`Diagnostic`]s collected
+/// while loading.
+pub type LoadResultWithDiagnostics<T> = Result<
+    (
+        Vec<Model<T>>,
+        Result<Vec<Material>, LoadError>,
+        Vec<Diagnostic>,
+    ),
+    LoadError,
+>;
+
+/// A [`LoadResult`] that additionally contains the vertex data of the whole
+/// file, which the models index into.
+pub type LoadResultWithAttributes<T> = Result<
+    (
+        Vec<Model<T>>,
+        Result<Vec<Material>, LoadError>,
+        Attributes<T>,
+    ),
+    LoadError,
+>;
+
 /// A [`Result`] containing all the materials loaded from the file and a map of
 /// `MTL` name to index. Or an error that occured while loading.
 pub type MTLLoadResult = Result<(Vec<Material>, HashMap<String, usize>), LoadError>;
@@ -750,6 +1533,10 @@ struct VertexIndices {
 
 static MISSING_INDEX: usize = usize::MAX;
 
+/// The material name of a `usemtl` statement that resets the material of the
+/// following faces to none, unless a material of that name exists.
+pub(crate) const NO_MATERIAL: &str = "(null)";
+
 impl VertexIndices {
     /// Parse the vertex indices from the face string.
     ///
@@ -806,27 +1593,223 @@ enum Face {
     Polygon(Vec<VertexIndices>),
 }
 
+impl Face {
+    /// The vertices of the face, in order.
+    fn vertices(&self) -> Vec<VertexIndices> {
+        match *self {
+            Face::Point(a) => vec![a],
+            Face::Line(a, b) => vec![a, b],
+            Face::Triangle(a, b, c) => vec![a, b, c],
+            Face::Quad(a, b, c, d) => vec![a, b, c, d],
+            Face::Polygon(ref indices) => indices.clone(),
+        }
+    }
+}
+
+/// The kind of primitive a [`Face`] is read as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Primitive {
+    /// A face, or any `l` statement unless
+    /// [`separate_lines_and_points`](LoadOptions::separate_lines_and_points)
+    /// is set.
+    Face,
+    /// A polyline read from an `l` statement.
+    Line,
+    /// Points read from a `p` statement.
+    Points,
+}
+
+impl Primitive {
+    /// Whether the primitive is skipped due to the `ignore_lines` or
+    /// `ignore_points` option.
+    fn is_ignored(self, load_options: &LoadOptions) -> bool {
+        match self {
+            Primitive::Face => false,
+            Primitive::Line => load_options.ignore_lines,
+            Primitive::Points => load_options.ignore_points,
+        }
+    }
+}
+
+/// The lengths of the index `Vec`s of a mesh before adding a line or points.
+fn primitive_start<T: ParseableV>(mesh: &Mesh<T>) -> [usize; 3] {
+    [
+        mesh.indices.len(),
+        mesh.texcoord_indices.len(),
+        mesh.normal_indices.len(),
+    ]
+}
+
+/// Move the indices of a line or points just added to a mesh from its
+/// `indices` to the `line_indices` or `point_indices` resp.
+///
+/// Texture coordinate and normal indices are dropped.
+fn split_off_primitive<T: ParseableV>(mesh: &mut Mesh<T>, primitive: Primitive, first: [usize; 3]) {
+    let [first_index, first_texcoord, first_normal] = first;
+    mesh.texcoord_indices.truncate(first_texcoord);
+    mesh.normal_indices.truncate(first_normal);
+
+    let indices = mesh.indices.drain(first_index..);
+    match primitive {
+        Primitive::Line => {
+            mesh.line_arities.push(indices.len() as u32);
+            mesh.line_indices.extend(indices);
+        }
+        Primitive::Points => mesh.point_indices.extend(indices),
+        Primitive::Face => unreachable!("faces stay in `indices`"),
+    }
+}
+
 /// Parse the float information from the words. Words is an iterator over the
 /// float strings. Returns `false` if parsing failed.
 fn parse_floatn<T: ParseableV>(val_str: &mut SplitWhitespace, vals: &mut Vec<T>, n: usize) -> bool {
-    // If we are failing. We need to return before we add the failed parse to the
-    // value vector.
-    let mut temp: Vec<T> = Vec::with_capacity(4);
+    // If we are failing we need to remove the values parsed so far again.
+    let start = vals.len();
     for p in val_str.take(n) {
         match FromStr::from_str(p) {
-            Ok(x) => temp.push(x),
-            Err(_) => return false,
+            Ok(x) => vals.push(x),
+            Err(_) => {
+                vals.truncate(start);
+                return false;
+            }
         }
     }
     // Require that we found the desired number of values.
-    if n == temp.len() {
-        vals.append(&mut temp);
+    if vals.len() - start == n {
         true
     } else {
+        vals.truncate(start);
         false
     }
 }
 
+/// Parse what follows the position of a `v` statement up to a comment:
+/// nothing, a weight, an RGB or an RGBA color. Returns the color, with an alpha
+/// of `1` unless given, and weight found or `None` if there is anything else.
+///
+/// The values are parsed as `f64` and then converted, so that meshes of integer
+/// precision still load files with fractional colors.
+#[allow(clippy::type_complexity)]
+fn parse_vertex_extras<T: ParseableV>(
+    val_str: SplitWhitespace,
+) -> Option<(Option<[T; 4]>, Option<T>)> {
+    let mut extras = [0.0; 4];
+    let mut count = 0;
+    for word in val_str.take_while(|word| !word.starts_with('#')) {
+        *extras.get_mut(count)? = word.parse::<f64>().ok()?;
+        count += 1;
+    }
+
+    match count {
+        0 => Some((None, None)),
+        1 => Some((None, Some(normals::from_f64(extras[0])))),
+        3 | 4 => {
+            let mut color = [T::one(); 4];
+            for (component, &value) in color.iter_mut().zip(&extras[..count]) {
+                *component = normals::from_f64(value);
+            }
+            Some((Some(color), None))
+        }
+        _ => None,
+    }
+}
+
+/// Convert a byte of a ZBrush `#MRGB` block into a color or mask component.
+///
+/// The byte is scaled from `0..=255` to `0..=1` unless `T` is an
+/// [integer](ParseableV::is_integer) type, which keeps it unscaled rather than
+/// rounding it to `0` or `1`.
+fn mrgb_component<T: ParseableV>(byte: u8) -> T {
+    if T::is_integer() {
+        T::from(byte).unwrap_or_else(T::zero)
+    } else {
+        normals::from_f64(f64::from(byte) / 255.0)
+    }
+}
+
+/// Extend per-vertex data by `vertices`, with `values` if they have some.
+///
+/// The data is only kept once any vertex has a value. From then on all
+/// vertices without one get `default`, including those before.
+fn extend_per_vertex<V: Copy>(
+    data: &mut Vec<V>,
+    vertices: Range<usize>,
+    values: Option<&[V]>,
+    default: &[V],
+) {
+    if data.is_empty() && values.is_none() {
+        return;
+    }
+    let n = default.len();
+    while data.len() < vertices.start * n {
+        data.extend_from_slice(default);
+    }
+    match values {
+        Some(values) => data.extend_from_slice(values),
+        None => {
+            for _ in vertices {
+                data.extend_from_slice(default);
+            }
+        }
+    }
+}
+
+/// Parse the one to three components of a texture coordinate, padding it to
+/// three with `0`. Returns the number of components found or `None` if parsing
+/// failed.
+fn parse_texcoord<T: ParseableV>(
+    val_str: &mut SplitWhitespace,
+    vals: &mut Vec<T>,
+) -> Option<usize> {
+    let start = vals.len();
+    for p in val_str.take(3) {
+        match FromStr::from_str(p) {
+            Ok(x) => vals.push(x),
+            Err(_) => {
+                vals.truncate(start);
+                return None;
+            }
+        }
+    }
+    let dimension = vals.len() - start;
+    if dimension == 0 {
+        return None;
+    }
+    vals.resize(start + 3, T::zero());
+    Some(dimension)
+}
+
+/// Drop the components beyond `dimension` of texture coordinates padded to
+/// three components.
+fn truncate_texcoords<T: ParseableV>(texcoords: &mut Vec<T>, dimension: usize) {
+    if dimension < 3 {
+        *texcoords = texcoords
+            .chunks_exact(3)
+            .flat_map(|texcoord| texcoord[..dimension].iter().copied())
+            .collect();
+    }
+}
+
+/// Parse `n` floats into the vector passed, substituting `0` for any that are
+/// missing or cannot be parsed.
+fn repair_floatn<T: ParseableV>(val_str: &mut SplitWhitespace, vals: &mut Vec<T>, n: usize) {
+    for _ in 0..n {
+        let val = val_str
+            .next()
+            .and_then(|p| FromStr::from_str(p).ok())
+            .unwrap_or_else(T::zero);
+        vals.push(val);
+    }
+}
+
+/// Parse a single material parameter.
+fn parse_float(mut val_str: SplitWhitespace) -> Result<f32, LoadError> {
+    val_str
+        .next()
+        .and_then(|p| FromStr::from_str(p).ok())
+        .ok_or_else(|| LoadErrorKind::MaterialParseError.into())
+}
+
 /// Parse the float3 into the array passed, returns false if parsing failed
 fn parse_float3<T: ParseableV>(val_str: SplitWhitespace, vals: &mut [T; 3]) -> bool {
     for (i, p) in val_str.enumerate().take(3) {
@@ -838,6 +1821,31 @@ fn parse_float3<T: ParseableV>(val_str: SplitWhitespace, vals: &mut [T; 3]) -> b
     true
 }
 
+/// Parse the arguments of an `MTL` color statement into `rgb`, or into
+/// `source` if they are XYZ values or a spectral curve.
+fn parse_color(
+    args: &str,
+    rgb: &mut [f32; 3],
+    source: &mut Option<ColorSource>,
+) -> Result<(), LoadError> {
+    let words = args.split_whitespace();
+    match words.clone().next() {
+        Some("xyz") | Some("spectral") => *source = Some(args.parse()?),
+        _ if parse_float3(words, rgb) => *source = None,
+        _ => return Err(LoadErrorKind::MaterialParseError.into()),
+    }
+    Ok(())
+}
+
+/// Parse the smoothing group id of an `s` statement. Both `off` and `0` turn
+/// smoothing off and yield `0`. Returns `None` if parsing failed.
+fn parse_smoothing_group(mut val_str: SplitWhitespace) -> Option<u32> {
+    match val_str.next()? {
+        "off" => Some(0),
+        p => FromStr::from_str(p).ok(),
+    }
+}
+
 /// Parse vertex indices for a face and append it to the list of faces passed.
 ///
 /// Also handles relative face indices (negative values) which is why passing
@@ -851,20 +1859,37 @@ fn parse_face(
     tex_sz: usize,
     norm_sz: usize,
 ) -> bool {
-    let mut indices = Vec::new();
+    // Only polygons with more than four vertices need to allocate.
+    let mut quad = [VertexIndices {
+        v: MISSING_INDEX,
+        vt: MISSING_INDEX,
+        vn: MISSING_INDEX,
+    }; 4];
+    let mut polygon = Vec::new();
+    let mut count = 0;
     for f in face_str {
-        match VertexIndices::parse(f, pos_sz, tex_sz, norm_sz) {
-            Some(v) => indices.push(v),
+        let v = match VertexIndices::parse(f, pos_sz, tex_sz, norm_sz) {
+            Some(v) => v,
             None => return false,
-        }
+        };
+        if count < 4 {
+            quad[count] = v;
+        } else {
+            if polygon.is_empty() {
+                polygon.extend_from_slice(&quad);
+            }
+            polygon.push(v);
+        }
+        count += 1;
     }
     // Check what kind face we read and push it on
-    match indices.len() {
-        1 => faces.push(Face::Point(indices[0])),
-        2 => faces.push(Face::Line(indices[0], indices[1])),
-        3 => faces.push(Face::Triangle(indices[0], indices[1], indices[2])),
-        4 => faces.push(Face::Quad(indices[0], indices[1], indices[2], indices[3])),
-        _ => faces.push(Face::Polygon(indices)),
+    let [a, b, c, d] = quad;
+    match count {
+        1 => faces.push(Face::Point(a)),
+        2 => faces.push(Face::Line(a, b)),
+        3 => faces.push(Face::Triangle(a, b, c)),
+        4 => faces.push(Face::Quad(a, b, c, d)),
+        _ => faces.push(Face::Polygon(polygon)),
     }
     true
 }
@@ -877,7 +1902,7 @@ fn add_vertex<T: ParseableV>(
     index_map: &mut HashMap<VertexIndices, u32>,
     vert: &VertexIndices,
     pos: &[T],
-    v_color: &[f32],
+    v_color: &[T],
     texcoord: &[T],
     normal: &[T],
 ) -> Result<(), LoadError> {
@@ -886,7 +1911,7 @@ fn add_vertex<T: ParseableV>(
         None => {
             let v = vert.v;
             if v.saturating_mul(3).saturating_add(2) >= pos.len() {
-                return Err(LoadError::FaceVertexOutOfBounds);
+                return Err(LoadErrorKind::FaceVertexOutOfBounds.into());
             }
             // Add the vertex to the mesh
             mesh.positions.push(pos[v * 3]);
@@ -894,35 +1919,27 @@ fn add_vertex<T: ParseableV>(
             mesh.positions.push(pos[v * 3 + 2]);
             if !texcoord.is_empty() && vert.vt != MISSING_INDEX {
                 let vt = vert.vt;
-                if vt * 2 + 1 >= texcoord.len() {
-                    return Err(LoadError::FaceTexCoordOutOfBounds);
+                if vt * 3 + 2 >= texcoord.len() {
+                    return Err(LoadErrorKind::FaceTexCoordOutOfBounds.into());
                 }
-                mesh.texcoords.push(texcoord[vt * 2]);
-                mesh.texcoords.push(texcoord[vt * 2 + 1]);
+                mesh.texcoords
+                    .extend_from_slice(&texcoord[vt * 3..vt * 3 + 3]);
             }
             if !normal.is_empty() && vert.vn != MISSING_INDEX {
                 let vn = vert.vn;
                 if vn * 3 + 2 >= normal.len() {
-                    return Err(LoadError::FaceNormalOutOfBounds);
+                    return Err(LoadErrorKind::FaceNormalOutOfBounds.into());
                 }
                 mesh.normals.push(normal[vn * 3]);
                 mesh.normals.push(normal[vn * 3 + 1]);
                 mesh.normals.push(normal[vn * 3 + 2]);
             }
             if !v_color.is_empty() {
-                if v_color.len() == 3 || v_color.len() == 4 {
-                    mesh.vertex_color.push(v_color[0]);
-                    mesh.vertex_color.push(v_color[1]);
-                    mesh.vertex_color.push(v_color[2]);
-                } else if v * 3 + 2 >= v_color.len() {
-                    println!("`add_vertex` v_color.len={}", v_color.len());
-                    println!("`add_vertex` pos.len={}", pos.len());
-                    return Err(LoadError::FaceColorOutOfBounds);
-                } else {
-                    mesh.vertex_color.push(v_color[v * 3]);
-                    mesh.vertex_color.push(v_color[v * 3 + 1]);
-                    mesh.vertex_color.push(v_color[v * 3 + 2]);
+                if v * 4 + 3 >= v_color.len() {
+                    return Err(LoadErrorKind::FaceColorOutOfBounds.into());
                 }
+                mesh.vertex_color
+                    .extend_from_slice(&v_color[v * 4..v * 4 + 4]);
             }
             let next = index_map.len() as u32;
             mesh.indices.push(next);
@@ -932,25 +1949,220 @@ fn add_vertex<T: ParseableV>(
     Ok(())
 }
 
+/// The number of faces added to a mesh since it had `first_index` indices and
+/// `first_arity` face arities – one per triangle if faces are triangulated.
+#[inline]
+fn faces_added<T: ParseableV>(
+    mesh: &Mesh<T>,
+    first_index: usize,
+    first_arity: usize,
+    load_options: &LoadOptions,
+) -> usize {
+    if load_options.triangulate {
+        (mesh.indices.len() - first_index) / 3
+    } else {
+        mesh.face_arities.len() - first_arity
+    }
+}
+
+/// Record a per-face value, such as the smoothing group, of each of the
+/// `faces` just added to a mesh.
+#[inline]
+fn push_per_face<V: Clone>(values: &mut Vec<V>, faces: usize, value: V) {
+    values.resize(values.len() + faces, value);
+}
+
+/// The material shared by all faces, if any.
+fn uniform_material(face_materials: &[Option<usize>]) -> Option<usize> {
+    match face_materials.split_first() {
+        Some((&first, rest)) if rest.iter().all(|&m| m == first) => first,
+        _ => None,
+    }
+}
+
+/// Tessellate the free-form geometry of an object into a mesh laid out as
+/// `load_options` ask for.
+fn tessellate_free_form<T: ParseableV>(
+    free_form: &FreeForm<T>,
+    tessellation_options: &TessellationOptions,
+    mat_id: Option<usize>,
+    load_options: &LoadOptions,
+) -> Mesh<T> {
+    let mut mesh = free_form.tessellate(tessellation_options);
+    mesh.material_id = mat_id;
+    if load_options.per_face_materials {
+        mesh.face_materials = vec![mat_id; mesh.indices.len() / 3];
+    }
+    if let Some(normal_options) = load_options.generate_normals {
+        mesh.compute_normals(&normal_options);
+    }
+
+    // The vertex data is per vertex already, which is what reordering would
+    // result in, too.
+    #[allow(unused_mut)]
+    let mut per_vertex = load_options.single_index;
+    #[cfg(feature = "reordering")]
+    {
+        per_vertex = per_vertex || load_options.reorder_data;
+    }
+    if !per_vertex {
+        if !mesh.texcoords.is_empty() {
+            mesh.texcoord_indices = mesh.indices.clone();
+        }
+        if !mesh.normals.is_empty() {
+            mesh.normal_indices = mesh.indices.clone();
+        }
+    }
+    mesh
+}
+
+/// Gather a value per vertex of a mesh with `vertex_count` vertices, given
+/// the `values` of the vertices of the file and the index of each of them in
+/// the mesh. Vertices without a value get `default`.
+fn gather_per_vertex<V: Copy>(
+    values: &[V],
+    default: V,
+    vertex_count: usize,
+    indices: impl Iterator<Item = (usize, u32)>,
+) -> Vec<V> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+    let mut gathered = vec![default; vertex_count];
+    for (v, index) in indices {
+        if let Some(&value) = values.get(v) {
+            gathered[index as usize] = value;
+        }
+    }
+    gathered
+}
+
+/// Append the vertices in `vertices` that are not used by any of `faces` to
+/// the positions, vertex colors, weights and masks of a mesh, without
+/// referring to them.
+///
+/// If the mesh has a normal and texture coordinate per position, including
+/// generated normals, these vertices get zero ones. That is what generating
+/// normals after adding them would result in, as they belong to no face.
+#[allow(clippy::too_many_arguments)]
+fn add_unreferenced_vertices<T: ParseableV>(
+    mesh: &mut Mesh<T>,
+    faces: &[Face],
+    primitives: &[Primitive],
+    vertices: Range<usize>,
+    pos: &[T],
+    v_color: &[T],
+    weights: &[T],
+    masks: &[T],
+    load_options: &LoadOptions,
+) {
+    let mut referenced = vec![false; vertices.len()];
+    for (f, primitive) in faces.iter().zip(primitives) {
+        match *f {
+            _ if primitive.is_ignored(load_options) => continue,
+            Face::Point(_) if load_options.ignore_points => continue,
+            Face::Line(..) if load_options.ignore_lines => continue,
+            _ => {}
+        }
+        for v in f.vertices() {
+            if vertices.contains(&v.v) {
+                referenced[v.v - vertices.start] = true;
+            }
+        }
+    }
+
+    // Only add colors if the mesh has one per vertex so far.
+    let vertex_count = mesh.positions.len() / 3;
+    let with_color =
+        mesh.vertex_color.len() / 4 == vertex_count && v_color.len() >= vertices.end * 4;
+    let with_normal = !mesh.normals.is_empty()
+        && mesh.normal_indices.is_empty()
+        && mesh.normals.len() / 3 == vertex_count;
+    let dimension = mesh.texcoord_dimension;
+    let with_texcoord = !mesh.texcoords.is_empty()
+        && mesh.texcoord_indices.is_empty()
+        && mesh.texcoords.len() / dimension == vertex_count;
+    let start = vertices.start;
+    for v in vertices.filter(|v| !referenced[v - start]) {
+        mesh.positions.extend_from_slice(&pos[v * 3..v * 3 + 3]);
+        if with_normal {
+            mesh.normals.extend_from_slice(&[T::zero(); 3]);
+        }
+        if with_texcoord {
+            mesh.texcoords
+                .resize(mesh.texcoords.len() + dimension, T::zero());
+        }
+        if with_color {
+            mesh.vertex_color
+                .extend_from_slice(&v_color[v * 4..v * 4 + 4]);
+        }
+        if !weights.is_empty() {
+            mesh.weights.resize(mesh.positions.len() / 3 - 1, T::one());
+            mesh.weights.push(weights[v]);
+        }
+        if !masks.is_empty() {
+            mesh.vertex_mask
+                .resize(mesh.positions.len() / 3 - 1, T::zero());
+            mesh.vertex_mask
+                .push(masks.get(v).copied().unwrap_or_else(T::zero));
+        }
+    }
+}
+
 /// Export a list of faces to a mesh and return it, optionally converting quads
 /// to tris.
+#[allow(clippy::too_many_arguments)]
 fn export_faces<T: ParseableV>(
     pos: &[T],
-    v_color: &[f32],
+    v_color: &[T],
+    weights: &[T],
+    masks: &[T],
     texcoord: &[T],
     normal: &[T],
     faces: &[Face],
+    primitives: &[Primitive],
+    smoothing_groups: &[Option<u32>],
+    face_materials: &[Option<usize>],
     mat_id: Option<usize>,
     load_options: &LoadOptions,
 ) -> Result<Mesh<T>, LoadError> {
     let mut index_map = HashMap::new_map();
     let mut mesh = Mesh {
         material_id: mat_id,
+        texcoord_dimension: 3,
         ..Default::default()
     };
     let mut is_all_triangles = true;
+    let ear_clipping = load_options.triangulate
+        && load_options.triangulation_method == TriangulationMethod::EarClipping;
+
+    for (((f, &smoothing_group), &material_id), &primitive) in faces
+        .iter()
+        .zip(smoothing_groups)
+        .zip(face_materials)
+        .zip(primitives)
+    {
+        if primitive != Primitive::Face {
+            if !primitive.is_ignored(load_options) {
+                let first = primitive_start(&mesh);
+                for v in f.vertices() {
+                    add_vertex(
+                        &mut mesh,
+                        &mut index_map,
+                        &v,
+                        pos,
+                        v_color,
+                        texcoord,
+                        normal,
+                    )?;
+                }
+                split_off_primitive(&mut mesh, primitive, first);
+            }
+            continue;
+        }
 
-    for f in faces {
+        let first_index = mesh.indices.len();
+        let first_arity = mesh.face_arities.len();
         // Optimized paths for Triangles and Quads, Polygon handles the general case of
         // an unknown length triangle fan.
         match *f {
@@ -986,6 +2198,40 @@ fn export_faces<T: ParseableV>(
                     mesh.face_arities.push(3);
                 }
             }
+            Face::Quad(a, b, c, d) if ear_clipping => {
+                let quad = [a, b, c, d];
+                for triangle in triangulation::ear_clip(pos, &quad) {
+                    for &i in &triangle {
+                        add_vertex(
+                            &mut mesh,
+                            &mut index_map,
+                            &quad[i],
+                            pos,
+                            v_color,
+                            texcoord,
+                            normal,
+                        )?;
+                    }
+                }
+            }
+            Face::Polygon(ref indices) if ear_clipping => {
+                if indices.len() < 3 {
+                    return Err(LoadErrorKind::InvalidPolygon.into());
+                }
+                for triangle in triangulation::ear_clip(pos, indices) {
+                    for &i in &triangle {
+                        add_vertex(
+                            &mut mesh,
+                            &mut index_map,
+                            &indices[i],
+                            pos,
+                            v_color,
+                            texcoord,
+                            normal,
+                        )?;
+                    }
+                }
+            }
             Face::Quad(ref a, ref b, ref c, ref d) => {
                 add_vertex(&mut mesh, &mut index_map, a, pos, v_color, texcoord, normal)?;
                 add_vertex(&mut mesh, &mut index_map, b, pos, v_color, texcoord, normal)?;
@@ -1003,8 +2249,8 @@ fn export_faces<T: ParseableV>(
             }
             Face::Polygon(ref indices) => {
                 if load_options.triangulate {
-                    let a = indices.get(0).ok_or(LoadError::InvalidPolygon)?;
-                    let mut b = indices.get(1).ok_or(LoadError::InvalidPolygon)?;
+                    let a = indices.first().ok_or(LoadErrorKind::InvalidPolygon)?;
+                    let mut b = indices.get(1).ok_or(LoadErrorKind::InvalidPolygon)?;
                     for c in indices.iter().skip(2) {
                         add_vertex(&mut mesh, &mut index_map, a, pos, v_color, texcoord, normal)?;
                         add_vertex(&mut mesh, &mut index_map, b, pos, v_color, texcoord, normal)?;
@@ -1020,6 +2266,26 @@ fn export_faces<T: ParseableV>(
                 }
             }
         }
+
+        let added = faces_added(&mesh, first_index, first_arity, load_options);
+        push_per_face(
+            &mut mesh.smoothing_groups,
+            added,
+            smoothing_group.unwrap_or(0),
+        );
+        if load_options.per_face_materials {
+            push_per_face(&mut mesh.face_materials, added, material_id);
+        }
+    }
+
+    if smoothing_groups.iter().all(Option::is_none) {
+        // Without any `s` statement the faces are not flat shaded, see
+        // `NormalOptions`.
+        mesh.smoothing_groups = Vec::new();
+    }
+
+    if load_options.per_face_materials {
+        mesh.material_id = uniform_material(&mesh.face_materials);
     }
 
     if is_all_triangles {
@@ -1027,6 +2293,17 @@ fn export_faces<T: ParseableV>(
         mesh.face_arities = Vec::new();
     }
 
+    let vertex_count = mesh.positions.len() / 3;
+    let indices = || index_map.iter().map(|(vert, &index)| (vert.v, index));
+    mesh.weights = gather_per_vertex(weights, T::one(), vertex_count, indices());
+    mesh.vertex_mask = gather_per_vertex(masks, T::zero(), vertex_count, indices());
+
+    if let Some(normal_options) = load_options.generate_normals {
+        if mesh.normals.is_empty() {
+            mesh.compute_normals(&normal_options);
+        }
+    }
+
     Ok(mesh)
 }
 
@@ -1042,7 +2319,7 @@ fn add_vertex_multi_index<T: ParseableV>(
     texcoord_index_map: &mut HashMap<usize, u32>,
     vert: &VertexIndices,
     pos: &[T],
-    v_color: &[f32],
+    v_color: &[T],
     texcoord: &[T],
     normal: &[T],
 ) -> Result<(), LoadError> {
@@ -1052,7 +2329,7 @@ fn add_vertex_multi_index<T: ParseableV>(
             let vertex = vert.v;
 
             if vertex.saturating_mul(3).saturating_add(2) >= pos.len() {
-                return Err(LoadError::FaceVertexOutOfBounds);
+                return Err(LoadErrorKind::FaceVertexOutOfBounds.into());
             }
 
             // Add the vertex to the mesh.
@@ -1068,19 +2345,11 @@ fn add_vertex_multi_index<T: ParseableV>(
             if !v_color.is_empty() {
                 let v = vert.v;
 
-                if v_color.len() == 3 || v_color.len() == 4 {
-                    mesh.vertex_color.push(v_color[0]);
-                    mesh.vertex_color.push(v_color[1]);
-                    mesh.vertex_color.push(v_color[2]);
-                } else if v * 3 + 2 >= v_color.len() {
-                    println!("`add_vertex` v_color.len={}", v_color.len());
-                    println!("`add_vertex` pos.len={}", pos.len());
-                    return Err(LoadError::FaceColorOutOfBounds);
-                } else {
-                    mesh.vertex_color.push(v_color[v * 3]);
-                    mesh.vertex_color.push(v_color[v * 3 + 1]);
-                    mesh.vertex_color.push(v_color[v * 3 + 2]);
+                if v * 4 + 3 >= v_color.len() {
+                    return Err(LoadErrorKind::FaceColorOutOfBounds.into());
                 }
+                mesh.vertex_color
+                    .extend_from_slice(&v_color[v * 4..v * 4 + 4]);
             }
         }
     }
@@ -1092,8 +2361,7 @@ fn add_vertex_multi_index<T: ParseableV>(
             // Special case: the very first vertex of the mesh has no index.
             if texcoord_indices.is_empty() {
                 // We have no choice, simply reference the first vertex.
-                mesh.texcoords.push(texcoord[0]);
-                mesh.texcoords.push(texcoord[1]);
+                mesh.texcoords.extend_from_slice(&texcoord[..3]);
 
                 texcoord_indices.push(0);
                 texcoord_index_map.insert(0, 0);
@@ -1110,12 +2378,12 @@ fn add_vertex_multi_index<T: ParseableV>(
                 None => {
                     let vt = vert.vt;
 
-                    if vt * 2 + 1 >= texcoord.len() {
-                        return Err(LoadError::FaceTexCoordOutOfBounds);
+                    if vt * 3 + 2 >= texcoord.len() {
+                        return Err(LoadErrorKind::FaceTexCoordOutOfBounds.into());
                     }
 
-                    mesh.texcoords.push(texcoord[vt * 2]);
-                    mesh.texcoords.push(texcoord[vt * 2 + 1]);
+                    mesh.texcoords
+                        .extend_from_slice(&texcoord[vt * 3..vt * 3 + 3]);
 
                     let next = texcoord_index_map.len() as u32;
                     mesh.texcoord_indices.push(next);
@@ -1152,7 +2420,7 @@ fn add_vertex_multi_index<T: ParseableV>(
                     let vn = vert.vn;
 
                     if vn * 3 + 2 >= normal.len() {
-                        return Err(LoadError::FaceNormalOutOfBounds);
+                        return Err(LoadErrorKind::FaceNormalOutOfBounds.into());
                     }
 
                     mesh.normals.push(normal[vn * 3]);
@@ -1172,12 +2440,18 @@ fn add_vertex_multi_index<T: ParseableV>(
 
 /// Export a list of faces to a mesh and return it, optionally converting quads
 /// to tris.
+#[allow(clippy::too_many_arguments)]
 fn export_faces_multi_index<T: ParseableV>(
     pos: &[T],
-    v_color: &[f32],
+    v_color: &[T],
+    weights: &[T],
+    masks: &[T],
     texcoord: &[T],
     normal: &[T],
     faces: &[Face],
+    primitives: &[Primitive],
+    smoothing_groups: &[Option<u32>],
+    face_materials: &[Option<usize>],
     mat_id: Option<usize>,
     load_options: &LoadOptions,
 ) -> Result<Mesh<T>, LoadError> {
@@ -1187,12 +2461,43 @@ fn export_faces_multi_index<T: ParseableV>(
 
     let mut mesh = Mesh {
         material_id: mat_id,
+        texcoord_dimension: 3,
         ..Default::default()
     };
 
     let mut is_all_triangles = true;
+    let ear_clipping = load_options.triangulate
+        && load_options.triangulation_method == TriangulationMethod::EarClipping;
+
+    for (((f, &smoothing_group), &material_id), &primitive) in faces
+        .iter()
+        .zip(smoothing_groups)
+        .zip(face_materials)
+        .zip(primitives)
+    {
+        if primitive != Primitive::Face {
+            if !primitive.is_ignored(load_options) {
+                let first = primitive_start(&mesh);
+                for v in f.vertices() {
+                    add_vertex_multi_index(
+                        &mut mesh,
+                        &mut index_map,
+                        &mut normal_index_map,
+                        &mut texcoord_index_map,
+                        &v,
+                        pos,
+                        v_color,
+                        texcoord,
+                        normal,
+                    )?;
+                }
+                split_off_primitive(&mut mesh, primitive, first);
+            }
+            continue;
+        }
 
-    for f in faces {
+        let first_index = mesh.indices.len();
+        let first_arity = mesh.face_arities.len();
         // Optimized paths for Triangles and Quads, Polygon handles the general case of
         // an unknown length triangle fan
         match *f {
@@ -1318,6 +2623,44 @@ fn export_faces_multi_index<T: ParseableV>(
                     mesh.face_arities.push(3);
                 }
             }
+            Face::Quad(a, b, c, d) if ear_clipping => {
+                let quad = [a, b, c, d];
+                for triangle in triangulation::ear_clip(pos, &quad) {
+                    for &i in &triangle {
+                        add_vertex_multi_index(
+                            &mut mesh,
+                            &mut index_map,
+                            &mut normal_index_map,
+                            &mut texcoord_index_map,
+                            &quad[i],
+                            pos,
+                            v_color,
+                            texcoord,
+                            normal,
+                        )?;
+                    }
+                }
+            }
+            Face::Polygon(ref indices) if ear_clipping => {
+                if indices.len() < 3 {
+                    return Err(LoadErrorKind::InvalidPolygon.into());
+                }
+                for triangle in triangulation::ear_clip(pos, indices) {
+                    for &i in &triangle {
+                        add_vertex_multi_index(
+                            &mut mesh,
+                            &mut index_map,
+                            &mut normal_index_map,
+                            &mut texcoord_index_map,
+                            &indices[i],
+                            pos,
+                            v_color,
+                            texcoord,
+                            normal,
+                        )?;
+                    }
+                }
+            }
             Face::Quad(ref a, ref b, ref c, ref d) => {
                 add_vertex_multi_index(
                     &mut mesh,
@@ -1405,8 +2748,8 @@ fn export_faces_multi_index<T: ParseableV>(
             }
             Face::Polygon(ref indices) => {
                 if load_options.triangulate {
-                    let a = indices.get(0).ok_or(LoadError::InvalidPolygon)?;
-                    let mut b = indices.get(1).ok_or(LoadError::InvalidPolygon)?;
+                    let a = indices.first().ok_or(LoadErrorKind::InvalidPolygon)?;
+                    let mut b = indices.get(1).ok_or(LoadErrorKind::InvalidPolygon)?;
                     for c in indices.iter().skip(2) {
                         add_vertex_multi_index(
                             &mut mesh,
@@ -1462,6 +2805,26 @@ fn export_faces_multi_index<T: ParseableV>(
                 }
             }
         }
+
+        let added = faces_added(&mesh, first_index, first_arity, load_options);
+        push_per_face(
+            &mut mesh.smoothing_groups,
+            added,
+            smoothing_group.unwrap_or(0),
+        );
+        if load_options.per_face_materials {
+            push_per_face(&mut mesh.face_materials, added, material_id);
+        }
+    }
+
+    if smoothing_groups.iter().all(Option::is_none) {
+        // Without any `s` statement the faces are not flat shaded, see
+        // `NormalOptions`.
+        mesh.smoothing_groups = Vec::new();
+    }
+
+    if load_options.per_face_materials {
+        mesh.material_id = uniform_material(&mesh.face_materials);
     }
 
     if is_all_triangles {
@@ -1469,18 +2832,58 @@ fn export_faces_multi_index<T: ParseableV>(
         mesh.face_arities = Vec::new();
     }
 
+    let vertex_count = mesh.positions.len() / 3;
+    let indices = || index_map.iter().map(|(&v, &index)| (v, index));
+    mesh.weights = gather_per_vertex(weights, T::one(), vertex_count, indices());
+    mesh.vertex_mask = gather_per_vertex(masks, T::zero(), vertex_count, indices());
+
     #[cfg(feature = "merging")]
     if load_options.merge_identical_points {
         if !mesh.vertex_color.is_empty() {
             mesh.vertex_color_indices = mesh.indices.clone();
-            merge_identical_points::<f32, 3>(
-                &mut mesh.vertex_color,
-                &mut mesh.vertex_color_indices,
-            );
+            merge_identical_points::<T, 4>(&mut mesh.vertex_color, &mut mesh.vertex_color_indices);
+        }
+        // Lines and points index the same positions as the faces.
+        let faces = mesh.indices.len();
+        let lines = mesh.line_indices.len();
+        mesh.indices.append(&mut mesh.line_indices);
+        mesh.indices.append(&mut mesh.point_indices);
+        if mesh.weights.is_empty() && mesh.vertex_mask.is_empty() {
+            merge_identical_points::<T, 3>(&mut mesh.positions, &mut mesh.indices);
+        } else {
+            // Only positions with the same weight and mask are identical.
+            let mut points: Vec<T> = mesh
+                .positions
+                .chunks_exact(3)
+                .enumerate()
+                .flat_map(|(i, p)| {
+                    let w = mesh.weights.get(i).copied().unwrap_or_else(T::one);
+                    let m = mesh.vertex_mask.get(i).copied().unwrap_or_else(T::zero);
+                    IntoIterator::into_iter([p[0], p[1], p[2], w, m])
+                })
+                .collect();
+            merge_identical_points::<T, 5>(&mut points, &mut mesh.indices);
+            mesh.positions = points
+                .chunks_exact(5)
+                .flat_map(|p| IntoIterator::into_iter([p[0], p[1], p[2]]))
+                .collect();
+            if !mesh.weights.is_empty() {
+                mesh.weights = points.chunks_exact(5).map(|p| p[3]).collect();
+            }
+            if !mesh.vertex_mask.is_empty() {
+                mesh.vertex_mask = points.chunks_exact(5).map(|p| p[4]).collect();
+            }
         }
-        merge_identical_points::<T, 3>(&mut mesh.positions, &mut mesh.indices);
+        mesh.point_indices = mesh.indices.split_off(faces + lines);
+        mesh.line_indices = mesh.indices.split_off(faces);
         merge_identical_points::<T, 3>(&mut mesh.normals, &mut mesh.normal_indices);
-        merge_identical_points::<T, 2>(&mut mesh.texcoords, &mut mesh.texcoord_indices);
+        merge_identical_points::<T, 3>(&mut mesh.texcoords, &mut mesh.texcoord_indices);
+    }
+
+    if let Some(normal_options) = load_options.generate_normals {
+        if mesh.normals.is_empty() {
+            mesh.compute_normals_multi_index(&normal_options);
+        }
     }
 
     #[cfg(feature = "reordering")]
@@ -1491,31 +2894,174 @@ fn export_faces_multi_index<T: ParseableV>(
     Ok(mesh)
 }
 
+/// Add the indices of a vertex to a mesh that refers to the [`Attributes`] of
+/// the file rather than holding vertex data of its own.
+fn add_vertex_shared<T: ParseableV>(
+    mesh: &mut Mesh<T>,
+    vert: &VertexIndices,
+    pos: &[T],
+    texcoord: &[T],
+    normal: &[T],
+) -> Result<(), LoadError> {
+    if vert.v >= pos.len() / 3 {
+        return Err(LoadErrorKind::FaceVertexOutOfBounds.into());
+    }
+    mesh.indices.push(vert.v as u32);
+
+    // Vertices without texture coordinates or normals use the previous ones,
+    // as in `add_vertex_multi_index()`.
+    for (indices, index, len, kind) in [
+        (
+            &mut mesh.texcoord_indices,
+            vert.vt,
+            texcoord.len() / 3,
+            LoadErrorKind::FaceTexCoordOutOfBounds,
+        ),
+        (
+            &mut mesh.normal_indices,
+            vert.vn,
+            normal.len() / 3,
+            LoadErrorKind::FaceNormalOutOfBounds,
+        ),
+    ] {
+        if len == 0 {
+            continue;
+        }
+        let index = if index == MISSING_INDEX {
+            indices.last().copied().unwrap_or(0)
+        } else if index >= len {
+            return Err(kind.into());
+        } else {
+            index as u32
+        };
+        indices.push(index);
+    }
+
+    Ok(())
+}
+
+/// Export a list of faces to a mesh indexing into the [`Attributes`] of the
+/// file, optionally converting polygons to tris.
+#[allow(clippy::too_many_arguments)]
+fn export_faces_shared<T: ParseableV>(
+    pos: &[T],
+    texcoord: &[T],
+    normal: &[T],
+    faces: &[Face],
+    primitives: &[Primitive],
+    smoothing_groups: &[Option<u32>],
+    face_materials: &[Option<usize>],
+    mat_id: Option<usize>,
+    load_options: &LoadOptions,
+) -> Result<Mesh<T>, LoadError> {
+    let mut mesh = Mesh {
+        material_id: mat_id,
+        ..Default::default()
+    };
+    let mut is_all_triangles = true;
+
+    for (((f, &smoothing_group), &material_id), &primitive) in faces
+        .iter()
+        .zip(smoothing_groups)
+        .zip(face_materials)
+        .zip(primitives)
+    {
+        let vertices = f.vertices();
+        if primitive != Primitive::Face {
+            if !primitive.is_ignored(load_options) {
+                let first = primitive_start(&mesh);
+                for v in &vertices {
+                    add_vertex_shared(&mut mesh, v, pos, texcoord, normal)?;
+                }
+                split_off_primitive(&mut mesh, primitive, first);
+            }
+            continue;
+        }
+
+        let first_index = mesh.indices.len();
+        let first_arity = mesh.face_arities.len();
+        let corners: Vec<usize> = match vertices.len() {
+            1 if load_options.ignore_points => continue,
+            2 if load_options.ignore_lines => continue,
+            n if !load_options.triangulate => {
+                if n != 3 {
+                    is_all_triangles = false;
+                }
+                mesh.face_arities.push(n as u32);
+                (0..n).collect()
+            }
+            0 => return Err(LoadErrorKind::InvalidPolygon.into()),
+            1 => vec![0, 0, 0],
+            2 => vec![0, 1, 1],
+            3 => vec![0, 1, 2],
+            _ if load_options.triangulation_method == TriangulationMethod::EarClipping => {
+                triangulation::ear_clip(pos, &vertices)
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .collect()
+            }
+            n => (1..n - 1).flat_map(|i| [0, i, i + 1]).collect(),
+        };
+
+        for &i in &corners {
+            add_vertex_shared(&mut mesh, &vertices[i], pos, texcoord, normal)?;
+        }
+
+        let added = faces_added(&mesh, first_index, first_arity, load_options);
+        push_per_face(
+            &mut mesh.smoothing_groups,
+            added,
+            smoothing_group.unwrap_or(0),
+        );
+        if load_options.per_face_materials {
+            push_per_face(&mut mesh.face_materials, added, material_id);
+        }
+    }
+
+    if smoothing_groups.iter().all(Option::is_none) {
+        // Without any `s` statement the faces are not flat shaded, see
+        // `NormalOptions`.
+        mesh.smoothing_groups = Vec::new();
+    }
+
+    if load_options.per_face_materials {
+        mesh.material_id = uniform_material(&mesh.face_materials);
+    }
+
+    if is_all_triangles {
+        // This is a triangle-only mesh.
+        mesh.face_arities = Vec::new();
+    }
+
+    Ok(mesh)
+}
+
 #[cfg(feature = "reordering")]
 #[inline]
 fn reorder_data<T: ParseableV>(mesh: &mut Mesh<T>) {
+    let dimension = mesh.texcoord_dimension;
+    let vertex_count = mesh.positions.len() / 3;
     // If we have per face per vertex data for UVs ...
-    if mesh.positions.len() < mesh.texcoords.len() {
+    if vertex_count < mesh.texcoords.len() / dimension {
         mesh.texcoords = mesh
             .texcoord_indices
             .iter()
             .flat_map(|&index| {
-                let index = index as usize * 2;
-                IntoIterator::into_iter([mesh.texcoords[index], mesh.texcoords[index + 1]])
+                let index = index as usize * dimension;
+                mesh.texcoords[index..index + dimension].iter().copied()
             })
             .collect::<Vec<_>>();
-    } else {
-        assert!(mesh.texcoords.len() == mesh.positions.len());
-
-        let mut new_texcoords = vec![T::zero(); mesh.positions.len()];
+    } else if !mesh.texcoords.is_empty() {
+        let mut new_texcoords = vec![T::zero(); vertex_count * dimension];
         mesh.texcoord_indices
             .iter()
             .zip(&mesh.indices)
             .for_each(|(&texcoord_index, &index)| {
-                let texcoord_index = texcoord_index as usize * 2;
-                let index = index as usize * 2;
-                new_texcoords[index] = mesh.texcoords[texcoord_index];
-                new_texcoords[index + 1] = mesh.texcoords[texcoord_index + 1];
+                let texcoord_index = texcoord_index as usize * dimension;
+                let index = index as usize * dimension;
+                new_texcoords[index..index + dimension]
+                    .copy_from_slice(&mesh.texcoords[texcoord_index..texcoord_index + dimension]);
             });
 
         mesh.texcoords = new_texcoords;
@@ -1525,32 +3071,25 @@ fn reorder_data<T: ParseableV>(mesh: &mut Mesh<T>) {
     mesh.texcoord_indices = Vec::new();
 
     // If we have per face per vertex data for normals ...
-    if mesh.positions.len() < mesh.normals.len() {
+    if vertex_count < mesh.normals.len() / 3 {
         mesh.normals = mesh
             .normal_indices
             .iter()
             .flat_map(|&index| {
-                let index = index as usize * 2;
-                IntoIterator::into_iter([
-                    mesh.normals[index],
-                    mesh.normals[index + 1],
-                    mesh.normals[index + 2],
-                ])
+                let index = index as usize * 3;
+                mesh.normals[index..index + 3].iter().copied()
             })
             .collect::<Vec<_>>();
-    } else {
-        assert!(mesh.normals.len() == mesh.positions.len());
-
-        let mut new_normals = vec![T::zero(); mesh.positions.len()];
+    } else if !mesh.normals.is_empty() {
+        let mut new_normals = vec![T::zero(); vertex_count * 3];
         mesh.normal_indices
             .iter()
             .zip(&mesh.indices)
             .for_each(|(&normal_index, &index)| {
                 let normal_index = normal_index as usize * 3;
                 let index = index as usize * 3;
-                new_normals[index] = mesh.normals[normal_index];
-                new_normals[index + 1] = mesh.normals[normal_index + 1];
-                new_normals[index + 2] = mesh.normals[normal_index + 2];
+                new_normals[index..index + 3]
+                    .copy_from_slice(&mesh.normals[normal_index..normal_index + 3]);
             });
 
         mesh.normals = new_normals;
@@ -1605,440 +3144,890 @@ fn merge_identical_points<T: ParseableV, const N: usize>(
         .for_each(|vertex| *vertex = compressed_indices[*vertex as usize]);
 }
 
-/// Load the various objects specified in the `OBJ` file and any associated
-/// `MTL` file.
-///
-/// Returns a pair of `Vec`s containing the loaded models and materials from the
-/// file.
-///
-/// # Arguments
+/// The state of an `OBJ` file being parsed.
 ///
-/// * `load_options` – Governs on-the-fly processing of the mesh during loading.
-///   See [`LoadOptions`] for more information.
-pub fn load_obj<P, T: ParseableV>(file_name: P, load_options: &LoadOptions) -> LoadResult<T>
-where
-    P: AsRef<Path> + fmt::Debug,
-{
-    let file = match File::open(file_name.as_ref()) {
-        Ok(f) => f,
-        Err(_e) => {
-            #[cfg(feature = "log")]
-            log::error!("load_obj - failed to open {:?} due to {}", file_name, _e);
-            return Err(LoadError::OpenFileFailed);
-        }
-    };
-    let mut reader = BufReader::new(file);
-    load_obj_buf(&mut reader, load_options, |mat_path| {
-        let full_path = if let Some(parent) = file_name.as_ref().parent() {
-            parent.join(mat_path)
-        } else {
-            mat_path.to_owned()
-        };
+/// Shared by all `OBJ` loaders, which only differ in how they read lines and
+/// load material libraries.
+struct ObjParser<'a, T: ParseableV> {
+    load_options: &'a LoadOptions,
+    // whether meshes index into the vertex data of the whole file
+    shared_attributes: bool,
+    models: Vec<Model<T>>,
+    materials: Vec<Material>,
+    mat_map: HashMap<String, usize>,
+    mtlresult: Result<Vec<Material>, LoadError>,
 
-        self::load_mtl(full_path)
-    })
+    tmp_pos: Vec<T>,
+    tmp_v_color: Vec<T>,
+    tmp_weight: Vec<T>,
+    // only as long as the last vertex with a mask
+    tmp_mask: Vec<T>,
+    // vertex the next entry of a `#MRGB` block belongs to
+    mrgb_vertex: usize,
+    // padded to 3 components, see `texcoord_dimension`
+    tmp_texcoord: Vec<T>,
+    tmp_normal: Vec<T>,
+    tmp_faces: Vec<Face>,
+    tmp_primitives: Vec<Primitive>,
+    tmp_smoothing_groups: Vec<Option<u32>>,
+    free_form: FreeFormParser<T>,
+    tmp_face_materials: Vec<Option<usize>>,
+    // largest number of components of any `vt` so far, `0` before the first
+    texcoord_dimension: usize,
+    // line each of the `tmp_faces` was read from
+    tmp_face_lines: Vec<usize>,
+    // index of the first vertex defined since the last model
+    first_vertex: usize,
+    // name of the current object being parsed
+    name: String,
+    // material used by the current object being parsed
+    mat_id: Option<usize>,
+    // smoothing group of the faces currently being parsed, `None` before the
+    // first `s` statement
+    smoothing_group: Option<u32>,
+    // number of the line currently being parsed
+    line_number: usize,
+    // number of the line the statement currently being parsed starts on
+    statement_line: usize,
+    // statement continued on the next line with a backslash
+    continued: String,
+    diagnostics: Vec<Diagnostic>,
 }
 
-/// Load the materials defined in a `MTL` file.
-///
-/// Returns a pair with a `Vec` holding all loaded materials and a `HashMap`
-/// containing a mapping of material names to indices in the Vec.
-pub fn load_mtl<P>(file_name: P) -> MTLLoadResult
-where
-    P: AsRef<Path> + fmt::Debug,
-{
-    let file = match File::open(file_name.as_ref()) {
-        Ok(f) => f,
-        Err(_e) => {
-            #[cfg(feature = "log")]
-            log::error!("load_mtl - failed to open {:?} due to {}", file_name, _e);
-            return Err(LoadError::OpenFileFailed);
+impl<'a, T: ParseableV> ObjParser<'a, T> {
+    fn new(load_options: &'a LoadOptions) -> Result<Self, LoadError> {
+        if !load_options.is_valid() {
+            return Err(LoadErrorKind::InvalidLoadOptionConfig.into());
         }
-    };
-    let mut reader = BufReader::new(file);
-    load_mtl_buf(&mut reader)
-}
 
-/// Load the various meshes in an `OBJ` buffer.
-///
-/// This could e.g. be a network stream, a text file already in memory etc.
-///
-/// # Arguments
-///
-/// You must pass a `material_loader` function, which will return a material
-/// given a name.
-///
-/// A trivial material loader may just look at the file name and then call
-/// `load_mtl_buf` with the in-memory MTL file source.
-///
-/// Alternatively it could pass an `MTL` file in memory to `load_mtl_buf` to
-/// parse materials from some buffer.
-///
-/// * `load_options` – Governs on-the-fly processing of the mesh during loading.
-///   See [`LoadOptions`] for more information.
-///
-/// # Example
-/// The test for `load_obj_buf` includes the OBJ and MTL files as strings
-/// and uses a `Cursor` to provide a `BufRead` interface on the buffer.
-///
-/// ```
-/// use std::{env, fs::File, io::BufReader};
-///
-/// let dir = env::current_dir().unwrap();
-/// let mut cornell_box_obj = dir.clone();
-/// cornell_box_obj.push("obj/cornell_box.obj");
-/// let mut cornell_box_file = BufReader::new(File::open(cornell_box_obj.as_path()).unwrap());
-///
-/// let mut cornell_box_mtl1 = dir.clone();
-/// cornell_box_mtl1.push("obj/cornell_box.mtl");
-///
-/// let mut cornell_box_mtl2 = dir.clone();
-/// cornell_box_mtl2.push("obj/cornell_box2.mtl");
-///
-/// let m = tobj64::load_obj_buf::<_, _, f64>(
-///     &mut cornell_box_file,
-///     &tobj64::LoadOptions {
-///         triangulate: true,
-///         single_index: true,
-///         ..Default::default()
-///     },
-///     |p| match p.file_name().unwrap().to_str().unwrap() {
-///         "cornell_box.mtl" => {
-///             let f = File::open(cornell_box_mtl1.as_path()).unwrap();
-///             tobj64::load_mtl_buf(&mut BufReader::new(f))
-///         }
-///         "cornell_box2.mtl" => {
-///             let f = File::open(cornell_box_mtl2.as_path()).unwrap();
-///             tobj64::load_mtl_buf(&mut BufReader::new(f))
-///         }
-///         _ => unreachable!(),
-///     },
-/// );
-/// ```
-pub fn load_obj_buf<B, ML, T: ParseableV>(
-    reader: &mut B,
-    load_options: &LoadOptions,
-    material_loader: ML,
-) -> LoadResult<T>
-where
-    B: BufRead,
-    ML: Fn(&Path) -> MTLLoadResult,
-{
-    if !load_options.is_valid() {
-        return Err(LoadError::InvalidLoadOptionConfig);
+        Ok(Self {
+            load_options,
+            shared_attributes: false,
+            models: Vec::new(),
+            materials: Vec::new(),
+            mat_map: HashMap::new_map(),
+            mtlresult: Ok(Vec::new()),
+            tmp_pos: Vec::new(),
+            tmp_v_color: Vec::new(),
+            tmp_weight: Vec::new(),
+            tmp_mask: Vec::new(),
+            mrgb_vertex: 0,
+            tmp_texcoord: Vec::new(),
+            tmp_normal: Vec::new(),
+            tmp_faces: Vec::new(),
+            tmp_primitives: Vec::new(),
+            tmp_smoothing_groups: Vec::new(),
+            free_form: FreeFormParser::new(),
+            tmp_face_materials: Vec::new(),
+            texcoord_dimension: 0,
+            tmp_face_lines: Vec::new(),
+            first_vertex: 0,
+            name: "unnamed_object".to_owned(),
+            mat_id: None,
+            smoothing_group: None,
+            line_number: 0,
+            statement_line: 0,
+            continued: String::new(),
+            diagnostics: Vec::new(),
+        })
     }
 
-    let mut models = Vec::new();
-    let mut materials = Vec::new();
-    let mut mat_map = HashMap::new_map();
+    /// Create a parser whose meshes index into the [`Attributes`] of the file.
+    ///
+    /// Options that modify the vertex data of the meshes are not supported.
+    fn with_shared_attributes(load_options: &'a LoadOptions) -> Result<Self, LoadError> {
+        #[allow(unused_mut)]
+        let mut modifies_data = load_options.single_index
+            || load_options.generate_normals.is_some()
+            || load_options.tessellate.is_some();
+        #[cfg(feature = "merging")]
+        {
+            modifies_data = modifies_data || load_options.merge_identical_points;
+        }
+        #[cfg(feature = "reordering")]
+        {
+            modifies_data = modifies_data || load_options.reorder_data;
+        }
+        if modifies_data {
+            return Err(LoadErrorKind::InvalidLoadOptionConfig.into());
+        }
 
-    let mut tmp_pos = Vec::new();
-    let mut tmp_v_color = Vec::new();
-    let mut tmp_texcoord = Vec::new();
-    let mut tmp_normal = Vec::new();
-    let mut tmp_faces: Vec<Face> = Vec::new();
-    // name of the current object being parsed
-    let mut name = "unnamed_object".to_owned();
-    // material used by the current object being parsed
-    let mut mat_id = None;
-    let mut mtlresult = Ok(Vec::new());
+        Ok(Self {
+            shared_attributes: true,
+            ..Self::new(load_options)?
+        })
+    }
+
+    /// Parse the next line of the file.
+    ///
+    /// Returns the name of the material library to load if the line is an
+    /// `mtllib` statement. The loaded library has to be passed to
+    /// [`add_materials()`](ObjParser::add_materials).
+    fn parse_line(&mut self, line: &str) -> Result<Option<String>, LoadError> {
+        self.line_number += 1;
+        if self.continued.is_empty() {
+            self.statement_line = self.line_number;
+        }
+
+        // Any statement but a comment may continue on the next line after a
+        // backslash.
+        if !line.trim_start().starts_with('#') {
+            if let Some(start) = line.trim_end().strip_suffix('\\') {
+                self.continued.push_str(start);
+                self.continued.push(' ');
+                return Ok(None);
+            }
+        }
+        if self.continued.is_empty() {
+            return self
+                .parse_complete_line(line)
+                .map(|mtllib| mtllib.map(str::to_owned));
+        }
+
+        let mut continued = std::mem::take(&mut self.continued);
+        continued.push_str(line);
+        let result = self
+            .parse_complete_line(&continued)
+            .map(|mtllib| mtllib.map(str::to_owned));
+        // Keep the allocation for the next continued statement.
+        continued.clear();
+        self.continued = continued;
+        result
+    }
+
+    fn parse_complete_line<'l>(&mut self, line: &'l str) -> Result<Option<&'l str>, LoadError> {
+        // Errors are reported at the first line of a continued statement.
+        let line_number = self.statement_line;
+        match self.parse_statement(line) {
+            Err(e) if self.load_options.lenient => {
+                self.recover(line, e.at_line(line_number, Some(line)));
+                Ok(None)
+            }
+            result => result.map_err(|e| e.at_line(line_number, Some(line))),
+        }
+    }
 
-    for line in reader.lines() {
-        let (line, mut words) = match line {
-            Ok(ref line) => (&line[..], line[..].split_whitespace()),
-            Err(_e) => {
-                #[cfg(feature = "log")]
-                log::error!("load_obj - failed to read line due to {}", _e);
-                return Err(LoadError::ReadError);
+    /// Repair or skip a malformed statement when loading leniently.
+    ///
+    /// Vertex data is repaired rather than skipped so that the indices of all
+    /// following vertices stay intact.
+    fn recover(&mut self, line: &str, error: LoadError) {
+        let mut words = line.split_whitespace();
+        let severity = match (words.next(), error.kind()) {
+            (Some("v"), LoadErrorKind::PositionParseError) => {
+                repair_floatn(&mut words, &mut self.tmp_pos, 3);
+                let (color, weight) = parse_vertex_extras(words).unwrap_or_default();
+                self.add_vertex_extras(color, weight);
+                Severity::Warning
             }
+            (Some("vt"), LoadErrorKind::TexcoordParseError) => {
+                repair_floatn(&mut words, &mut self.tmp_texcoord, 3);
+                Severity::Warning
+            }
+            (Some("vn"), LoadErrorKind::NormalParseError) => {
+                repair_floatn(&mut words, &mut self.tmp_normal, 3);
+                Severity::Warning
+            }
+            // The position is valid, only the values after it are ignored.
+            (Some("v"), LoadErrorKind::VertexColorParseError) => {
+                self.add_vertex_extras(None, None);
+                Severity::Warning
+            }
+            (Some("s"), LoadErrorKind::SmoothingGroupParseError) => Severity::Warning,
+            _ => Severity::Error,
         };
-        match words.next() {
-            Some("#") | None => continue,
-            Some("v") => {
-                if !parse_floatn(&mut words, &mut tmp_pos, 3) {
-                    return Err(LoadError::PositionParseError);
+        self.report(severity, error);
+    }
+
+    /// Record the color and weight of the vertex parsed last.
+    fn add_vertex_extras(&mut self, color: Option<[T; 4]>, weight: Option<T>) {
+        let vertex = self.tmp_pos.len() / 3 - 1;
+        extend_per_vertex(
+            &mut self.tmp_v_color,
+            vertex..vertex + 1,
+            color.as_ref().map(|color| &color[..]),
+            &[T::one(); 4],
+        );
+        extend_per_vertex(
+            &mut self.tmp_weight,
+            vertex..vertex + 1,
+            weight.as_ref().map(slice::from_ref),
+            &[T::one()],
+        );
+    }
+
+    /// Decode a ZBrush `#MRGB` block, which holds the mask and color of the
+    /// next vertices of the file as eight hex digits `MMRRGGBB` each.
+    fn parse_mrgb(&mut self, words: SplitWhitespace) -> Result<(), LoadError> {
+        let vertex_count = self.tmp_pos.len() / 3;
+        for word in words {
+            if word.len() % 8 != 0 || !word.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(LoadErrorKind::VertexColorParseError.into());
+            }
+            for start in (0..word.len()).step_by(8) {
+                let vertex = self.mrgb_vertex;
+                if vertex >= vertex_count {
+                    return Err(LoadErrorKind::VertexColorParseError.into());
                 }
+                let [mask, r, g, b] = [0, 2, 4, 6].map(|i| {
+                    let byte = u8::from_str_radix(&word[start + i..start + i + 2], 16);
+                    mrgb_component::<T>(byte.unwrap_or_default())
+                });
 
-                // Add inline vertex colors if present.
-                parse_floatn(&mut words, &mut tmp_v_color, 3);
+                // Pad the colors up to the last vertex before replacing one.
+                extend_per_vertex(
+                    &mut self.tmp_v_color,
+                    vertex_count..vertex_count,
+                    Some(&[]),
+                    &[T::one(); 4],
+                );
+                self.tmp_v_color[vertex * 4..vertex * 4 + 4].copy_from_slice(&[
+                    r,
+                    g,
+                    b,
+                    mrgb_component(u8::MAX),
+                ]);
+                self.tmp_mask.resize(vertex, T::zero());
+                self.tmp_mask.push(mask);
+                self.mrgb_vertex += 1;
             }
-            Some("vt") => {
-                if !parse_floatn(&mut words, &mut tmp_texcoord, 2) {
-                    return Err(LoadError::TexcoordParseError);
+        }
+        Ok(())
+    }
+
+    /// Record a problem that does not abort loading.
+    fn report(&mut self, severity: Severity, error: LoadError) {
+        #[cfg(feature = "log")]
+        match severity {
+            Severity::Warning => log::warn!("load_obj - {}", error),
+            Severity::Error => log::error!("load_obj - {}", error),
+        }
+        self.diagnostics.push(Diagnostic { severity, error });
+    }
+
+    /// Turn a failure to read the next line into an error.
+    fn read_error(&self, e: io::Error) -> LoadError {
+        #[cfg(feature = "log")]
+        log::error!("load_obj - failed to read line due to {}", e);
+        LoadError::with_io_error(LoadErrorKind::ReadError, e).at_line(self.line_number + 1, None)
+    }
+
+    fn parse_statement<'l>(&mut self, line: &'l str) -> Result<Option<&'l str>, LoadError> {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("#") | None => {}
+            Some("#MRGB") => self.parse_mrgb(words)?,
+            Some("v") => {
+                if !parse_floatn(&mut words, &mut self.tmp_pos, 3) {
+                    return Err(LoadErrorKind::PositionParseError.into());
+                }
+                match parse_vertex_extras(words) {
+                    Some((color, weight)) => self.add_vertex_extras(color, weight),
+                    None => return Err(LoadErrorKind::VertexColorParseError.into()),
                 }
             }
+            Some("vt") => match parse_texcoord(&mut words, &mut self.tmp_texcoord) {
+                Some(dimension) => self.texcoord_dimension = self.texcoord_dimension.max(dimension),
+                None => return Err(LoadErrorKind::TexcoordParseError.into()),
+            },
             Some("vn") => {
-                if !parse_floatn(&mut words, &mut tmp_normal, 3) {
-                    return Err(LoadError::NormalParseError);
+                if !parse_floatn(&mut words, &mut self.tmp_normal, 3) {
+                    return Err(LoadErrorKind::NormalParseError.into());
                 }
             }
-            Some("f") | Some("l") => {
+            Some(keyword @ ("f" | "l" | "p")) => {
+                let separate = self.load_options.separate_lines_and_points;
+                let primitive = match keyword {
+                    "l" if separate => Primitive::Line,
+                    "p" if separate => Primitive::Points,
+                    "p" => return Ok(None),
+                    _ => Primitive::Face,
+                };
                 if !parse_face(
                     words,
-                    &mut tmp_faces,
-                    tmp_pos.len() / 3,
-                    tmp_texcoord.len() / 2,
-                    tmp_normal.len() / 3,
+                    &mut self.tmp_faces,
+                    self.tmp_pos.len() / 3,
+                    self.tmp_texcoord.len() / 3,
+                    self.tmp_normal.len() / 3,
                 ) {
-                    return Err(LoadError::FaceParseError);
+                    return Err(LoadErrorKind::FaceParseError.into());
                 }
+                self.face_added(primitive);
             }
+            Some(
+                keyword @ ("vp" | "cstype" | "deg" | "curv" | "curv2" | "surf" | "parm" | "trim"
+                | "hole" | "scrv" | "end" | "ctech" | "stech"),
+            ) => self.free_form.parse_statement(
+                keyword,
+                words,
+                self.tmp_pos.len() / 3,
+                self.tmp_texcoord.len() / 3,
+                self.tmp_normal.len() / 3,
+            )?,
+            // A malformed `s` statement turns smoothing off when loading
+            // leniently.
+            Some("s") => match parse_smoothing_group(words) {
+                Some(group) => self.smoothing_group = Some(group),
+                None => {
+                    self.smoothing_group = Some(0);
+                    return Err(LoadErrorKind::SmoothingGroupParseError.into());
+                }
+            },
             // Just treating object and group tags identically. Should there be different behavior
             // for them?
             Some("o") | Some("g") => {
                 // If we were already parsing an object then a new object name
                 // signals the end of the current one, so push it onto our list of objects
-                if !tmp_faces.is_empty() {
-                    models.push(Model::new(
-                        if load_options.single_index {
-                            export_faces(
-                                &tmp_pos,
-                                &tmp_v_color,
-                                &tmp_texcoord,
-                                &tmp_normal,
-                                &tmp_faces,
-                                mat_id,
-                                load_options,
-                            )?
-                        } else {
-                            export_faces_multi_index(
-                                &tmp_pos,
-                                &tmp_v_color,
-                                &tmp_texcoord,
-                                &tmp_normal,
-                                &tmp_faces,
-                                mat_id,
-                                load_options,
-                            )?
-                        },
-                        name,
-                    ));
-                    tmp_faces.clear();
+                if self.has_model_data() {
+                    let name = std::mem::take(&mut self.name);
+                    self.push_model(name)?;
                 }
+                let line = line.trim_start();
                 let size = line.chars().next().unwrap().len_utf8();
-                name = line[size..].trim().to_owned();
-                if name.is_empty() {
-                    name = "unnamed_object".to_owned();
-                }
-            }
-            Some("mtllib") => {
-                if let Some(mtllib) = words.next() {
-                    let mat_file = Path::new(mtllib).to_path_buf();
-                    match material_loader(mat_file.as_path()) {
-                        Ok((mut mats, map)) => {
-                            // Merge the loaded material lib with any currently loaded ones,
-                            // offsetting the indices of the appended
-                            // materials by our current length
-                            let mat_offset = materials.len();
-                            materials.append(&mut mats);
-                            for m in map {
-                                mat_map.insert(m.0, m.1 + mat_offset);
-                            }
-                        }
-                        Err(e) => {
-                            mtlresult = Err(e);
-                        }
-                    }
-                } else {
-                    return Err(LoadError::MaterialParseError);
+                self.name = line[size..].trim().to_owned();
+                if self.name.is_empty() {
+                    self.name = "unnamed_object".to_owned();
                 }
             }
+            Some("mtllib") => match words.next() {
+                Some(mtllib) => return Ok(Some(mtllib)),
+                None => return Err(LoadErrorKind::MaterialParseError.into()),
+            },
             Some("usemtl") => {
-                let mat_name = line.split_once(' ').unwrap_or_default().1.trim().to_owned();
+                let mat_name = line.trim().split_once(' ').unwrap_or_default().1.trim();
 
                 if !mat_name.is_empty() {
-                    let new_mat = mat_map.get(&mat_name).cloned();
+                    let new_mat = self.mat_map.get(mat_name).cloned();
                     // As materials are returned per-model, a new material within an object
                     // has to emit a new model with the same name but different material
-                    if mat_id != new_mat && !tmp_faces.is_empty() {
-                        models.push(Model::new(
-                            if load_options.single_index {
-                                export_faces(
-                                    &tmp_pos,
-                                    &tmp_v_color,
-                                    &tmp_texcoord,
-                                    &tmp_normal,
-                                    &tmp_faces,
-                                    mat_id,
-                                    load_options,
-                                )?
-                            } else {
-                                export_faces_multi_index(
-                                    &tmp_pos,
-                                    &tmp_v_color,
-                                    &tmp_texcoord,
-                                    &tmp_normal,
-                                    &tmp_faces,
-                                    mat_id,
-                                    load_options,
-                                )?
-                            },
-                            name.clone(),
-                        ));
-                        tmp_faces.clear();
+                    if self.mat_id != new_mat
+                        && self.has_model_data()
+                        && !self.load_options.per_face_materials
+                    {
+                        self.push_model(self.name.clone())?;
                     }
-                    if new_mat.is_none() {
-                        #[cfg(feature = "log")]
-                        log::warn!("Object {} refers to unfound material: {}", name, mat_name);
+                    if new_mat.is_none() && mat_name != NO_MATERIAL {
+                        let error = LoadError::new(LoadErrorKind::MaterialNotFound)
+                            .at_line(self.statement_line, Some(line));
+                        self.report(Severity::Warning, error);
                     }
-                    mat_id = new_mat;
+                    self.mat_id = new_mat;
                 } else {
-                    return Err(LoadError::MaterialParseError);
+                    return Err(LoadErrorKind::MaterialParseError.into());
                 }
             }
             // Just ignore unrecognized characters
             Some(_) => {}
         }
+
+        Ok(None)
+    }
+
+    /// Whether anything was parsed that would end up in a new model.
+    fn has_model_data(&self) -> bool {
+        !self.tmp_faces.is_empty()
+            || self.free_form.has_object_data()
+            || (self.load_options.keep_unreferenced_vertices
+                && !self.shared_attributes
+                && self.first_vertex < self.tmp_pos.len() / 3)
+    }
+
+    /// Record the kind and the state the face added last was read in.
+    fn face_added(&mut self, primitive: Primitive) {
+        self.tmp_primitives.push(primitive);
+        self.tmp_smoothing_groups.push(self.smoothing_group);
+        self.tmp_face_materials.push(self.mat_id);
+        self.tmp_face_lines.push(self.statement_line);
+    }
+
+    /// Merge a loaded material library with any currently loaded ones.
+    fn add_materials(&mut self, result: MTLLoadResult) {
+        match result {
+            Ok((mut mats, map)) => {
+                // Offset the indices of the appended materials by our current
+                // length.
+                let mat_offset = self.materials.len();
+                self.materials.append(&mut mats);
+                for m in map {
+                    self.mat_map.insert(m.0, m.1 + mat_offset);
+                }
+            }
+            Err(e) => {
+                self.mtlresult = Err(e);
+            }
+        }
     }
 
-    // For the last object in the file we won't encounter another object name to
-    // tell us when it's done, so if we're parsing an object push the last one
-    // on the list as well
-    models.push(Model::new(
-        if load_options.single_index {
+    /// Export the faces parsed since the last model as a new model.
+    fn push_model(&mut self, name: String) -> Result<(), LoadError> {
+        if self.load_options.lenient {
+            self.skip_invalid_faces();
+        }
+
+        let result = if self.shared_attributes {
+            export_faces_shared(
+                &self.tmp_pos,
+                &self.tmp_texcoord,
+                &self.tmp_normal,
+                &self.tmp_faces,
+                &self.tmp_primitives,
+                &self.tmp_smoothing_groups,
+                &self.tmp_face_materials,
+                self.mat_id,
+                self.load_options,
+            )
+        } else if self.load_options.single_index {
             export_faces(
-                &tmp_pos,
-                &tmp_v_color,
-                &tmp_texcoord,
-                &tmp_normal,
-                &tmp_faces,
-                mat_id,
-                load_options,
-            )?
+                &self.tmp_pos,
+                &self.tmp_v_color,
+                &self.tmp_weight,
+                &self.tmp_mask,
+                &self.tmp_texcoord,
+                &self.tmp_normal,
+                &self.tmp_faces,
+                &self.tmp_primitives,
+                &self.tmp_smoothing_groups,
+                &self.tmp_face_materials,
+                self.mat_id,
+                self.load_options,
+            )
         } else {
             export_faces_multi_index(
-                &tmp_pos,
-                &tmp_v_color,
-                &tmp_texcoord,
-                &tmp_normal,
-                &tmp_faces,
-                mat_id,
-                load_options,
-            )?
-        },
-        name,
-    ));
+                &self.tmp_pos,
+                &self.tmp_v_color,
+                &self.tmp_weight,
+                &self.tmp_mask,
+                &self.tmp_texcoord,
+                &self.tmp_normal,
+                &self.tmp_faces,
+                &self.tmp_primitives,
+                &self.tmp_smoothing_groups,
+                &self.tmp_face_materials,
+                self.mat_id,
+                self.load_options,
+            )
+        }
+        .map_err(|e| self.locate_face_error(e))
+        .map(|mut mesh| {
+            mesh.texcoord_dimension = self.mesh_texcoord_dimension();
+            truncate_texcoords(&mut mesh.texcoords, mesh.texcoord_dimension);
+            let vertices = self.first_vertex..self.tmp_pos.len() / 3;
+            if self.load_options.keep_unreferenced_vertices && !self.shared_attributes {
+                add_unreferenced_vertices(
+                    &mut mesh,
+                    &self.tmp_faces,
+                    &self.tmp_primitives,
+                    vertices,
+                    &self.tmp_pos,
+                    &self.tmp_v_color,
+                    &self.tmp_weight,
+                    &self.tmp_mask,
+                    self.load_options,
+                );
+            }
+            mesh
+        });
+        let free_form = self.free_form.take_object(
+            &self.tmp_pos,
+            &self.tmp_weight,
+            &self.tmp_texcoord,
+            &self.tmp_normal,
+        );
 
-    if !materials.is_empty() {
-        mtlresult = Ok(materials);
+        self.first_vertex = self.tmp_pos.len() / 3;
+        self.tmp_faces.clear();
+        self.tmp_primitives.clear();
+        self.tmp_smoothing_groups.clear();
+        self.tmp_face_materials.clear();
+        self.tmp_face_lines.clear();
+
+        match result.and_then(|mesh| Ok((mesh, free_form?))) {
+            Ok((mesh, free_form)) => {
+                let tessellated = match self.load_options.tessellate {
+                    Some(options) if !free_form.is_empty() => Some(tessellate_free_form(
+                        &free_form,
+                        &options,
+                        self.mat_id,
+                        self.load_options,
+                    )),
+                    _ => None,
+                };
+                match tessellated {
+                    Some(tessellated) if mesh.positions.is_empty() => self.models.push(Model {
+                        mesh: tessellated,
+                        free_form,
+                        name,
+                    }),
+                    Some(tessellated) => {
+                        self.models.push(Model::new(mesh, name.clone()));
+                        self.models.push(Model {
+                            mesh: tessellated,
+                            free_form,
+                            name,
+                        });
+                    }
+                    None => self.models.push(Model {
+                        mesh,
+                        free_form,
+                        name,
+                    }),
+                }
+            }
+            Err(e) if self.load_options.lenient => self.report(Severity::Error, e),
+            Err(e) => return Err(e),
+        }
+        Ok(())
     }
 
-    Ok((models, mtlresult))
-}
+    /// Drop the faces that refer to vertex data that does not exist, reporting
+    /// each of them.
+    fn skip_invalid_faces(&mut self) {
+        let mut valid = 0;
+        for i in 0..self.tmp_faces.len() {
+            let error = [
+                LoadErrorKind::FaceVertexOutOfBounds,
+                LoadErrorKind::FaceTexCoordOutOfBounds,
+                LoadErrorKind::FaceNormalOutOfBounds,
+                LoadErrorKind::FaceColorOutOfBounds,
+            ]
+            .iter()
+            .copied()
+            .find(|&kind| {
+                self.tmp_faces[i]
+                    .vertices()
+                    .iter()
+                    .any(|v| self.is_out_of_bounds(v, kind))
+            });
 
-/// Load the various materials in a `MTL` buffer.
-pub fn load_mtl_buf<B: BufRead>(reader: &mut B) -> MTLLoadResult {
-    let mut materials = Vec::new();
-    let mut mat_map = HashMap::new_map();
-    // The current material being parsed
-    let mut cur_mat = Material::default();
-    for line in reader.lines() {
-        let (line, mut words) = match line {
-            Ok(ref line) => (line.trim(), line[..].split_whitespace()),
-            Err(_e) => {
-                #[cfg(feature = "log")]
-                log::error!("load_obj - failed to read line due to {}", _e);
-                return Err(LoadError::ReadError);
+            match error {
+                Some(kind) => {
+                    let error = LoadError::new(kind).at_line(self.tmp_face_lines[i], None);
+                    self.report(Severity::Error, error);
+                }
+                None => {
+                    self.tmp_faces.swap(valid, i);
+                    self.tmp_primitives.swap(valid, i);
+                    self.tmp_smoothing_groups.swap(valid, i);
+                    self.tmp_face_materials.swap(valid, i);
+                    self.tmp_face_lines.swap(valid, i);
+                    valid += 1;
+                }
+            }
+        }
+        self.tmp_faces.truncate(valid);
+        self.tmp_primitives.truncate(valid);
+        self.tmp_smoothing_groups.truncate(valid);
+        self.tmp_face_materials.truncate(valid);
+        self.tmp_face_lines.truncate(valid);
+    }
+
+    /// Attach the line of the face that caused an error during export.
+    ///
+    /// Face indices are only checked once an object is complete, as they may
+    /// refer to vertices that are defined later in the file.
+    fn locate_face_error(&self, error: LoadError) -> LoadError {
+        match self.tmp_faces.iter().position(|face| {
+            face.vertices()
+                .iter()
+                .any(|v| self.is_out_of_bounds(v, error.kind()))
+        }) {
+            Some(face) => error.at_line(self.tmp_face_lines[face], None),
+            None => error,
+        }
+    }
+
+    /// The number of components of the texture coordinates of a mesh
+    /// completed now.
+    fn mesh_texcoord_dimension(&self) -> usize {
+        match self.texcoord_dimension {
+            0 => 2,
+            dimension => dimension,
+        }
+    }
+
+    /// Whether a face vertex causes an error of the given kind on export.
+    fn is_out_of_bounds(&self, v: &VertexIndices, kind: LoadErrorKind) -> bool {
+        let texcoords = self.tmp_texcoord.len() / 3;
+        let normals = self.tmp_normal.len() / 3;
+        let colors = self.tmp_v_color.len() / 4;
+
+        match kind {
+            LoadErrorKind::FaceVertexOutOfBounds => v.v >= self.tmp_pos.len() / 3,
+            LoadErrorKind::FaceTexCoordOutOfBounds => {
+                texcoords != 0 && v.vt != MISSING_INDEX && v.vt >= texcoords
             }
+            LoadErrorKind::FaceNormalOutOfBounds => {
+                normals != 0 && v.vn != MISSING_INDEX && v.vn >= normals
+            }
+            LoadErrorKind::FaceColorOutOfBounds => colors > 1 && v.v >= colors,
+            _ => false,
+        }
+    }
+
+    fn finish(mut self) -> LoadResultWithDiagnostics<T> {
+        self.push_last_model()?;
+        Ok((self.models, self.mtlresult, self.diagnostics))
+    }
+
+    fn finish_with_attributes(mut self) -> LoadResultWithAttributes<T> {
+        self.push_last_model()?;
+        let texcoord_dimension = self.mesh_texcoord_dimension();
+        truncate_texcoords(&mut self.tmp_texcoord, texcoord_dimension);
+        if !self.tmp_mask.is_empty() {
+            self.tmp_mask.resize(self.tmp_pos.len() / 3, T::zero());
+        }
+        let attributes = Attributes {
+            positions: self.tmp_pos,
+            vertex_color: self.tmp_v_color,
+            weights: self.tmp_weight,
+            vertex_mask: self.tmp_mask,
+            normals: self.tmp_normal,
+            texcoords: self.tmp_texcoord,
+            texcoord_dimension,
         };
+        Ok((self.models, self.mtlresult, attributes))
+    }
+
+    fn push_last_model(&mut self) -> Result<(), LoadError> {
+        // A backslash on the last line continues the statement with nothing.
+        if !self.continued.is_empty() {
+            let continued = std::mem::take(&mut self.continued);
+            self.parse_complete_line(&continued)?;
+        }
+
+        // For the last object in the file we won't encounter another object name to
+        // tell us when it's done, so if we're parsing an object push the last one
+        // on the list as well
+        let name = std::mem::take(&mut self.name);
+        self.push_model(name)?;
+
+        if !self.materials.is_empty() {
+            self.mtlresult = Ok(std::mem::take(&mut self.materials));
+        }
+        Ok(())
+    }
+}
+
+/// The state of an `MTL` file being parsed.
+struct MtlParser {
+    materials: Vec<Material>,
+    mat_map: HashMap<String, usize>,
+    // The current material being parsed
+    cur_mat: Material,
+    // whether the current material has a `d` statement, overriding `Tr`
+    has_dissolve: bool,
+    // number of the line currently being parsed
+    line_number: usize,
+}
+
+impl MtlParser {
+    fn new() -> Self {
+        Self {
+            materials: Vec::new(),
+            mat_map: HashMap::new_map(),
+            cur_mat: Material::default(),
+            has_dissolve: false,
+            line_number: 0,
+        }
+    }
+
+    /// Parse the next line of the file.
+    fn parse_line(&mut self, line: &str) -> Result<(), LoadError> {
+        self.line_number += 1;
+        let line_number = self.line_number;
+        self.parse_statement(line.trim())
+            .map_err(|e| e.at_line(line_number, Some(line)))
+    }
+
+    /// Turn a failure to read the next line into an error.
+    fn read_error(&self, e: io::Error) -> LoadError {
+        #[cfg(feature = "log")]
+        log::error!("load_mtl - failed to read line due to {}", e);
+        LoadError::with_io_error(LoadErrorKind::ReadError, e).at_line(self.line_number + 1, None)
+    }
+
+    fn parse_statement(&mut self, line: &str) -> Result<(), LoadError> {
+        let cur_mat = &mut self.cur_mat;
+        let mut words = line.split_whitespace();
 
         match words.next() {
-            Some("#") | None => continue,
+            Some("#") | None => {}
             Some("newmtl") => {
                 // If we were passing a material save it out to our vector
-                if !cur_mat.name.is_empty() {
-                    mat_map.insert(cur_mat.name.clone(), materials.len());
-                    materials.push(cur_mat);
-                }
-                cur_mat = Material::default();
-                cur_mat.name = line[6..].trim().to_owned();
-                if cur_mat.name.is_empty() {
-                    return Err(LoadError::InvalidObjectName);
-                }
-            }
-            Some("Ka") => {
-                if !parse_float3(words, &mut cur_mat.ambient) {
-                    return Err(LoadError::MaterialParseError);
-                }
-            }
-            Some("Kd") => {
-                if !parse_float3(words, &mut cur_mat.diffuse) {
-                    return Err(LoadError::MaterialParseError);
-                }
-            }
-            Some("Ks") => {
-                if !parse_float3(words, &mut cur_mat.specular) {
-                    return Err(LoadError::MaterialParseError);
+                self.push_material();
+                self.cur_mat.name = line[6..].trim().to_owned();
+                if self.cur_mat.name.is_empty() {
+                    return Err(LoadErrorKind::InvalidObjectName.into());
                 }
             }
+            Some("Ka") => parse_color(
+                &line[2..],
+                &mut cur_mat.ambient,
+                &mut cur_mat.ambient_source,
+            )?,
+            Some("Kd") => parse_color(
+                &line[2..],
+                &mut cur_mat.diffuse,
+                &mut cur_mat.diffuse_source,
+            )?,
+            Some("Ks") => parse_color(
+                &line[2..],
+                &mut cur_mat.specular,
+                &mut cur_mat.specular_source,
+            )?,
             Some("Ns") => {
                 if let Some(p) = words.next() {
                     match FromStr::from_str(p) {
                         Ok(x) => cur_mat.shininess = x,
-                        Err(_) => return Err(LoadError::MaterialParseError),
+                        Err(_) => return Err(LoadErrorKind::MaterialParseError.into()),
                     }
                 } else {
-                    return Err(LoadError::MaterialParseError);
+                    return Err(LoadErrorKind::MaterialParseError.into());
                 }
             }
             Some("Ni") => {
                 if let Some(p) = words.next() {
                     match FromStr::from_str(p) {
                         Ok(x) => cur_mat.optical_density = x,
-                        Err(_) => return Err(LoadError::MaterialParseError),
+                        Err(_) => return Err(LoadErrorKind::MaterialParseError.into()),
                     }
                 } else {
-                    return Err(LoadError::MaterialParseError);
+                    return Err(LoadErrorKind::MaterialParseError.into());
                 }
             }
             Some("d") => {
-                if let Some(p) = words.next() {
-                    match FromStr::from_str(p) {
-                        Ok(x) => cur_mat.dissolve = x,
-                        Err(_) => return Err(LoadError::MaterialParseError),
-                    }
-                } else {
-                    return Err(LoadError::MaterialParseError);
+                let mut value = words.next();
+                cur_mat.dissolve_halo = value == Some("-halo");
+                if cur_mat.dissolve_halo {
+                    value = words.next();
                 }
+                match value.map(str::parse) {
+                    Some(Ok(x)) => cur_mat.dissolve = x,
+                    _ => return Err(LoadErrorKind::MaterialParseError.into()),
+                }
+                self.has_dissolve = true;
+            }
+            Some("Tr") => {
+                let transparency = parse_float(words)?;
+                if !self.has_dissolve {
+                    cur_mat.dissolve = 1.0 - transparency;
+                }
+            }
+            Some("Tf") => {
+                let mut filter = [0.0; 3];
+                parse_color(
+                    &line[2..],
+                    &mut filter,
+                    &mut cur_mat.transmission_filter_source,
+                )?;
+                cur_mat.transmission_filter = match cur_mat.transmission_filter_source {
+                    Some(_) => None,
+                    None => Some(filter),
+                };
             }
+            Some("sharpness") => cur_mat.sharpness = Some(parse_float(words)?),
             Some("map_Ka") => match line.get(6..).map(str::trim) {
-                Some("") | None => return Err(LoadError::MaterialParseError),
-                Some(tex) => cur_mat.ambient_texture = tex.to_owned(),
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.ambient_texture = tex.parse()?,
             },
             Some("map_Kd") => match line.get(6..).map(str::trim) {
-                Some("") | None => return Err(LoadError::MaterialParseError),
-                Some(tex) => cur_mat.diffuse_texture = tex.to_owned(),
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.diffuse_texture = tex.parse()?,
             },
             Some("map_Ks") => match line.get(6..).map(str::trim) {
-                Some("") | None => return Err(LoadError::MaterialParseError),
-                Some(tex) => cur_mat.specular_texture = tex.to_owned(),
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.specular_texture = tex.parse()?,
             },
             Some("map_Bump") | Some("map_bump") => match line.get(8..).map(str::trim) {
-                Some("") | None => return Err(LoadError::MaterialParseError),
-                Some(tex) => cur_mat.normal_texture = tex.to_owned(),
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.normal_texture = tex.parse()?,
             },
             Some("map_Ns") | Some("map_ns") | Some("map_NS") => {
                 match line.get(6..).map(str::trim) {
-                    Some("") | None => return Err(LoadError::MaterialParseError),
-                    Some(tex) => cur_mat.shininess_texture = tex.to_owned(),
+                    Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                    Some(tex) => cur_mat.shininess_texture = tex.parse()?,
                 }
             }
             Some("bump") => match line.get(4..).map(str::trim) {
-                Some("") | None => return Err(LoadError::MaterialParseError),
-                Some(tex) => cur_mat.normal_texture = tex.to_owned(),
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.normal_texture = tex.parse()?,
             },
             Some("map_d") => match line.get(5..).map(str::trim) {
-                Some("") | None => return Err(LoadError::MaterialParseError),
-                Some(tex) => cur_mat.dissolve_texture = tex.to_owned(),
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.dissolve_texture = tex.parse()?,
+            },
+            Some("norm") => match line.get(4..).map(str::trim) {
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.pbr_normal_texture = tex.parse()?,
+            },
+            Some("disp") => match line.get(4..).map(str::trim) {
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.displacement_texture = tex.parse()?,
+            },
+            Some("decal") => match line.get(5..).map(str::trim) {
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.decal_texture = tex.parse()?,
+            },
+            Some("refl") => match line.get(4..).map(str::trim) {
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => {
+                    let tex: Texture = tex.parse()?;
+                    let target = match tex.options.map_type.as_deref() {
+                        None | Some("sphere") => &mut cur_mat.reflection_texture,
+                        Some(map_type) => cur_mat
+                            .reflection_cube
+                            .face_mut(map_type)
+                            .ok_or(LoadErrorKind::MaterialParseError)?,
+                    };
+                    *target = tex;
+                }
+            },
+            Some("map_aat") => {
+                cur_mat.texture_anti_aliasing = match words.next() {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => return Err(LoadErrorKind::MaterialParseError.into()),
+                }
+            }
+            Some("Pr") => cur_mat.roughness = Some(parse_float(words)?),
+            Some("Pm") => cur_mat.metallic = Some(parse_float(words)?),
+            Some("Ps") => cur_mat.sheen = Some(parse_float(words)?),
+            Some("Pc") => cur_mat.clearcoat_thickness = Some(parse_float(words)?),
+            Some("Pcr") => cur_mat.clearcoat_roughness = Some(parse_float(words)?),
+            Some("Ke") => {
+                let mut emissive = [0.0; 3];
+                parse_color(&line[2..], &mut emissive, &mut cur_mat.emissive_source)?;
+                cur_mat.emissive = match cur_mat.emissive_source {
+                    Some(_) => None,
+                    None => Some(emissive),
+                };
+            }
+            Some("aniso") => cur_mat.anisotropy = Some(parse_float(words)?),
+            Some("anisor") => cur_mat.anisotropy_rotation = Some(parse_float(words)?),
+            Some("map_Pr") => match line.get(6..).map(str::trim) {
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.roughness_texture = tex.parse()?,
+            },
+            Some("map_Pm") => match line.get(6..).map(str::trim) {
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.metallic_texture = tex.parse()?,
+            },
+            Some("map_Ps") => match line.get(6..).map(str::trim) {
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.sheen_texture = tex.parse()?,
+            },
+            Some("map_Ke") => match line.get(6..).map(str::trim) {
+                Some("") | None => return Err(LoadErrorKind::MaterialParseError.into()),
+                Some(tex) => cur_mat.emissive_texture = tex.parse()?,
             },
             Some("illum") => {
                 if let Some(p) = words.next() {
                     match FromStr::from_str(p) {
                         Ok(x) => cur_mat.illumination_model = Some(x),
-                        Err(_) => return Err(LoadError::MaterialParseError),
+                        Err(_) => return Err(LoadErrorKind::MaterialParseError.into()),
                     }
                 } else {
-                    return Err(LoadError::MaterialParseError);
+                    return Err(LoadErrorKind::MaterialParseError.into());
                 }
             }
             Some(unknown) => {
@@ -2048,49 +4037,702 @@ pub fn load_mtl_buf<B: BufRead>(reader: &mut B) -> MTLLoadResult {
                 }
             }
         }
+
+        Ok(())
     }
 
-    // Finalize the last material we were parsing
-    if !cur_mat.name.is_empty() {
-        mat_map.insert(cur_mat.name.clone(), materials.len());
-        materials.push(cur_mat);
+    /// Save out the material parsed so far, if any, and start a new one.
+    fn push_material(&mut self) {
+        self.has_dissolve = false;
+        let cur_mat = std::mem::take(&mut self.cur_mat);
+        if !cur_mat.name.is_empty() {
+            self.mat_map
+                .insert(cur_mat.name.clone(), self.materials.len());
+            self.materials.push(cur_mat);
+        }
     }
 
-    Ok((materials, mat_map))
+    fn finish(mut self) -> MTLLoadResult {
+        // Finalize the last material we were parsing
+        self.push_material();
+
+        Ok((self.materials, self.mat_map))
+    }
 }
 
-#[cfg(feature = "async")]
-/// Load the various meshes in an `OBJ` buffer.
+/// Load the various objects specified in the `OBJ` file and any associated
+/// `MTL` file.
 ///
-/// This could e.g. be a text file already in memory, a file loaded
-///  asynchronously over the network etc.
+/// Returns a pair of `Vec`s containing the loaded models and materials from the
+/// file.
 ///
 /// # Arguments
 ///
-/// You must pass a `material_loader` function, which will return a future
-/// that loads a material given a name.
-///
-/// A trivial material loader may just look at the file name and then call
-/// `load_mtl_buf` with the in-memory MTL file source.
-///
-/// Alternatively it could pass an `MTL` file in memory to `load_mtl_buf` to
-/// parse materials from some buffer.
-///
 /// * `load_options` – Governs on-the-fly processing of the mesh during loading.
 ///   See [`LoadOptions`] for more information.
+pub fn load_obj<P, T: ParseableV>(file_name: P, load_options: &LoadOptions) -> LoadResult<T>
+where
+    P: AsRef<Path> + fmt::Debug,
+{
+    load_obj_with_diagnostics(file_name, load_options)
+        .map(|(models, materials, _)| (models, materials))
+}
+
+/// Load the various objects specified in the `OBJ` file and any associated
+/// `MTL` file, collecting [`Diagnostic`]s along the way.
+///
+/// This is the same as [`load_obj()`] but additionally returns the problems
+/// that did not abort loading. Combine it with
+/// [`lenient`](LoadOptions::lenient) to load files with malformed statements.
+pub fn load_obj_with_diagnostics<P, T: ParseableV>(
+    file_name: P,
+    load_options: &LoadOptions,
+) -> LoadResultWithDiagnostics<T>
+where
+    P: AsRef<Path> + fmt::Debug,
+{
+    let mut reader = open_obj(&file_name)?;
+    load_obj_buf_with_diagnostics(&mut reader, load_options, |mat_path| {
+        load_mtl_next_to(file_name.as_ref(), mat_path)
+    })
+    .map(|(models, materials, diagnostics)| {
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|d| Diagnostic {
+                error: d.error.in_file(file_name.as_ref()),
+                ..d
+            })
+            .collect();
+        (models, materials, diagnostics)
+    })
+    .map_err(|e| e.in_file(file_name.as_ref()))
+}
+
+/// Load the various objects specified in the `OBJ` file, with the associated
+/// `MTL` files loaded by `material_loader`.
+///
+/// Unlike [`load_obj()`], which looks for material libraries next to the
+/// `OBJ` file, this passes the path of each `mtllib` statement to
+/// `material_loader` as it is written in the file.
+///
+/// # Example
+///
+/// ```
+/// use tobj64::{CachingLoader, SearchPathLoader};
+///
+/// // Parse libraries shared by many files once, wherever they are.
+/// let loader = CachingLoader::new(SearchPathLoader::new(["obj", "materials"]));
+/// let (models, materials) = tobj64::load_obj_with_loader::<_, _, f32>(
+///     "obj/cornell_box.obj",
+///     &tobj64::LoadOptions::default(),
+///     &loader,
+/// )
+/// .unwrap();
+/// # assert!(!models.is_empty());
+/// # assert!(!materials.unwrap().is_empty());
+/// ```
+pub fn load_obj_with_loader<P, ML, T: ParseableV>(
+    file_name: P,
+    load_options: &LoadOptions,
+    material_loader: &ML,
+) -> LoadResult<T>
+where
+    P: AsRef<Path> + fmt::Debug,
+    ML: MaterialLoader + ?Sized,
+{
+    let mut reader = open_obj(&file_name)?;
+    load_obj_buf(&mut reader, load_options, |mat_path| {
+        material_loader.load(mat_path)
+    })
+    .map_err(|e| e.in_file(file_name.as_ref()))
+}
+
+/// Load the various objects specified in the `OBJ` file and any associated
+/// `MTL` file, keeping the vertex data of the whole file in one place.
+///
+/// Rather than each [`Mesh`] holding a copy of the vertex data it uses, the
+/// returned [`Attributes`] contain all positions, vertex colors, texture
+/// coordinates and normals in the order they are specified in the file. The
+/// [`indices`](Mesh::indices), [`texcoord_indices`](Mesh::texcoord_indices)
+/// and [`normal_indices`](Mesh::normal_indices) of each mesh index into them,
+/// so they map directly back to the statements of the file. The `Vec`s
+/// holding vertex data in each `Mesh` are empty.
+///
+/// The [`single_index`](LoadOptions::single_index),
+/// [`merge_identical_points`](LoadOptions::merge_identical_points),
+/// [`reorder_data`](LoadOptions::reorder_data) and
+/// [`generate_normals`](LoadOptions::generate_normals) options would modify the
+/// vertex data and lead to an
+/// [`InvalidLoadOptionConfig`](LoadErrorKind::InvalidLoadOptionConfig) error.
+///
+/// # Example
+///
+/// ```
+/// let (models, _, attributes) = tobj64::load_obj_with_attributes::<_, f32>(
+///     "obj/cornell_box.obj",
+///     &tobj64::LoadOptions::default(),
+/// )
+/// .unwrap();
+///
+/// // Position of the first vertex of the first face, as specified in the file.
+/// let i = models[0].mesh.indices[0] as usize;
+/// let pos = &attributes.positions[i * 3..i * 3 + 3];
+/// # assert_eq!(pos, [552.8, 0.0, 0.0]);
+/// ```
+pub fn load_obj_with_attributes<P, T: ParseableV>(
+    file_name: P,
+    load_options: &LoadOptions,
+) -> LoadResultWithAttributes<T>
+where
+    P: AsRef<Path> + fmt::Debug,
+{
+    let mut reader = open_obj(&file_name)?;
+    load_obj_buf_with_attributes(&mut reader, load_options, |mat_path| {
+        load_mtl_next_to(file_name.as_ref(), mat_path)
+    })
+    .map_err(|e| e.in_file(file_name.as_ref()))
+}
+
+/// Open an `OBJ` file for reading.
+fn open_obj<P>(file_name: &P) -> Result<BufReader<File>, LoadError>
+where
+    P: AsRef<Path> + fmt::Debug,
+{
+    match File::open(file_name.as_ref()) {
+        Ok(f) => Ok(BufReader::new(f)),
+        Err(e) => {
+            #[cfg(feature = "log")]
+            log::error!("load_obj - failed to open {:?} due to {}", file_name, e);
+            Err(LoadError::with_io_error(LoadErrorKind::OpenFileFailed, e)
+                .in_file(file_name.as_ref()))
+        }
+    }
+}
+
+/// Load a material library referenced by an `OBJ` file, relative to the
+/// directory of the `OBJ` file.
+fn load_mtl_next_to(obj_file: &Path, mat_path: &Path) -> MTLLoadResult {
+    let full_path = if let Some(parent) = obj_file.parent() {
+        parent.join(mat_path)
+    } else {
+        mat_path.to_owned()
+    };
+
+    self::load_mtl(full_path)
+}
+
+/// Load the materials defined in a `MTL` file.
+///
+/// Returns a pair with a `Vec` holding all loaded materials and a `HashMap`
+/// containing a mapping of material names to indices in the Vec.
+pub fn load_mtl<P>(file_name: P) -> MTLLoadResult
+where
+    P: AsRef<Path> + fmt::Debug,
+{
+    let file = match File::open(file_name.as_ref()) {
+        Ok(f) => f,
+        Err(e) => {
+            #[cfg(feature = "log")]
+            log::error!("load_mtl - failed to open {:?} due to {}", file_name, e);
+            return Err(LoadError::with_io_error(LoadErrorKind::OpenFileFailed, e)
+                .in_file(file_name.as_ref()));
+        }
+    };
+    let mut reader = BufReader::new(file);
+    load_mtl_buf(&mut reader)
+        .map(|materials| in_mtl_file(materials, file_name.as_ref()))
+        .map_err(|e| e.in_file(file_name.as_ref()))
+}
+
+/// Record the file the materials were loaded from.
+fn in_mtl_file(
+    (mut materials, mat_map): (Vec<Material>, HashMap<String, usize>),
+    file_name: &Path,
+) -> (Vec<Material>, HashMap<String, usize>) {
+    for material in &mut materials {
+        material.mtl_file = Some(file_name.to_owned());
+    }
+    (materials, mat_map)
+}
+
+/// Load the various meshes in an `OBJ` buffer.
+///
+/// This could e.g. be a network stream, a text file already in memory etc.
+///
+/// # Arguments
+///
+/// You must pass a `material_loader` function, which will return a material
+/// given a name.
+///
+/// A trivial material loader may just look at the file name and then call
+/// `load_mtl_buf` with the in-memory MTL file source.
+///
+/// Alternatively it could pass an `MTL` file in memory to `load_mtl_buf` to
+/// parse materials from some buffer.
+///
+/// * `load_options` – Governs on-the-fly processing of the mesh during loading.
+///   See [`LoadOptions`] for more information.
+///
+/// # Line Continuation
+///
+/// A backslash at the end of a line continues any statement but a comment on
+/// the next line, e.g. `f 1 2 \` followed by `3`. This applies to `o`, `g`,
+/// `usemtl`, `mtllib` and `f` statements as well as to free-form ones, so
+/// names ending in a backslash lose it and absorb the next line. Errors in a
+/// continued statement are reported at the [line](LoadError::line) it starts
+/// on.
 ///
 /// # Example
 /// The test for `load_obj_buf` includes the OBJ and MTL files as strings
 /// and uses a `Cursor` to provide a `BufRead` interface on the buffer.
 ///
 /// ```
+/// use std::{env, fs::File, io::BufReader};
+///
+/// let dir = env::current_dir().unwrap();
+/// let mut cornell_box_obj = dir.clone();
+/// cornell_box_obj.push("obj/cornell_box.obj");
+/// let mut cornell_box_file = BufReader::new(File::open(cornell_box_obj.as_path()).unwrap());
+///
+/// let mut cornell_box_mtl1 = dir.clone();
+/// cornell_box_mtl1.push("obj/cornell_box.mtl");
+///
+/// let mut cornell_box_mtl2 = dir.clone();
+/// cornell_box_mtl2.push("obj/cornell_box2.mtl");
+///
+/// let m = tobj64::load_obj_buf::<_, _, f64>(
+///     &mut cornell_box_file,
+///     &tobj64::LoadOptions {
+///         triangulate: true,
+///         single_index: true,
+///         ..Default::default()
+///     },
+///     |p| match p.file_name().unwrap().to_str().unwrap() {
+///         "cornell_box.mtl" => {
+///             let f = File::open(cornell_box_mtl1.as_path()).unwrap();
+///             tobj64::load_mtl_buf(&mut BufReader::new(f))
+///         }
+///         "cornell_box2.mtl" => {
+///             let f = File::open(cornell_box_mtl2.as_path()).unwrap();
+///             tobj64::load_mtl_buf(&mut BufReader::new(f))
+///         }
+///         _ => unreachable!(),
+///     },
+/// );
+/// ```
+pub fn load_obj_buf<B, ML, T: ParseableV>(
+    reader: &mut B,
+    load_options: &LoadOptions,
+    material_loader: ML,
+) -> LoadResult<T>
+where
+    B: BufRead,
+    ML: Fn(&Path) -> MTLLoadResult,
+{
+    load_obj_buf_with_diagnostics(reader, load_options, material_loader)
+        .map(|(models, materials, _)| (models, materials))
+}
+
+/// Load the various meshes in an `OBJ` buffer, collecting [`Diagnostic`]s
+/// along the way.
+///
+/// This is the same as [`load_obj_buf()`] but additionally returns the
+/// problems that did not abort loading. Combine it with
+/// [`lenient`](LoadOptions::lenient) to load files with malformed statements.
+pub fn load_obj_buf_with_diagnostics<B, ML, T: ParseableV>(
+    reader: &mut B,
+    load_options: &LoadOptions,
+    material_loader: ML,
+) -> LoadResultWithDiagnostics<T>
+where
+    B: BufRead,
+    ML: Fn(&Path) -> MTLLoadResult,
+{
+    parse_obj_buf(reader, ObjParser::new(load_options)?, material_loader)?.finish()
+}
+
+/// Load the various meshes in an `OBJ` buffer, keeping the vertex data of the
+/// whole file in one place.
+///
+/// This is the same as [`load_obj_buf()`] but returns the vertex data
+/// separately, see [`load_obj_with_attributes()`].
+pub fn load_obj_buf_with_attributes<B, ML, T: ParseableV>(
+    reader: &mut B,
+    load_options: &LoadOptions,
+    material_loader: ML,
+) -> LoadResultWithAttributes<T>
+where
+    B: BufRead,
+    ML: Fn(&Path) -> MTLLoadResult,
+{
+    let parser = ObjParser::with_shared_attributes(load_options)?;
+    parse_obj_buf(reader, parser, material_loader)?.finish_with_attributes()
+}
+
+/// Feed all lines of an `OBJ` buffer to `parser`.
+fn parse_obj_buf<'a, B, ML, T: ParseableV>(
+    reader: &mut B,
+    mut parser: ObjParser<'a, T>,
+    material_loader: ML,
+) -> Result<ObjParser<'a, T>, LoadError>
+where
+    B: BufRead,
+    ML: Fn(&Path) -> MTLLoadResult,
+{
+    let mut line = String::new();
+
+    while read_line(reader, &mut line).map_err(|e| parser.read_error(e))? {
+        if let Some(mtllib) = parser.parse_line(&line)? {
+            parser.add_materials(material_loader(Path::new(&mtllib)));
+        }
+    }
+
+    Ok(parser)
+}
+
+/// Load the various meshes in an `OBJ` file held in memory.
+///
+/// This is the fastest way to load an `OBJ` file: lines are parsed in place
+/// rather than being copied out of a reader first. Any byte slice will do,
+/// e.g. a file read with [`std::fs::read()`] or a memory-mapped file.
+///
+/// The result is identical to calling [`load_obj_buf()`] with the same data.
+///
+/// # Arguments
+///
+/// * `material_loader` – See [`load_obj_buf()`]. [`load_mtl_bytes()`] can be
+///   used to parse `MTL` files held in memory.
+///
+/// * `load_options` – Governs on-the-fly processing of the mesh during loading.
+///   See [`LoadOptions`] for more information.
+pub fn load_obj_bytes<ML, T: ParseableV>(
+    bytes: &[u8],
+    load_options: &LoadOptions,
+    material_loader: ML,
+) -> LoadResult<T>
+where
+    ML: Fn(&Path) -> MTLLoadResult,
+{
+    load_obj_bytes_with_diagnostics(bytes, load_options, material_loader)
+        .map(|(models, materials, _)| (models, materials))
+}
+
+/// Load the various meshes in an `OBJ` file held in memory, collecting
+/// [`Diagnostic`]s along the way.
+///
+/// This is the same as [`load_obj_bytes()`] but additionally returns the
+/// problems that did not abort loading.
+pub fn load_obj_bytes_with_diagnostics<ML, T: ParseableV>(
+    bytes: &[u8],
+    load_options: &LoadOptions,
+    material_loader: ML,
+) -> LoadResultWithDiagnostics<T>
+where
+    ML: Fn(&Path) -> MTLLoadResult,
+{
+    let mut parser = ObjParser::new(load_options)?;
+
+    for line in text_lines(bytes) {
+        let line = line.map_err(|e| parser.read_error(invalid_utf8(e)))?;
+        if let Some(mtllib) = parser.parse_line(line)? {
+            parser.add_materials(material_loader(Path::new(&mtllib)));
+        }
+    }
+
+    parser.finish()
+}
+
+/// Load the various materials in a `MTL` buffer.
+pub fn load_mtl_buf<B: BufRead>(reader: &mut B) -> MTLLoadResult {
+    let mut parser = MtlParser::new();
+    let mut line = String::new();
+
+    while read_line(reader, &mut line).map_err(|e| parser.read_error(e))? {
+        parser.parse_line(&line)?;
+    }
+
+    parser.finish()
+}
+
+/// Load the various materials in a `MTL` file held in memory.
+///
+/// The result is identical to calling [`load_mtl_buf()`] with the same data.
+pub fn load_mtl_bytes(bytes: &[u8]) -> MTLLoadResult {
+    let mut parser = MtlParser::new();
+
+    for line in text_lines(bytes) {
+        let line = line.map_err(|e| parser.read_error(invalid_utf8(e)))?;
+        parser.parse_line(line)?;
+    }
+
+    parser.finish()
+}
+
+/// Read the next line into `buf`, reusing its allocation, and strip the line
+/// ending the way [`BufRead::lines()`] does.
+///
+/// Returns `false` once the end of the input is reached.
+fn read_line<B: BufRead>(reader: &mut B, buf: &mut String) -> io::Result<bool> {
+    buf.clear();
+    if reader.read_line(buf)? == 0 {
+        return Ok(false);
+    }
+    trim_line_ending(buf);
+    Ok(true)
+}
+
+/// Remove a trailing `\n` or `\r\n`.
+fn trim_line_ending(buf: &mut String) {
+    if buf.ends_with('\n') {
+        buf.pop();
+        if buf.ends_with('\r') {
+            buf.pop();
+        }
+    }
+}
+
+/// Split a buffer into lines the way [`BufRead::lines()`] does, without
+/// copying them.
+fn byte_lines(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    // `lines()` yields nothing at all for empty input and no empty line after
+    // a trailing line ending.
+    let bytes = if bytes.is_empty() { None } else { Some(bytes) };
+    bytes
+        .into_iter()
+        .flat_map(|bytes| {
+            let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+            let mut start = 0;
+            memchr::memchr_iter(b'\n', bytes)
+                .chain(std::iter::once(bytes.len()))
+                .map(move |end| {
+                    let line = &bytes[start..end];
+                    start = end + 1;
+                    line
+                })
+        })
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+/// Split a buffer into lines like [`byte_lines()`] and convert them to `str`.
+///
+/// The whole buffer is validated as UTF-8 at once. If it is invalid the lines
+/// before the first invalid one are yielded as usual, followed by the error
+/// for that line.
+fn text_lines(bytes: &[u8]) -> impl Iterator<Item = Result<&str, str::Utf8Error>> {
+    let (text, rest) = match str::from_utf8(bytes) {
+        Ok(text) => (text, &[][..]),
+        Err(e) => {
+            let valid = &bytes[..e.valid_up_to()];
+            let start = memchr::memrchr(b'\n', valid).map_or(0, |i| i + 1);
+            (
+                str::from_utf8(&valid[..start]).unwrap_or_default(),
+                &bytes[start..],
+            )
+        }
+    };
+    let text = if text.is_empty() { None } else { Some(text) };
+    let lines = text.into_iter().flat_map(|text| {
+        text.strip_suffix('\n')
+            .unwrap_or(text)
+            .split('\n')
+            .map(|line| Ok(line.strip_suffix('\r').unwrap_or(line)))
+    });
+    let error = byte_lines(rest)
+        .next()
+        .and_then(|line| str::from_utf8(line).err());
+    lines.chain(error.map(Err))
+}
+
+/// The error [`BufRead::lines()`] reports for invalid UTF-8.
+fn invalid_utf8(e: str::Utf8Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Number of bytes the async loaders read before yielding to the executor.
+#[cfg(feature = "async")]
+const ASYNC_YIELD_BYTES: usize = 64 * 1024;
+
+/// Read the next line of an async reader into `buf`, see [`read_line()`].
+///
+/// Yields to the executor whenever [`ASYNC_YIELD_BYTES`] have been read, as
+/// readers with data at hand, e.g. ones wrapping a buffer in memory, never
+/// return [`Poll::Pending`] themselves.
+#[cfg(feature = "async")]
+async fn read_line_async<B>(reader: &mut B, buf: &mut String, read: &mut usize) -> io::Result<bool>
+where
+    B: AsyncBufRead + Unpin,
+{
+    buf.clear();
+    let len = reader.read_line(buf).await?;
+    if len == 0 {
+        return Ok(false);
+    }
+    trim_line_ending(buf);
+
+    *read += len;
+    if *read >= ASYNC_YIELD_BYTES {
+        *read = 0;
+        YieldNow(false).await;
+    }
+    Ok(true)
+}
+
+/// A future that returns [`Poll::Pending`] once before completing, giving
+/// other tasks the chance to run.
+#[cfg(feature = "async")]
+struct YieldNow(bool);
+
+#[cfg(feature = "async")]
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+/// Load the various objects specified in the `OBJ` file and any associated
+/// `MTL` file asynchronously.
+///
+/// This is the async equivalent of [`load_obj()`]. Files are read via
+/// [`tokio::fs`], which requires a Tokio runtime.
+///
+/// # Example
+///
+/// ```
+/// async {
+///     let (models, materials) = tobj64::load_obj_async::<_, f32>(
+///         "obj/cornell_box.obj",
+///         &tobj64::GPU_LOAD_OPTIONS,
+///     )
+///     .await
+///     .expect("Failed to load OBJ file");
+/// };
+/// ```
+pub async fn load_obj_async<P, V>(file_name: P, load_options: &LoadOptions) -> LoadResult<V>
+where
+    P: AsRef<Path> + fmt::Debug,
+    V: ParseableV,
+{
+    let file = match tokio::fs::File::open(file_name.as_ref()).await {
+        Ok(f) => f,
+        Err(e) => {
+            #[cfg(feature = "log")]
+            log::error!(
+                "load_obj_async - failed to open {:?} due to {}",
+                file_name,
+                e
+            );
+            return Err(LoadError::with_io_error(LoadErrorKind::OpenFileFailed, e)
+                .in_file(file_name.as_ref()));
+        }
+    };
+    let mut reader = tokio::io::BufReader::new(file);
+    let parent = file_name.as_ref().parent().map(Path::to_owned);
+    load_obj_buf_async(&mut reader, load_options, |mat_path| {
+        let full_path = match &parent {
+            Some(parent) => parent.join(mat_path),
+            None => PathBuf::from(mat_path),
+        };
+
+        load_mtl_async(full_path)
+    })
+    .await
+    .map_err(|e| e.in_file(file_name.as_ref()))
+}
+
+#[cfg(feature = "async")]
+/// Load the materials defined in a `MTL` file asynchronously.
+///
+/// This is the async equivalent of [`load_mtl()`].
+pub async fn load_mtl_async<P>(file_name: P) -> MTLLoadResult
+where
+    P: AsRef<Path> + fmt::Debug,
+{
+    let file = match tokio::fs::File::open(file_name.as_ref()).await {
+        Ok(f) => f,
+        Err(e) => {
+            #[cfg(feature = "log")]
+            log::error!(
+                "load_mtl_async - failed to open {:?} due to {}",
+                file_name,
+                e
+            );
+            return Err(LoadError::with_io_error(LoadErrorKind::OpenFileFailed, e)
+                .in_file(file_name.as_ref()));
+        }
+    };
+    let mut reader = tokio::io::BufReader::new(file);
+    load_mtl_buf_async(&mut reader)
+        .await
+        .map(|materials| in_mtl_file(materials, file_name.as_ref()))
+        .map_err(|e| e.in_file(file_name.as_ref()))
+}
+
+#[cfg(feature = "async")]
+/// Load the various materials in a `MTL` buffer asynchronously.
+pub async fn load_mtl_buf_async<B>(reader: &mut B) -> MTLLoadResult
+where
+    B: AsyncBufRead + Unpin,
+{
+    let mut parser = MtlParser::new();
+    let mut line = String::new();
+    let mut read = 0;
+
+    while read_line_async(reader, &mut line, &mut read)
+        .await
+        .map_err(|e| parser.read_error(e))?
+    {
+        parser.parse_line(&line)?;
+    }
+
+    parser.finish()
+}
+
+#[cfg(feature = "async")]
+/// Load the various meshes in an `OBJ` buffer.
+///
+/// This could e.g. be a text file already in memory, a file loaded
+/// asynchronously over the network etc. Any
+/// [`AsyncBufRead`](tokio::io::AsyncBufRead) will do. The loader yields to the
+/// executor regularly, so large buffers do not block other tasks.
+///
+/// # Arguments
+///
+/// You must pass a `material_loader` function, which will return a future
+/// that loads a material given a name.
+///
+/// A trivial material loader may just look at the file name and then call
+/// `load_mtl_buf` with the in-memory MTL file source.
+///
+/// Alternatively it could pass an `MTL` file in memory to `load_mtl_buf` to
+/// parse materials from some buffer.
+///
+/// * `load_options` – Governs on-the-fly processing of the mesh during loading.
+///   See [`LoadOptions`] for more information.
+///
+/// # Example
+/// The test for `load_obj_buf_async` includes the OBJ and MTL files as
+/// strings and uses a `Cursor` to provide an `AsyncBufRead` interface on the
+/// buffer.
+///
+/// ```
 /// async {
 ///     use std::{env, fs::File, io::BufReader};
 ///
 ///     let dir = env::current_dir().unwrap();
 ///     let mut cornell_box_obj = dir.clone();
 ///     cornell_box_obj.push("obj/cornell_box.obj");
-///     let mut cornell_box_file = BufReader::new(File::open(cornell_box_obj.as_path()).unwrap());
+///     let mut cornell_box_file = tokio::io::BufReader::new(
+///         tokio::fs::File::open(cornell_box_obj.as_path()).await.unwrap(),
+///     );
 ///
 ///     let m = tobj64::load_obj_buf_async::<_, f32, _, _>(
 ///         &mut cornell_box_file,
@@ -2127,207 +4769,25 @@ pub async fn load_obj_buf_async<B, V, ML, MLFut>(
     material_loader: ML,
 ) -> LoadResult<V>
 where
-    B: BufRead,
+    B: AsyncBufRead + Unpin,
     V: ParseableV,
     ML: Fn(String) -> MLFut,
     MLFut: Future<Output = MTLLoadResult>,
 {
-    if !load_options.is_valid() {
-        return Err(LoadError::InvalidLoadOptionConfig);
-    }
-
-    let mut models = Vec::new();
-    let mut materials = Vec::new();
-    let mut mat_map = HashMap::new_map();
-
-    let mut tmp_pos = Vec::new();
-    let mut tmp_v_color = Vec::new();
-    let mut tmp_texcoord = Vec::new();
-    let mut tmp_normal = Vec::new();
-    let mut tmp_faces: Vec<Face> = Vec::new();
-    // name of the current object being parsed
-    let mut name = "unnamed_object".to_owned();
-    // material used by the current object being parsed
-    let mut mat_id = None;
-    let mut mtlresult = Ok(Vec::new());
+    let mut parser = ObjParser::new(load_options)?;
+    let mut line = String::new();
+    let mut read = 0;
 
-    for line in reader.lines() {
-        let (line, mut words) = match line {
-            Ok(ref line) => (&line[..], line[..].split_whitespace()),
-            Err(_e) => {
-                #[cfg(feature = "log")]
-                log::error!("load_obj - failed to read line due to {}", _e);
-                return Err(LoadError::ReadError);
-            }
-        };
-        match words.next() {
-            Some("#") | None => continue,
-            Some("v") => {
-                if !parse_floatn(&mut words, &mut tmp_pos, 3) {
-                    return Err(LoadError::PositionParseError);
-                }
-
-                // Add inline vertex colors if present.
-                parse_floatn(&mut words, &mut tmp_v_color, 3);
-            }
-            Some("vt") => {
-                if !parse_floatn(&mut words, &mut tmp_texcoord, 2) {
-                    return Err(LoadError::TexcoordParseError);
-                }
-            }
-            Some("vn") => {
-                if !parse_floatn(&mut words, &mut tmp_normal, 3) {
-                    return Err(LoadError::NormalParseError);
-                }
-            }
-            Some("f") | Some("l") => {
-                if !parse_face(
-                    words,
-                    &mut tmp_faces,
-                    tmp_pos.len() / 3,
-                    tmp_texcoord.len() / 2,
-                    tmp_normal.len() / 3,
-                ) {
-                    return Err(LoadError::FaceParseError);
-                }
-            }
-            // Just treating object and group tags identically. Should there be different behavior
-            // for them?
-            Some("o") | Some("g") => {
-                // If we were already parsing an object then a new object name
-                // signals the end of the current one, so push it onto our list of objects
-                if !tmp_faces.is_empty() {
-                    models.push(Model::new(
-                        if load_options.single_index {
-                            export_faces(
-                                &tmp_pos,
-                                &tmp_v_color,
-                                &tmp_texcoord,
-                                &tmp_normal,
-                                &tmp_faces,
-                                mat_id,
-                                load_options,
-                            )?
-                        } else {
-                            export_faces_multi_index(
-                                &tmp_pos,
-                                &tmp_v_color,
-                                &tmp_texcoord,
-                                &tmp_normal,
-                                &tmp_faces,
-                                mat_id,
-                                load_options,
-                            )?
-                        },
-                        name,
-                    ));
-                    tmp_faces.clear();
-                }
-                name = line[1..].trim().to_owned();
-                if name.is_empty() {
-                    name = "unnamed_object".to_owned();
-                }
-            }
-            Some("mtllib") => {
-                if let Some(mtllib) = words.next() {
-                    let mat_file = String::from(mtllib);
-                    match material_loader(mat_file).await {
-                        Ok((mut mats, map)) => {
-                            // Merge the loaded material lib with any currently loaded ones,
-                            // offsetting the indices of the appended
-                            // materials by our current length
-                            let mat_offset = materials.len();
-                            materials.append(&mut mats);
-                            for m in map {
-                                mat_map.insert(m.0, m.1 + mat_offset);
-                            }
-                        }
-                        Err(e) => {
-                            mtlresult = Err(e);
-                        }
-                    }
-                } else {
-                    return Err(LoadError::MaterialParseError);
-                }
-            }
-            Some("usemtl") => {
-                let mat_name = line[7..].trim().to_owned();
-                if !mat_name.is_empty() {
-                    let new_mat = mat_map.get(&mat_name).cloned();
-                    // As materials are returned per-model, a new material within an object
-                    // has to emit a new model with the same name but different material
-                    if mat_id != new_mat && !tmp_faces.is_empty() {
-                        models.push(Model::new(
-                            if load_options.single_index {
-                                export_faces(
-                                    &tmp_pos,
-                                    &tmp_v_color,
-                                    &tmp_texcoord,
-                                    &tmp_normal,
-                                    &tmp_faces,
-                                    mat_id,
-                                    load_options,
-                                )?
-                            } else {
-                                export_faces_multi_index(
-                                    &tmp_pos,
-                                    &tmp_v_color,
-                                    &tmp_texcoord,
-                                    &tmp_normal,
-                                    &tmp_faces,
-                                    mat_id,
-                                    load_options,
-                                )?
-                            },
-                            name.clone(),
-                        ));
-                        tmp_faces.clear();
-                    }
-                    if new_mat.is_none() {
-                        #[cfg(feature = "log")]
-                        log::warn!("Object {} refers to unfound material: {}", name, mat_name);
-                    }
-                    mat_id = new_mat;
-                } else {
-                    return Err(LoadError::MaterialParseError);
-                }
-            }
-            // Just ignore unrecognized characters
-            Some(_) => {}
+    while read_line_async(reader, &mut line, &mut read)
+        .await
+        .map_err(|e| parser.read_error(e))?
+    {
+        if let Some(mtllib) = parser.parse_line(&line)? {
+            parser.add_materials(material_loader(mtllib).await);
         }
     }
 
-    // For the last object in the file we won't encounter another object name to
-    // tell us when it's done, so if we're parsing an object push the last one
-    // on the list as well
-    models.push(Model::new(
-        if load_options.single_index {
-            export_faces(
-                &tmp_pos,
-                &tmp_v_color,
-                &tmp_texcoord,
-                &tmp_normal,
-                &tmp_faces,
-                mat_id,
-                load_options,
-            )?
-        } else {
-            export_faces_multi_index(
-                &tmp_pos,
-                &tmp_v_color,
-                &tmp_texcoord,
-                &tmp_normal,
-                &tmp_faces,
-                mat_id,
-                load_options,
-            )?
-        },
-        name,
-    ));
-
-    if !materials.is_empty() {
-        mtlresult = Ok(materials);
-    }
-
-    Ok((models, mtlresult))
+    parser
+        .finish()
+        .map(|(models, materials, _)| (models, materials))
 }
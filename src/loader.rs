@@ -0,0 +1,179 @@
+//! Loading of the material libraries referenced by `mtllib` statements.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{
+    load_mtl, load_mtl_bytes, HashMap, LoadError, LoadErrorKind, MTLLoadResult, NewHashMap,
+};
+
+/// Loads the material library referenced by an `mtllib` statement.
+///
+/// Passed to [`load_obj_with_loader()`](crate::load_obj_with_loader). Any
+/// `Fn(&Path) -> MTLLoadResult`, such as the material loaders passed to
+/// [`load_obj_buf()`](crate::load_obj_buf), is a `MaterialLoader` too.
+///
+/// The stock implementations can be combined, e.g. a [`CachingLoader`]
+/// around a [`SearchPathLoader`].
+pub trait MaterialLoader {
+    /// Load the library at `path`, as specified in the `mtllib` statement.
+    fn load(&self, path: &Path) -> MTLLoadResult;
+}
+
+impl<F> MaterialLoader for F
+where
+    F: Fn(&Path) -> MTLLoadResult,
+{
+    fn load(&self, path: &Path) -> MTLLoadResult {
+        self(path)
+    }
+}
+
+/// Looks for material libraries in a list of directories, in order.
+///
+/// File and directory names are matched case-insensitively if there is no
+/// exact match, as they often are in files authored on Windows.
+#[derive(Debug, Clone, Default)]
+pub struct SearchPathLoader {
+    /// The directories to search, in order.
+    pub directories: Vec<PathBuf>,
+}
+
+impl SearchPathLoader {
+    /// Create a loader searching `directories` in order.
+    pub fn new<I>(directories: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<PathBuf>,
+    {
+        Self {
+            directories: directories.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl MaterialLoader for SearchPathLoader {
+    fn load(&self, path: &Path) -> MTLLoadResult {
+        match self
+            .directories
+            .iter()
+            .find_map(|directory| find_case_insensitive(directory, path))
+        {
+            Some(full_path) => load_mtl(full_path),
+            None => Err(LoadError::new(LoadErrorKind::OpenFileFailed).in_file(path)),
+        }
+    }
+}
+
+/// Find `path` relative to `directory`, matching each component that does not
+/// exist exactly case-insensitively.
+pub(crate) fn find_case_insensitive(directory: &Path, path: &Path) -> Option<PathBuf> {
+    let exact = directory.join(path);
+    if exact.is_file() {
+        return Some(exact);
+    }
+
+    let mut found = directory.to_owned();
+    for component in path.components() {
+        let name = match component {
+            Component::Normal(name) => name,
+            _ => {
+                found.push(component);
+                continue;
+            }
+        };
+        if !found.join(name).exists() {
+            let lowercase = name.to_string_lossy().to_lowercase();
+            let entry = fs::read_dir(&found).ok()?.find_map(|entry| {
+                let entry = entry.ok()?;
+                (entry.file_name().to_string_lossy().to_lowercase() == lowercase)
+                    .then(|| entry.file_name())
+            })?;
+            found.push(entry);
+        } else {
+            found.push(name);
+        }
+    }
+    found.is_file().then_some(found)
+}
+
+/// Caches the libraries loaded by another [`MaterialLoader`], so that a
+/// library shared by many `OBJ` files is only parsed once.
+///
+/// Libraries are cached by the path they are requested with. Failed loads are
+/// not cached.
+#[derive(Debug, Default)]
+pub struct CachingLoader<L> {
+    loader: L,
+    cache: Mutex<HashMap<PathBuf, MTLLoadResult>>,
+}
+
+impl<L: MaterialLoader> CachingLoader<L> {
+    /// Cache the libraries loaded by `loader`.
+    pub fn new(loader: L) -> Self {
+        Self {
+            loader,
+            cache: Mutex::new(HashMap::new_map()),
+        }
+    }
+
+    /// Forget all cached libraries, e.g. after they changed on disk.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+impl<L: MaterialLoader> MaterialLoader for CachingLoader<L> {
+    fn load(&self, path: &Path) -> MTLLoadResult {
+        if let Some(cached) = self
+            .cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(path)
+        {
+            return cached.clone();
+        }
+        // Not holding the lock while loading lets other threads use the cache
+        // meanwhile, at the risk of loading the same library twice.
+        let result = self.loader.load(path);
+        if result.is_ok() {
+            self.cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(path.to_owned(), result.clone());
+        }
+        result
+    }
+}
+
+/// Serves material libraries held in memory, by the path they are referenced
+/// with in `mtllib` statements.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLoader {
+    /// The source of each library, by path.
+    pub libraries: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryLoader {
+    /// Create a loader without any libraries.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the library referenced as `path` with the `MTL` `source`.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, source: impl Into<Vec<u8>>) {
+        self.libraries.insert(path.into(), source.into());
+    }
+}
+
+impl MaterialLoader for MemoryLoader {
+    fn load(&self, path: &Path) -> MTLLoadResult {
+        match self.libraries.get(path) {
+            Some(source) => load_mtl_bytes(source).map_err(|e| e.in_file(path)),
+            None => Err(LoadError::new(LoadErrorKind::OpenFileFailed).in_file(path)),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Generation of vertex normals for meshes that do not specify any.
+
+use crate::{HashMap, Mesh, NewHashMap, ParseableV};
+
+/// How the normal of a vertex is derived from the faces surrounding it.
+///
+/// Used by [`NormalOptions`].
+#[cfg_attr(feature = "arb", derive(arbitrary::Arbitrary))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalMode {
+    /// Every face corner gets the normal of its face. Vertices are never
+    /// shared between faces that are not coplanar.
+    Flat,
+    /// The normals of all faces sharing a vertex are averaged, weighted by
+    /// the area of each face.
+    #[default]
+    AreaWeighted,
+    /// The normals of all faces sharing a vertex are averaged, weighted by
+    /// the angle each face spans at the vertex.
+    ///
+    /// This is less sensitive to the way a surface is tessellated than
+    /// [`AreaWeighted`](NormalMode::AreaWeighted).
+    AngleWeighted,
+}
+
+/// Options for generating vertex normals.
+///
+/// Passed to [`Mesh::compute_normals()`], [`Mesh::compute_normals_multi_index()`]
+/// and [`LoadOptions::generate_normals`](crate::LoadOptions::generate_normals).
+///
+/// [Smoothing groups](Mesh::smoothing_groups) are honored if the mesh has
+/// them: faces are only smoothed together if they belong to the same group
+/// and faces in group `0` are always flat shaded.
+#[cfg_attr(feature = "arb", derive(arbitrary::Arbitrary))]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NormalOptions {
+    /// How face normals are combined at shared vertices.
+    pub mode: NormalMode,
+    /// Maximum angle, in degrees, between two adjacent faces for them to be
+    /// smoothed together.
+    ///
+    /// Where the angle between two faces is larger the shared vertex is split,
+    /// creating a hard edge. If `None` all adjacent faces are smoothed
+    /// together.
+    pub crease_angle: Option<f32>,
+}
+
+impl<T: ParseableV> Mesh<T> {
+    /// Generate per-vertex normals, replacing any existing ones.
+    ///
+    /// This is meant for meshes loaded with
+    /// [`single_index`](crate::LoadOptions::single_index) set to `true`: the
+    /// resulting [`normals`](Mesh::normals) share the [`indices`](Mesh::indices)
+    /// of the positions.
+    ///
+    /// Vertices whose faces need different normals (hard edges, flat shading)
+    /// are split – their position, texture coordinate and vertex color are
+    /// duplicated and the `indices` of the affected faces updated.
+    pub fn compute_normals(&mut self, options: &NormalOptions) {
+        let corner_normals = self.corner_normals(options);
+
+        let vertex_count = self.positions.len() / 3;
+        let mut vertex_normals: Vec<Option<[f64; 3]>> = vec![None; vertex_count];
+        let mut split_map = HashMap::<(u32, [u64; 3]), u32>::new_map();
+
+        for (index, normal) in self.indices.iter_mut().zip(corner_normals) {
+            let v = *index as usize;
+            match vertex_normals[v] {
+                None => vertex_normals[v] = Some(normal),
+                Some(existing) if existing == normal => {}
+                Some(_) => {
+                    let key = (*index, normal.map(f64::to_bits));
+                    *index = match split_map.get(&key) {
+                        Some(&split) => split,
+                        None => {
+                            let split = vertex_normals.len() as u32;
+                            self.positions.extend_from_within(v * 3..v * 3 + 3);
+                            let dimension = self.texcoord_dimension;
+                            if self.texcoords.len() >= (v + 1) * dimension {
+                                self.texcoords
+                                    .extend_from_within(v * dimension..(v + 1) * dimension);
+                            }
+                            if self.vertex_color.len() >= v * 4 + 4 {
+                                self.vertex_color.extend_from_within(v * 4..v * 4 + 4);
+                            }
+                            if self.weights.len() > v {
+                                self.weights.push(self.weights[v]);
+                            }
+                            if self.vertex_mask.len() > v {
+                                self.vertex_mask.push(self.vertex_mask[v]);
+                            }
+                            vertex_normals.push(Some(normal));
+                            split_map.insert(key, split);
+                            split
+                        }
+                    };
+                }
+            }
+        }
+
+        self.normals = vertex_normals
+            .into_iter()
+            .flat_map(|normal| normal.unwrap_or_default())
+            .map(from_f64)
+            .collect();
+        self.normal_indices = Vec::new();
+    }
+
+    /// Generate normals with their own [`normal_indices`](Mesh::normal_indices),
+    /// replacing any existing ones.
+    ///
+    /// This is meant for meshes loaded with
+    /// [`single_index`](crate::LoadOptions::single_index) set to `false`. The
+    /// topology of the mesh is not changed, identical normals are shared.
+    pub fn compute_normals_multi_index(&mut self, options: &NormalOptions) {
+        let mut normal_map = HashMap::<[u64; 3], u32>::new_map();
+        let mut normals = Vec::new();
+
+        self.normal_indices = self
+            .corner_normals(options)
+            .into_iter()
+            .map(|normal| {
+                *normal_map
+                    .entry(normal.map(f64::to_bits))
+                    .or_insert_with(|| {
+                        normals.extend_from_slice(&normal);
+                        (normals.len() / 3 - 1) as u32
+                    })
+            })
+            .collect();
+        self.normals = normals.into_iter().map(from_f64).collect();
+    }
+
+    /// Compute the normal of every face corner, i.e. one normal per entry in
+    /// [`indices`](Mesh::indices).
+    fn corner_normals(&self, options: &NormalOptions) -> Vec<[f64; 3]> {
+        let position = |index: u32| {
+            let i = index as usize * 3;
+            [
+                to_f64(self.positions[i]),
+                to_f64(self.positions[i + 1]),
+                to_f64(self.positions[i + 2]),
+            ]
+        };
+
+        // Start and length of each face in `indices`.
+        let faces: Vec<(usize, usize)> = if self.face_arities.is_empty() {
+            (0..self.indices.len() / 3).map(|f| (f * 3, 3)).collect()
+        } else {
+            self.face_arities
+                .iter()
+                .scan(0, |start, &arity| {
+                    let face = (*start, arity as usize);
+                    *start += arity as usize;
+                    Some(face)
+                })
+                .collect()
+        };
+
+        // Newell's method also gives sensible results for non-planar
+        // polygons. The length of the result is twice the face's area.
+        let area_normals: Vec<[f64; 3]> = faces
+            .iter()
+            .map(|&(start, len)| newell_normal(len, |i| position(self.indices[start + i])))
+            .collect();
+        let unit_normals: Vec<[f64; 3]> = area_normals.iter().map(normalize).collect();
+
+        let mut corner_faces = Vec::with_capacity(self.indices.len());
+        for (face, &(_, len)) in faces.iter().enumerate() {
+            // `repeat_n` needs Rust 1.82.
+            #[allow(clippy::manual_repeat_n)]
+            corner_faces.extend(std::iter::repeat(face).take(len));
+        }
+
+        if options.mode == NormalMode::Flat {
+            return corner_faces.iter().map(|&f| unit_normals[f]).collect();
+        }
+
+        // The contribution of each face corner to the normal of its vertex.
+        let weights: Vec<[f64; 3]> = match options.mode {
+            NormalMode::AngleWeighted => corner_faces
+                .iter()
+                .enumerate()
+                .map(|(corner, &f)| {
+                    let (start, len) = faces[f];
+                    if len < 3 {
+                        return [0.0; 3];
+                    }
+                    let i = corner - start;
+                    let p = position(self.indices[corner]);
+                    let prev = position(self.indices[start + (i + len - 1) % len]);
+                    let next = position(self.indices[start + (i + 1) % len]);
+                    let angle = angle_between(&sub(&prev, &p), &sub(&next, &p));
+                    scale(&unit_normals[f], angle)
+                })
+                .collect(),
+            _ => corner_faces.iter().map(|&f| area_normals[f]).collect(),
+        };
+
+        let mut incident = vec![Vec::new(); self.positions.len() / 3];
+        for (corner, &index) in self.indices.iter().enumerate() {
+            incident[index as usize].push(corner);
+        }
+
+        let cos_crease = options
+            .crease_angle
+            .map(|angle| (angle as f64).to_radians().cos());
+        let smooth_together = |a: usize, b: usize| {
+            if a == b {
+                return true;
+            }
+            if let (Some(&group_a), Some(&group_b)) =
+                (self.smoothing_groups.get(a), self.smoothing_groups.get(b))
+            {
+                if group_a == 0 || group_a != group_b {
+                    return false;
+                }
+            }
+            match cos_crease {
+                Some(cos_crease) => dot(&unit_normals[a], &unit_normals[b]) >= cos_crease,
+                None => true,
+            }
+        };
+
+        self.indices
+            .iter()
+            .zip(&corner_faces)
+            .map(|(&index, &face)| {
+                // Points and lines take the plain average of the faces around
+                // them as they have no orientation of their own.
+                let is_polygon = faces[face].1 >= 3;
+                let sum = incident[index as usize]
+                    .iter()
+                    .filter(|&&other| !is_polygon || smooth_together(face, corner_faces[other]))
+                    .fold([0.0; 3], |sum, &other| add(&sum, &weights[other]));
+                let normal = normalize(&sum);
+                if normal == [0.0; 3] {
+                    unit_normals[face]
+                } else {
+                    normal
+                }
+            })
+            .collect()
+    }
+}
+
+#[inline]
+pub(crate) fn to_f64<T: ParseableV>(value: T) -> f64 {
+    value.to_f64().unwrap_or_default()
+}
+
+#[inline]
+pub(crate) fn from_f64<T: ParseableV>(value: f64) -> T {
+    T::from(value).unwrap_or_else(T::zero)
+}
+
+/// The normal of the polygon with the `len` corners given by `corner`, by
+/// Newell's method. Its length is twice the polygon's area.
+pub(crate) fn newell_normal(len: usize, corner: impl Fn(usize) -> [f64; 3]) -> [f64; 3] {
+    (0..len).fold([0.0; 3], |n, i| {
+        let a = corner(i);
+        let b = corner((i + 1) % len);
+        [
+            n[0] + (a[1] - b[1]) * (a[2] + b[2]),
+            n[1] + (a[2] - b[2]) * (a[0] + b[0]),
+            n[2] + (a[0] - b[0]) * (a[1] + b[1]),
+        ]
+    })
+}
+
+#[inline]
+fn add(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+#[inline]
+fn sub(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+#[inline]
+fn scale(a: &[f64; 3], s: f64) -> [f64; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+#[inline]
+pub(crate) fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline]
+pub(crate) fn cross(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[inline]
+pub(crate) fn normalize(a: &[f64; 3]) -> [f64; 3] {
+    let length = dot(a, a).sqrt();
+    if length > 0.0 {
+        scale(a, 1.0 / length)
+    } else {
+        [0.0; 3]
+    }
+}
+
+#[inline]
+fn angle_between(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    let lengths = (dot(a, a) * dot(b, b)).sqrt();
+    if lengths > 0.0 {
+        (dot(a, b) / lengths).clamp(-1.0, 1.0).acos()
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,346 @@
+//! Multi-threaded loading of `OBJ` files held in memory.
+//!
+//! The file is split into chunks at line boundaries. Vertex data and faces
+//! are parsed for all chunks in parallel. Everything that depends on the
+//! state of the parser – objects, groups, materials, smoothing groups and
+//! faces with relative indices – is then applied in file order.
+
+use std::{path::Path, slice, str};
+
+use rayon::prelude::*;
+
+use crate::{
+    byte_lines, extend_per_vertex, invalid_utf8, parse_face, parse_floatn, parse_texcoord,
+    parse_vertex_extras, Face, LoadOptions, LoadResult, LoadResultWithDiagnostics, MTLLoadResult,
+    ObjParser, ParseableV, Primitive,
+};
+
+/// Approximate size of the chunks the file is split into, in bytes.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Load the various meshes in an `OBJ` file held in memory, using all threads
+/// of the global [`rayon`] thread pool.
+///
+/// The result is identical to calling [`load_obj_bytes()`](crate::load_obj_bytes)
+/// or [`load_obj_buf()`](crate::load_obj_buf) with the same data. The
+/// `material_loader` is called from the calling thread.
+///
+/// # Arguments
+///
+/// * `material_loader` – See [`load_obj_buf()`](crate::load_obj_buf).
+///
+/// * `load_options` – Governs on-the-fly processing of the mesh during loading.
+///   See [`LoadOptions`] for more information.
+pub fn load_obj_bytes_parallel<ML, T>(
+    bytes: &[u8],
+    load_options: &LoadOptions,
+    material_loader: ML,
+) -> LoadResult<T>
+where
+    ML: Fn(&Path) -> MTLLoadResult,
+    T: ParseableV + Send,
+{
+    load_obj_bytes_parallel_with_diagnostics(bytes, load_options, material_loader)
+        .map(|(models, materials, _)| (models, materials))
+}
+
+/// Load the various meshes in an `OBJ` file held in memory using multiple
+/// threads, collecting [`Diagnostic`](crate::Diagnostic)s along the way.
+///
+/// This is the same as [`load_obj_bytes_parallel()`] but additionally returns
+/// the problems that did not abort loading.
+pub fn load_obj_bytes_parallel_with_diagnostics<ML, T>(
+    bytes: &[u8],
+    load_options: &LoadOptions,
+    material_loader: ML,
+) -> LoadResultWithDiagnostics<T>
+where
+    ML: Fn(&Path) -> MTLLoadResult,
+    T: ParseableV + Send,
+{
+    load_chunked(bytes, load_options, material_loader, CHUNK_SIZE)
+}
+
+/// A statement that depends on the state of the parser.
+struct Statement<'a> {
+    /// Index of the line of the statement within its chunk.
+    line: usize,
+    /// Length of the positions, texture coordinates and normals of the chunk
+    /// before the statement.
+    data: [usize; 3],
+    /// Largest number of texture coordinate components of the chunk before
+    /// the statement.
+    texcoord_dimension: usize,
+    kind: StatementKind<'a>,
+}
+
+enum StatementKind<'a> {
+    /// The next one of the faces parsed with the chunk.
+    Face,
+    /// Any other statement, left to the parser.
+    Line(&'a str),
+    /// A line that is not valid UTF-8.
+    InvalidUtf8(str::Utf8Error),
+}
+
+/// A chunk of the file, parsed without knowing about any of the others.
+struct Chunk<'a, T> {
+    lines: usize,
+    pos: Vec<T>,
+    v_color: Vec<T>,
+    weights: Vec<T>,
+    // first vertices with a color and weight, the ones before are padded
+    first_color: Option<usize>,
+    first_weight: Option<usize>,
+    texcoord: Vec<T>,
+    texcoord_dimension: usize,
+    normal: Vec<T>,
+    faces: Vec<Face>,
+    statements: Vec<Statement<'a>>,
+}
+
+impl<'a, T: ParseableV> Chunk<'a, T> {
+    fn parse(bytes: &'a [u8]) -> Self {
+        let mut chunk = Self {
+            lines: 0,
+            pos: Vec::new(),
+            v_color: Vec::new(),
+            weights: Vec::new(),
+            first_color: None,
+            first_weight: None,
+            texcoord: Vec::new(),
+            texcoord_dimension: 0,
+            normal: Vec::new(),
+            faces: Vec::new(),
+            statements: Vec::new(),
+        };
+
+        // Whether the previous line continues on this one.
+        let mut continued = false;
+        for (index, line) in byte_lines(bytes).enumerate() {
+            chunk.lines += 1;
+            let line = match str::from_utf8(line) {
+                Ok(line) => line,
+                Err(e) => {
+                    chunk.push(index, StatementKind::InvalidUtf8(e));
+                    continue;
+                }
+            };
+
+            // Statements spanning multiple lines are left to the parser.
+            let continues = line.trim_end().ends_with('\\');
+            if continued || continues {
+                continued = continues;
+                chunk.push(index, StatementKind::Line(line));
+                continue;
+            }
+
+            // Mirrors `ObjParser::parse_statement()`. Malformed statements are
+            // left to the parser so that errors are reported the same way.
+            let mut words = line.split_whitespace();
+            let parsed = match words.next() {
+                Some("#") | None => true,
+                Some("v") => {
+                    let start = chunk.pos.len();
+                    let extras = match parse_floatn(&mut words, &mut chunk.pos, 3) {
+                        true => parse_vertex_extras(words),
+                        false => None,
+                    };
+                    match extras {
+                        Some((color, weight)) => {
+                            chunk.add_vertex_extras(color, weight);
+                            true
+                        }
+                        None => {
+                            chunk.pos.truncate(start);
+                            false
+                        }
+                    }
+                }
+                Some("vt") => match parse_texcoord(&mut words, &mut chunk.texcoord) {
+                    Some(dimension) => {
+                        chunk.texcoord_dimension = chunk.texcoord_dimension.max(dimension);
+                        true
+                    }
+                    None => false,
+                },
+                Some("vn") => parse_floatn(&mut words, &mut chunk.normal, 3),
+                // Relative indices depend on the amount of vertex data before
+                // the face in the whole file, absolute ones do not. Lines and
+                // points are rare enough to leave to the parser.
+                Some("f") if !line.contains('-') => {
+                    let parsed = parse_face(words, &mut chunk.faces, 0, 0, 0);
+                    if parsed {
+                        chunk.push(index, StatementKind::Face);
+                    }
+                    parsed
+                }
+                Some(_) => false,
+            };
+            if !parsed {
+                chunk.push(index, StatementKind::Line(line));
+            }
+        }
+
+        chunk
+    }
+
+    /// Mirrors `ObjParser::add_vertex_extras()`.
+    fn add_vertex_extras(&mut self, color: Option<[T; 4]>, weight: Option<T>) {
+        let vertex = self.pos.len() / 3 - 1;
+        if color.is_some() && self.first_color.is_none() {
+            self.first_color = Some(vertex);
+        }
+        if weight.is_some() && self.first_weight.is_none() {
+            self.first_weight = Some(vertex);
+        }
+        extend_per_vertex(
+            &mut self.v_color,
+            vertex..vertex + 1,
+            color.as_ref().map(|color| &color[..]),
+            &[T::one(); 4],
+        );
+        extend_per_vertex(
+            &mut self.weights,
+            vertex..vertex + 1,
+            weight.as_ref().map(slice::from_ref),
+            &[T::one()],
+        );
+    }
+
+    fn push(&mut self, line: usize, kind: StatementKind<'a>) {
+        self.statements.push(Statement {
+            line,
+            data: [self.pos.len(), self.texcoord.len(), self.normal.len()],
+            texcoord_dimension: self.texcoord_dimension,
+            kind,
+        });
+    }
+}
+
+/// Split `bytes` into chunks of at least `chunk_size` bytes that end at a line
+/// boundary, keeping statements continued on the next line together.
+fn split_chunks(bytes: &[u8], chunk_size: usize) -> Vec<&[u8]> {
+    let mut chunks = Vec::with_capacity(bytes.len() / chunk_size + 1);
+    let mut rest = bytes;
+    while rest.len() > chunk_size {
+        let mut end = chunk_size;
+        loop {
+            end =
+                memchr::memchr(b'\n', &rest[end..]).map_or(rest.len(), |newline| end + newline + 1);
+            // A backslash before any trailing whitespace continues the line.
+            let last = rest[..end].iter().rposition(|b| !b.is_ascii_whitespace());
+            if end == rest.len() || last.map(|last| rest[last]) != Some(b'\\') {
+                break;
+            }
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+/// Append `source[*copied..len]` to `target`.
+fn copy_up_to<V: Copy>(target: &mut Vec<V>, source: &[V], copied: &mut usize, len: usize) {
+    target.extend_from_slice(&source[*copied..len]);
+    *copied = len;
+}
+
+pub(crate) fn load_chunked<ML, T>(
+    bytes: &[u8],
+    load_options: &LoadOptions,
+    material_loader: ML,
+    chunk_size: usize,
+) -> LoadResultWithDiagnostics<T>
+where
+    ML: Fn(&Path) -> MTLLoadResult,
+    T: ParseableV + Send,
+{
+    let mut parser = ObjParser::new(load_options)?;
+
+    let chunks: Vec<Chunk<T>> = split_chunks(bytes, chunk_size)
+        .into_par_iter()
+        .map(Chunk::parse)
+        .collect();
+
+    let mut first_line = 0;
+    for chunk in chunks {
+        let Chunk {
+            lines,
+            pos,
+            v_color,
+            weights,
+            first_color,
+            first_weight,
+            texcoord,
+            texcoord_dimension,
+            normal,
+            faces,
+            statements,
+        } = chunk;
+        let mut faces = faces.into_iter();
+        let mut copied = [0; 3];
+        let mut copy_data = |parser: &mut ObjParser<T>, data: [usize; 3], dimension: usize| {
+            // Colors and weights of the vertices, once the chunk has any.
+            let vertices = copied[0] / 3..data[0] / 3;
+            let first = parser.tmp_pos.len() / 3;
+            let targets = first..first + vertices.len();
+            let colors = match first_color {
+                Some(vertex) if vertex < vertices.end => {
+                    Some(&v_color[vertices.start * 4..vertices.end * 4])
+                }
+                _ => None,
+            };
+            let vertex_weights = match first_weight {
+                Some(vertex) if vertex < vertices.end => Some(&weights[vertices.clone()]),
+                _ => None,
+            };
+            extend_per_vertex(
+                &mut parser.tmp_v_color,
+                targets.clone(),
+                colors,
+                &[T::one(); 4],
+            );
+            extend_per_vertex(&mut parser.tmp_weight, targets, vertex_weights, &[T::one()]);
+
+            copy_up_to(&mut parser.tmp_pos, &pos, &mut copied[0], data[0]);
+            copy_up_to(&mut parser.tmp_texcoord, &texcoord, &mut copied[1], data[1]);
+            copy_up_to(&mut parser.tmp_normal, &normal, &mut copied[2], data[2]);
+            parser.texcoord_dimension = parser.texcoord_dimension.max(dimension);
+        };
+
+        for statement in statements {
+            // The parser sees the vertex data in the same state as if it had
+            // parsed the file line by line.
+            copy_data(&mut parser, statement.data, statement.texcoord_dimension);
+            parser.line_number = first_line + statement.line;
+
+            match statement.kind {
+                StatementKind::Face => {
+                    parser.line_number += 1;
+                    parser.statement_line = parser.line_number;
+                    parser.tmp_faces.extend(faces.next());
+                    parser.face_added(Primitive::Face);
+                }
+                StatementKind::Line(line) => {
+                    if let Some(mtllib) = parser.parse_line(line)? {
+                        parser.add_materials(material_loader(Path::new(&mtllib)));
+                    }
+                }
+                StatementKind::InvalidUtf8(e) => return Err(parser.read_error(invalid_utf8(e))),
+            }
+        }
+        copy_data(
+            &mut parser,
+            [pos.len(), texcoord.len(), normal.len()],
+            texcoord_dimension,
+        );
+        first_line += lines;
+    }
+
+    parser.finish()
+}
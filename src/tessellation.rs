@@ -0,0 +1,604 @@
+//! Tessellation of free-form curves and surfaces into polylines and triangle
+//! meshes.
+
+use crate::{
+    normals::{from_f64, to_f64},
+    Basis, CurveTechnique, FreeForm, Mesh, ParseableV, Surface, SurfaceTechnique, TrimCurve,
+};
+
+/// Upper bound for the number of steps a single polynomial span is split
+/// into, whatever the approximation technique asks for.
+const MAX_STEPS: usize = 256;
+
+/// Options for tessellating free-form geometry.
+///
+/// Passed to [`FreeForm::tessellate()`] and
+/// [`LoadOptions::tessellate`](crate::LoadOptions::tessellate).
+#[cfg_attr(feature = "arb", derive(arbitrary::Arbitrary))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TessellationOptions {
+    /// Number of steps each polynomial span of a curve or surface is split
+    /// into unless the file specifies an approximation technique via `ctech`
+    /// or `stech`. Also used for trimming curves.
+    pub resolution: u32,
+    /// Use [`resolution`](TessellationOptions::resolution) for all curves and
+    /// surfaces, ignoring the [`technique`](crate::Curve::technique) they
+    /// were loaded with.
+    pub ignore_file_techniques: bool,
+}
+
+impl Default for TessellationOptions {
+    /// Split each span into 8 steps unless the file says otherwise.
+    fn default() -> Self {
+        Self {
+            resolution: 8,
+            ignore_file_techniques: false,
+        }
+    }
+}
+
+impl<T: ParseableV> FreeForm<T> {
+    /// Approximate the curves and surfaces by polylines and triangles.
+    ///
+    /// The result is a mesh with a single index, like one loaded with
+    /// [`single_index`](crate::LoadOptions::single_index) set:
+    ///
+    /// * Surfaces become triangles in [`indices`](Mesh::indices). Parts cut
+    ///   away by their `trim` and `hole` loops are left out. Texture
+    ///   coordinates are interpolated from those of the control points or, if
+    ///   there are none, are the normalized surface parameters.
+    ///
+    /// * Curves become polylines in [`line_indices`](Mesh::line_indices) and
+    ///   [`line_arities`](Mesh::line_arities). Their texture coordinates are
+    ///   the normalized curve parameter and `0`.
+    ///
+    /// Bezier curves and surfaces as well as B-splines, rational or not, are
+    /// supported. Elements using other bases or whose parameter values do not
+    /// match their control points are skipped, as are special curves
+    /// (`scrv`) and curves in parameter space that do not trim a surface.
+    pub fn tessellate(&self, options: &TessellationOptions) -> Mesh<T> {
+        let mut mesh = Mesh::default();
+        for surface in &self.surfaces {
+            self.tessellate_surface(surface, options, &mut mesh);
+        }
+        for curve in &self.curves {
+            let range = curve.range.map(to_f64);
+            let points = match self.control_points(&curve.control_points, curve.rational) {
+                Some(points) => points,
+                None => continue,
+            };
+            let knots = match Knots::new(
+                curve.basis,
+                curve.degree,
+                &curve.knots,
+                range,
+                Some(points.len()),
+            ) {
+                Some(knots) => knots,
+                None => continue,
+            };
+
+            let refinement = match curve.technique {
+                Some(technique) if !options.ignore_file_techniques => match technique {
+                    CurveTechnique::ConstantParameter(resolution) => {
+                        Refinement::Steps(resolution as f64 * curve.degree as f64)
+                    }
+                    CurveTechnique::ConstantSpace(max_length) => {
+                        Refinement::MaxLength(max_length as f64)
+                    }
+                    CurveTechnique::Curvature {
+                        max_distance,
+                        max_angle,
+                    } => Refinement::Curvature {
+                        max_distance: max_distance as f64,
+                        max_angle: max_angle as f64,
+                    },
+                },
+                _ => Refinement::Steps(options.resolution as f64),
+            };
+            let samples = knots.samples(range, |first| {
+                refinement.steps(&points[first..=first + knots.degree])
+            });
+
+            let start = mesh.positions.len() as u32 / 3;
+            let [first, last] = [samples[0], samples[samples.len() - 1]];
+            for &u in &samples {
+                let position = combine(knots.basis(u), &points);
+                mesh.positions
+                    .extend(position.iter().map(|&x| from_f64::<T>(x)));
+                mesh.texcoords
+                    .extend([from_f64(normalize(u, [first, last])), T::zero()]);
+            }
+            mesh.line_indices
+                .extend(start..start + samples.len() as u32);
+            mesh.line_arities.push(samples.len() as u32);
+        }
+        mesh
+    }
+
+    /// Append the triangles of a surface to `mesh`, unless the surface cannot
+    /// be tessellated.
+    fn tessellate_surface(
+        &self,
+        surface: &Surface<T>,
+        options: &TessellationOptions,
+        mesh: &mut Mesh<T>,
+    ) -> Option<()> {
+        let [degree_u, degree_v] = surface.degree;
+        let range_u = surface.range_u.map(to_f64);
+        let range_v = surface.range_v.map(to_f64);
+        let knots_u = Knots::new(surface.basis, degree_u, &surface.knots_u, range_u, None)?;
+        let knots_v = Knots::new(surface.basis, degree_v, &surface.knots_v, range_v, None)?;
+        let count_u = knots_u.count;
+        let points = self.control_points(&surface.control_points, surface.rational)?;
+        if points.len() != count_u * knots_v.count {
+            return None;
+        }
+        // Texture coordinates are weighted like the positions.
+        let texcoords = match surface.texcoord_indices.len() == points.len() {
+            true => surface
+                .texcoord_indices
+                .iter()
+                .zip(&points)
+                .map(|(&i, point)| {
+                    let t = self.texcoords.get(i as usize * 2..i as usize * 2 + 2)?;
+                    Some([to_f64(t[0]), to_f64(t[1]), 0.0, point[3]])
+                })
+                .collect::<Option<Vec<_>>>(),
+            false => None,
+        };
+
+        let (refinement_u, refinement_v, trim_resolution) = match surface.technique {
+            Some(technique) if !options.ignore_file_techniques => match technique {
+                SurfaceTechnique::ConstantParameterA {
+                    resolution_u,
+                    resolution_v,
+                } => (
+                    Refinement::Steps(resolution_u as f64 * degree_u as f64),
+                    Refinement::Steps(resolution_v as f64 * degree_v as f64),
+                    None,
+                ),
+                SurfaceTechnique::ConstantParameterB(resolution) => (
+                    Refinement::Steps(resolution as f64 * degree_u as f64),
+                    Refinement::Steps(resolution as f64 * degree_v as f64),
+                    Some(resolution as f64),
+                ),
+                SurfaceTechnique::ConstantSpace(max_length) => (
+                    Refinement::MaxLength(max_length as f64),
+                    Refinement::MaxLength(max_length as f64),
+                    None,
+                ),
+                SurfaceTechnique::Curvature {
+                    max_distance,
+                    max_angle,
+                } => {
+                    let refinement = Refinement::Curvature {
+                        max_distance: max_distance as f64,
+                        max_angle: max_angle as f64,
+                    };
+                    (refinement, refinement, None)
+                }
+            },
+            _ => {
+                let refinement = Refinement::Steps(options.resolution as f64);
+                (refinement, refinement, None)
+            }
+        };
+
+        // Each span needs as many steps as its most demanding row or column
+        // of control points.
+        let samples_u = knots_u.samples(range_u, |first| {
+            (0..knots_v.count)
+                .map(|j| {
+                    let row = &points[j * count_u..(j + 1) * count_u];
+                    refinement_u.steps(&row[first..=first + degree_u as usize])
+                })
+                .max()
+                .unwrap_or(1)
+        });
+        let samples_v = knots_v.samples(range_v, |first| {
+            (0..count_u)
+                .map(|i| {
+                    let column: Vec<_> = (first..=first + degree_v as usize)
+                        .map(|j| points[j * count_u + i])
+                        .collect();
+                    refinement_v.steps(&column)
+                })
+                .max()
+                .unwrap_or(1)
+        });
+
+        let trim_loops = |loops: &[Vec<TrimCurve<T>>]| {
+            loops
+                .iter()
+                .map(|segments| self.trim_polygon(segments, options, trim_resolution))
+                .collect::<Option<Vec<_>>>()
+        };
+        let trims = trim_loops(&surface.trims)?;
+        let holes = trim_loops(&surface.holes)?;
+        let keep = |point: [f64; 2]| {
+            (trims.is_empty() || trims.iter().any(|polygon| contains(polygon, point)))
+                && !holes.iter().any(|polygon| contains(polygon, point))
+        };
+
+        let bounds_u = [samples_u[0], samples_u[samples_u.len() - 1]];
+        let bounds_v = [samples_v[0], samples_v[samples_v.len() - 1]];
+        // Only vertices of triangles that are not trimmed away are added.
+        let mut vertices = vec![None; samples_u.len() * samples_v.len()];
+        let mut vertex = |i: usize, j: usize, mesh: &mut Mesh<T>| {
+            *vertices[j * samples_u.len() + i].get_or_insert_with(|| {
+                let (u, v) = (samples_u[i], samples_v[j]);
+                let basis = surface_basis(&knots_u, &knots_v, u, v);
+                let position = combine(basis.iter().copied(), &points);
+                mesh.positions
+                    .extend(position.iter().map(|&x| from_f64::<T>(x)));
+                let texcoord = match &texcoords {
+                    Some(texcoords) => {
+                        let [s, t, _] = combine(basis.iter().copied(), texcoords);
+                        [s, t]
+                    }
+                    None => [normalize(u, bounds_u), normalize(v, bounds_v)],
+                };
+                mesh.texcoords
+                    .extend(texcoord.iter().map(|&x| from_f64::<T>(x)));
+                mesh.positions.len() as u32 / 3 - 1
+            })
+        };
+
+        for j in 0..samples_v.len() - 1 {
+            for i in 0..samples_u.len() - 1 {
+                let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                for triangle in [[0, 1, 2], [0, 2, 3]] {
+                    let centroid = triangle.iter().fold([0.0; 2], |sum, &corner| {
+                        let (i, j) = corners[corner];
+                        [sum[0] + samples_u[i] / 3.0, sum[1] + samples_v[j] / 3.0]
+                    });
+                    if !keep(centroid) {
+                        continue;
+                    }
+                    for &corner in &triangle {
+                        let (i, j) = corners[corner];
+                        let index = vertex(i, j, mesh);
+                        mesh.indices.push(index);
+                    }
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Look up the positions of control points with their weights, which are
+    /// `1` unless `rational`.
+    fn control_points(&self, indices: &[u32], rational: bool) -> Option<Vec<Point>> {
+        indices
+            .iter()
+            .map(|&i| {
+                let i = i as usize;
+                let p = self.positions.get(i * 3..i * 3 + 3)?;
+                let weight = match self.weights.get(i) {
+                    Some(&weight) if rational => to_f64(weight),
+                    _ => 1.0,
+                };
+                Some([to_f64(p[0]), to_f64(p[1]), to_f64(p[2]), weight])
+            })
+            .collect()
+    }
+
+    /// Sample a trimming loop into a polygon in the parameter space of its
+    /// surface.
+    fn trim_polygon(
+        &self,
+        segments: &[TrimCurve<T>],
+        options: &TessellationOptions,
+        resolution: Option<f64>,
+    ) -> Option<Vec<[f64; 2]>> {
+        let mut polygon = Vec::new();
+        for segment in segments {
+            let curve = self.curves_2d.get(segment.curve)?;
+            let range = segment.range.map(to_f64);
+            let points = curve
+                .control_points
+                .iter()
+                .map(|&i| {
+                    let p = self
+                        .parameter_vertices
+                        .get(i as usize * 3..i as usize * 3 + 3)?;
+                    let weight = if curve.rational { to_f64(p[2]) } else { 1.0 };
+                    Some([to_f64(p[0]), to_f64(p[1]), 0.0, weight])
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let knots = Knots::new(
+                curve.basis,
+                curve.degree,
+                &curve.knots,
+                range,
+                Some(points.len()),
+            )?;
+
+            let steps = match resolution {
+                Some(resolution) => Refinement::Steps(resolution * curve.degree as f64),
+                None => Refinement::Steps(options.resolution as f64),
+            };
+            for u in knots.samples(range, |first| {
+                steps.steps(&points[first..=first + knots.degree])
+            }) {
+                let [x, y, _] = combine(knots.basis(u), &points);
+                polygon.push([x, y]);
+            }
+        }
+        Some(polygon)
+    }
+}
+
+/// A control point with its weight, `[x, y, z, w]`.
+type Point = [f64; 4];
+
+/// The weighted sum of control points for the given basis function values.
+fn combine(basis: impl Iterator<Item = (usize, f64)>, points: &[Point]) -> [f64; 3] {
+    let mut sum = [0.0; 3];
+    let mut weight = 0.0;
+    for (i, b) in basis {
+        let [x, y, z, w] = points[i];
+        let bw = b * w;
+        sum = [sum[0] + bw * x, sum[1] + bw * y, sum[2] + bw * z];
+        weight += bw;
+    }
+    if weight != 0.0 {
+        sum = sum.map(|x| x / weight);
+    }
+    sum
+}
+
+/// The non-zero basis function values of a surface at `(u, v)` with the
+/// indices of their control points.
+fn surface_basis(knots_u: &Knots, knots_v: &Knots, u: f64, v: f64) -> Vec<(usize, f64)> {
+    let basis_u: Vec<_> = knots_u.basis(u).collect();
+    knots_v
+        .basis(v)
+        .flat_map(|(j, bv)| {
+            basis_u
+                .iter()
+                .map(move |&(i, bu)| (j * knots_u.count + i, bu * bv))
+        })
+        .collect()
+}
+
+/// Map `x` from `range` to `[0, 1]`.
+fn normalize(x: f64, [start, end]: [f64; 2]) -> f64 {
+    if start == end {
+        0.0
+    } else {
+        (x - start) / (end - start)
+    }
+}
+
+/// Whether a point is inside a polygon, by the even-odd rule.
+fn contains(polygon: &[[f64; 2]], [x, y]: [f64; 2]) -> bool {
+    let mut inside = false;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + polygon.len() - 1) % polygon.len()];
+        if (a[1] > y) != (b[1] > y) && x < (b[0] - a[0]) * (y - a[1]) / (b[1] - a[1]) + a[0] {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// How finely a polynomial span is sampled.
+#[derive(Debug, Clone, Copy)]
+enum Refinement {
+    /// A fixed number of steps.
+    Steps(f64),
+    /// Steps no longer than the given length.
+    MaxLength(f64),
+    /// Steps that deviate and turn by no more than the given distance and
+    /// angle.
+    Curvature { max_distance: f64, max_angle: f64 },
+}
+
+impl Refinement {
+    /// The number of steps for a span, estimated from the control points it
+    /// depends on.
+    fn steps(&self, points: &[Point]) -> usize {
+        let points: Vec<[f64; 3]> = points.iter().map(|p| [p[0], p[1], p[2]]).collect();
+        let steps = match *self {
+            Refinement::Steps(steps) => steps,
+            Refinement::MaxLength(max_length) => {
+                points.windows(2).map(|w| distance(w[0], w[1])).sum::<f64>() / max_length
+            }
+            Refinement::Curvature {
+                max_distance,
+                max_angle,
+            } => {
+                let angle: f64 = points
+                    .windows(3)
+                    .map(|w| angle(sub(w[1], w[0]), sub(w[2], w[1])))
+                    .sum();
+                let deviation = points
+                    .iter()
+                    .map(|&p| distance_to_line(p, points[0], points[points.len() - 1]))
+                    .fold(0.0, f64::max);
+                // The deviation of a chord shrinks with the square of the
+                // number of steps.
+                (angle / max_angle).max((deviation / max_distance).sqrt())
+            }
+        };
+        // `max` also turns NaN into a single step.
+        steps.ceil().max(1.0).min(MAX_STEPS as f64) as usize
+    }
+}
+
+/// The parameter values of a curve or one direction of a surface, as a
+/// B-spline knot vector.
+struct Knots {
+    degree: usize,
+    knots: Vec<f64>,
+    // number of control points
+    count: usize,
+}
+
+impl Knots {
+    /// Get the knot vector of a curve or surface direction from its `parm`
+    /// values, `None` if it cannot be tessellated.
+    ///
+    /// Bezier `parm` values are the breakpoints between segments. Without them
+    /// `range` is split into as many segments as the `count` of control
+    /// points requires, or a single one if unknown.
+    // `repeat_n` and `Option::is_none_or` need Rust 1.82.
+    #[allow(clippy::manual_repeat_n, clippy::unnecessary_map_or)]
+    fn new<T: ParseableV>(
+        basis: Basis,
+        degree: u32,
+        parameters: &[T],
+        range: [f64; 2],
+        count: Option<usize>,
+    ) -> Option<Self> {
+        let degree = degree as usize;
+        if degree == 0 {
+            return None;
+        }
+        let knots: Vec<f64> = match basis {
+            Basis::BSpline => parameters.iter().map(|&k| to_f64(k)).collect(),
+            Basis::Bezier => {
+                let breakpoints: Vec<f64> = if parameters.len() >= 2 {
+                    parameters.iter().map(|&k| to_f64(k)).collect()
+                } else {
+                    let segments = count.map_or(1, |count| count.saturating_sub(1) / degree);
+                    let [start, end] = [range[0].min(range[1]), range[0].max(range[1])];
+                    (0..=segments.max(1))
+                        .map(|i| start + (end - start) * i as f64 / segments.max(1) as f64)
+                        .collect()
+                };
+                // Segments share their end points.
+                let last = breakpoints.len() - 1;
+                breakpoints
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, &b)| {
+                        let multiplicity = if i == 0 || i == last {
+                            degree + 1
+                        } else {
+                            degree
+                        };
+                        std::iter::repeat(b).take(multiplicity)
+                    })
+                    .collect()
+            }
+            _ => return None,
+        };
+
+        let knots = Self {
+            degree,
+            count: knots.len().checked_sub(degree + 1)?,
+            knots,
+        };
+        let valid = knots.count > degree
+            && count.map_or(true, |count| count == knots.count)
+            && knots.knots.windows(2).all(|w| w[0] <= w[1])
+            && knots.knots[degree] < knots.knots[knots.count];
+        if valid {
+            Some(knots)
+        } else {
+            None
+        }
+    }
+
+    /// The parameter values to sample within `range`, clamped to the domain
+    /// of the knot vector. Each span is split into the number of `steps`
+    /// returned for the index of its first control point.
+    fn samples(&self, range: [f64; 2], steps: impl Fn(usize) -> usize) -> Vec<f64> {
+        let domain = [self.knots[self.degree], self.knots[self.count]];
+        let clamp = |x: f64| x.max(domain[0]).min(domain[1]);
+        let (start, end) = (clamp(range[0].min(range[1])), clamp(range[0].max(range[1])));
+
+        let mut samples = vec![start];
+        for span in self.degree..self.count {
+            let (a, b) = (self.knots[span].max(start), self.knots[span + 1].min(end));
+            if a >= b {
+                continue;
+            }
+            let steps = steps(span - self.degree);
+            samples.extend((1..=steps).map(|i| a + (b - a) * i as f64 / steps as f64));
+        }
+        // A curve may be traversed backwards.
+        if range[0] > range[1] {
+            samples.reverse();
+        }
+        samples
+    }
+
+    /// The non-zero basis function values at `u` with the indices of their
+    /// control points.
+    fn basis(&self, u: f64) -> impl Iterator<Item = (usize, f64)> {
+        let (p, k) = (self.degree, &self.knots);
+        // The last non-empty span starting at or before `u`.
+        let span = (p..self.count)
+            .rev()
+            .find(|&span| k[span] < k[span + 1] && k[span] <= u)
+            .unwrap_or(p);
+
+        // Cox-de Boor recursion.
+        let mut n = vec![0.0; p + 1];
+        let mut left = vec![0.0; p + 1];
+        let mut right = vec![0.0; p + 1];
+        n[0] = 1.0;
+        for j in 1..=p {
+            left[j] = u - k[span + 1 - j];
+            right[j] = k[span + j] - u;
+            let mut saved = 0.0;
+            for r in 0..j {
+                let denominator = right[r + 1] + left[j - r];
+                let temp = if denominator == 0.0 {
+                    0.0
+                } else {
+                    n[r] / denominator
+                };
+                n[r] = saved + right[r + 1] * temp;
+                saved = left[j - r] * temp;
+            }
+            n[j] = saved;
+        }
+        n.into_iter()
+            .enumerate()
+            .map(move |(i, b)| (span - p + i, b))
+    }
+}
+
+#[inline]
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+#[inline]
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline]
+fn distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    dot(sub(a, b), sub(a, b)).sqrt()
+}
+
+/// The angle between two vectors in degrees, `0` if either is zero.
+fn angle(a: [f64; 3], b: [f64; 3]) -> f64 {
+    let lengths = dot(a, a).sqrt() * dot(b, b).sqrt();
+    if lengths == 0.0 {
+        return 0.0;
+    }
+    (dot(a, b) / lengths).clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+/// The distance of `p` from the line through `a` and `b`.
+fn distance_to_line(p: [f64; 3], a: [f64; 3], b: [f64; 3]) -> f64 {
+    let line = sub(b, a);
+    let length = dot(line, line);
+    let offset = sub(p, a);
+    if length == 0.0 {
+        return dot(offset, offset).sqrt();
+    }
+    let t = dot(offset, line) / length;
+    distance(
+        p,
+        [a[0] + t * line[0], a[1] + t * line[1], a[2] + t * line[2]],
+    )
+}
@@ -2,11 +2,12 @@ use std::{
     env,
     fs::File,
     io::{BufReader, Cursor},
+    path::Path,
 };
 
-const CORNELL_BOX_OBJ: &'static str = include_str!("../obj/cornell_box.obj");
-const CORNELL_BOX_MTL1: &'static str = include_str!("../obj/cornell_box.mtl");
-const CORNELL_BOX_MTL2: &'static str = include_str!("../obj/cornell_box2.mtl");
+const CORNELL_BOX_OBJ: &str = include_str!("../obj/cornell_box.obj");
+const CORNELL_BOX_MTL1: &str = include_str!("../obj/cornell_box.mtl");
+const CORNELL_BOX_MTL2: &str = include_str!("../obj/cornell_box2.mtl");
 
 #[test]
 fn simple_triangle() {
@@ -71,7 +72,12 @@ fn simple_triangle_colored() {
     assert_eq!(mesh.indices, expect_idx);
 
     // Verify vertex colors are loaded
-    let expect_vertex_color = vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+    #[rustfmt::skip]
+    let expect_vertex_color = vec![
+        1.0, 0.0, 0.0, 1.0,
+        0.0, 1.0, 0.0, 1.0,
+        0.0, 0.0, 1.0, 1.0,
+    ];
     assert_eq!(mesh.vertex_color, expect_vertex_color);
 }
 
@@ -115,11 +121,11 @@ fn simple_quad_colored_merge() {
     // Verify vertex colors are loaded and correctly indexed.
     #[rustfmt::skip]
     let expect_vertex_color = vec![
-        1.0, 0.0, 0.0,
-        0.0, 1.0, 0.0,
-        0.0, 0.0, 1.0,
-        0.0, 0.0, 0.0,
-        1.0, 1.0, 1.0,
+        1.0, 0.0, 0.0, 1.0,
+        0.0, 1.0, 0.0, 1.0,
+        0.0, 0.0, 1.0, 1.0,
+        0.0, 0.0, 0.0, 1.0,
+        1.0, 1.0, 1.0, 1.0,
     ];
     assert_eq!(mesh.vertex_color, expect_vertex_color);
     let expect_vertex_color_index = vec![0, 1, 2, 3, 2, 4];
@@ -386,10 +392,8 @@ fn validate_cornell(models: Vec<crate::Model<f64>>, mats: Vec<crate::Material>)
     assert_eq!(mat.ambient, [0.0, 0.0, 0.0]);
     assert_eq!(mat.diffuse, [1.0, 1.0, 1.0]);
     assert_eq!(mat.specular, [0.0, 0.0, 0.0]);
-    assert_eq!(
-        mat.unknown_param.get("Ke").map(|s| s.as_ref()),
-        Some("1 1 1")
-    );
+    assert_eq!(mat.emissive, Some([1.0, 1.0, 1.0]));
+    assert!(mat.unknown_param.is_empty());
     assert_eq!(mat.illumination_model, None);
 
     // Verify red material loaded properly
@@ -512,6 +516,55 @@ fn test_async_custom_material_loader() {
     validate_cornell(models, mats);
 }
 
+#[cfg(feature = "async")]
+#[test]
+fn test_async_load_files() {
+    let m = tokio_test::block_on(crate::load_obj_async(
+        "obj/cornell_box.obj",
+        &crate::LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..Default::default()
+        },
+    ));
+    let (models, mats) = m.unwrap();
+    validate_cornell(models, mats.unwrap());
+
+    let err = tokio_test::block_on(crate::load_mtl_async("obj/does_not_exist.mtl")).unwrap_err();
+    assert_eq!(err.kind(), crate::LoadErrorKind::OpenFileFailed);
+    assert_eq!(err.path(), Some(Path::new("obj/does_not_exist.mtl")));
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_yields() {
+    use std::{
+        future::Future,
+        task::{Context, Poll, Waker},
+    };
+
+    // A reader with all data at hand never blocks on its own.
+    let obj = "v 0 0 0\n".repeat(100_000);
+    let mut reader = Cursor::new(obj.as_bytes());
+    let options = crate::LoadOptions::default();
+    let mut future = Box::pin(crate::load_obj_buf_async::<_, f32, _, _>(
+        &mut reader,
+        &options,
+        |_| async { unreachable!() },
+    ));
+
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut pending = 0;
+    let (models, _) = loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(result) => break result.unwrap(),
+            Poll::Pending => pending += 1,
+        }
+    };
+    assert!(pending > 1);
+    assert_eq!(models.len(), 1);
+}
+
 #[test]
 fn test_custom_material_loader_files() {
     let dir = env::current_dir().unwrap();
@@ -552,6 +605,163 @@ fn test_custom_material_loader_files() {
     validate_cornell(models, mats);
 }
 
+#[test]
+fn stock_material_loaders() {
+    use crate::MaterialLoader;
+    use std::cell::Cell;
+
+    // Libraries are found in any of the directories, whatever their case.
+    let search = crate::SearchPathLoader::new(["missing", "obj"]);
+    let (mats, _) = search.load(Path::new("CORNELL_BOX.MTL")).unwrap();
+    assert_eq!(mats.len(), 2);
+    let err = search.load(Path::new("missing.mtl")).unwrap_err();
+    assert_eq!(err.kind(), crate::LoadErrorKind::OpenFileFailed);
+
+    let mut memory = crate::MemoryLoader::new();
+    memory.insert("cornell_box.mtl", CORNELL_BOX_MTL1);
+    memory.insert("cornell_box2.mtl", CORNELL_BOX_MTL2);
+    let (models, mats) = crate::load_obj_buf(
+        &mut Cursor::new(CORNELL_BOX_OBJ),
+        &crate::LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..Default::default()
+        },
+        |p| memory.load(p),
+    )
+    .unwrap();
+    validate_cornell(models, mats.unwrap());
+
+    // Every library is loaded once, however many files use it.
+    let loads = Cell::new(0);
+    let caching = crate::CachingLoader::new(|p: &Path| {
+        loads.set(loads.get() + 1);
+        memory.load(p)
+    });
+    for _ in 0..3 {
+        let (_, mats) = crate::load_obj_with_loader::<_, _, f32>(
+            "obj/cornell_box.obj",
+            &crate::LoadOptions::default(),
+            &caching,
+        )
+        .unwrap();
+        assert_eq!(mats.unwrap().len(), 5);
+    }
+    assert_eq!(loads.get(), 2);
+    caching.clear();
+    caching.load(Path::new("cornell_box.mtl")).unwrap();
+    assert_eq!(loads.get(), 3);
+}
+
+#[test]
+fn resolve_texture_paths() {
+    let root = env::temp_dir().join(format!("tobj_textures_{}", std::process::id()));
+    for dir in ["mtl/textures", "fallback"] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["mtl/textures/wood.png", "fallback/spec.png", "alpha.png"] {
+        std::fs::write(root.join(file), b"").unwrap();
+    }
+    let alpha = root.join("alpha.png");
+    let mtl = format!(
+        "newmtl wood
+map_Kd textures\\Wood.PNG
+map_Ks C:\\Users\\artist\\spec.png
+map_d {}
+map_Bump /home/artist/bump.png
+",
+        alpha.display()
+    );
+    std::fs::write(root.join("mtl/lib.mtl"), mtl).unwrap();
+    let (mats, _) = crate::load_mtl(root.join("mtl/lib.mtl")).unwrap();
+    let wood = &mats[0];
+    assert_eq!(
+        wood.mtl_file.as_deref(),
+        Some(root.join("mtl/lib.mtl").as_path())
+    );
+    assert_eq!(
+        wood.diffuse_texture.normalized_path(),
+        Path::new("textures/Wood.PNG")
+    );
+
+    // Relative to the `MTL` file with any case, in the search directories by
+    // file name alone and at absolute paths that exist.
+    let resolver = crate::TextureResolver::new([root.join("fallback")]);
+    let resolve = |texture| resolver.resolve(wood, texture);
+    assert_eq!(
+        resolve(&wood.diffuse_texture),
+        Some(root.join("mtl/textures/wood.png"))
+    );
+    assert_eq!(
+        resolve(&wood.specular_texture),
+        Some(root.join("fallback/spec.png"))
+    );
+    assert_eq!(resolve(&wood.dissolve_texture), Some(alpha));
+    assert_eq!(resolve(&wood.normal_texture), None);
+    assert_eq!(
+        resolver.missing(&mats),
+        [crate::MissingTexture {
+            material: 0,
+            file_name: "/home/artist/bump.png".to_string(),
+        }]
+    );
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+#[cfg(feature = "image")]
+fn decode_textures() {
+    use std::sync::Arc;
+
+    let root = env::temp_dir().join(format!("tobj_decode_{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let pixels = [255, 0, 0, 255, 0, 255, 0, 128];
+    image::save_buffer(
+        root.join("red_green.png"),
+        &pixels,
+        2,
+        1,
+        image::ExtendedColorType::Rgba8,
+    )
+    .unwrap();
+    std::fs::write(root.join("broken.png"), b"not a png").unwrap();
+    let mtl = "newmtl first
+map_Kd red_green.png
+map_Ks broken.png
+newmtl second
+map_Kd red_green.png
+map_d broken.png
+norm missing.png
+";
+    std::fs::write(root.join("lib.mtl"), mtl).unwrap();
+    let (mats, _) = crate::load_mtl(root.join("lib.mtl")).unwrap();
+
+    let decoded = crate::TextureResolver::default().decode(&mats);
+    let first = decoded.materials[0].get(&mats[0].diffuse_texture).unwrap();
+    assert_eq!((first.width, first.height), (2, 1));
+    assert_eq!(first.pixels, pixels);
+
+    // Both materials share the texture, which is decoded once, as is the
+    // broken one.
+    let second = decoded.materials[1].get(&mats[1].diffuse_texture).unwrap();
+    assert!(Arc::ptr_eq(first, second));
+    assert!(decoded.materials[0]
+        .get(&mats[0].specular_texture)
+        .is_none());
+    assert_eq!(decoded.errors.len(), 1);
+    assert_eq!(decoded.errors[0].path, root.join("broken.png"));
+    assert_eq!(
+        decoded.missing,
+        [crate::MissingTexture {
+            material: 1,
+            file_name: "missing.png".to_string(),
+        }]
+    );
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
 #[test]
 fn test_invalid_index() {
     let m = crate::load_obj::<_, f64>(
@@ -564,5 +774,1762 @@ fn test_invalid_index() {
     );
     assert!(m.is_err());
     let err = m.err().unwrap();
-    assert_eq!(err, crate::LoadError::FaceVertexOutOfBounds);
+    assert_eq!(err.kind(), crate::LoadErrorKind::FaceVertexOutOfBounds);
+    // The error points at the offending face.
+    assert_eq!(err.line(), Some(12));
+    assert_eq!(
+        err.path(),
+        Some(std::path::Path::new("obj/invalid_index.obj"))
+    );
+}
+
+#[test]
+fn error_context() {
+    let obj = "o Broken\nv 0 0 0\nv 1 0 0\nf 1 2 x\n";
+    let err = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &crate::LoadOptions::default(),
+        |_| unreachable!(),
+    )
+    .unwrap_err();
+    assert_eq!(err, crate::LoadErrorKind::FaceParseError);
+    assert_eq!(err.line(), Some(4));
+    assert_eq!(err.statement(), Some("f 1 2 x"));
+    assert_eq!(err.path(), None);
+    assert_eq!(err.to_string(), "line 4: face parse error: \"f 1 2 x\"");
+
+    let mtl = "newmtl good\nKd 1 1 1\n\nnewmtl bad\nKd 1 one 1\n";
+    let err = crate::load_mtl_buf(&mut Cursor::new(mtl)).unwrap_err();
+    assert_eq!(err.kind(), crate::LoadErrorKind::MaterialParseError);
+    assert_eq!(err.line(), Some(5));
+    assert_eq!(err.statement(), Some("Kd 1 one 1"));
+
+    // A missing file keeps its path and the underlying I/O error.
+    let err = crate::load_mtl("obj/does_not_exist.mtl").unwrap_err();
+    assert_eq!(err.kind(), crate::LoadErrorKind::OpenFileFailed);
+    assert_eq!(
+        err.path(),
+        Some(std::path::Path::new("obj/does_not_exist.mtl"))
+    );
+    assert_eq!(
+        err.io_error().map(|e| e.kind()),
+        Some(std::io::ErrorKind::NotFound)
+    );
+    assert!(std::error::Error::source(&err).is_some());
+    assert!(err
+        .to_string()
+        .starts_with("obj/does_not_exist.mtl: open file failed: "));
+}
+
+#[test]
+fn lenient_loading() {
+    let obj = "o Broken
+v 0 0 0
+v 1 0 0
+v 1 1
+v 0 one 0
+vt 0 0
+f 1 2 3
+f 1 2 x
+f 1 3 4
+f 1 2 9
+s maybe
+usemtl missing
+f 2 3 4
+";
+    let options = crate::LoadOptions {
+        lenient: true,
+        ..Default::default()
+    };
+    let (models, _, diagnostics) = crate::load_obj_buf_with_diagnostics::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &options,
+        |_| unreachable!(),
+    )
+    .unwrap();
+
+    use crate::{LoadErrorKind::*, Severity::*};
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.severity, d.kind(), d.line().unwrap()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (Warning, PositionParseError, 4),
+            (Warning, PositionParseError, 5),
+            (Error, FaceParseError, 8),
+            (Warning, SmoothingGroupParseError, 11),
+            (Warning, MaterialNotFound, 12),
+            (Error, FaceVertexOutOfBounds, 10),
+        ]
+    );
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "warning: line 4: position parse error: \"v 1 1\""
+    );
+
+    // Repaired vertices keep the numbering of the following ones intact.
+    let mesh = &models[0].mesh;
+    assert_eq!(
+        mesh.positions,
+        [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0]
+    );
+    assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3, 1, 2, 3]);
+
+    // Without `lenient` the first problem aborts loading.
+    let err = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &crate::LoadOptions::default(),
+        |_| unreachable!(),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), PositionParseError);
+    assert_eq!(err.line(), Some(4));
+}
+
+#[test]
+fn smoothing_groups() {
+    let obj = "
+o Groups
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 1
+s 1
+f 1 2 3 4
+s off
+f 1 2 5
+s 2
+f 2 3 5
+";
+    let load = |triangulate| {
+        crate::load_obj_buf::<_, _, f64>(
+            &mut Cursor::new(obj),
+            &crate::LoadOptions {
+                triangulate,
+                ..Default::default()
+            },
+            |_| unreachable!(),
+        )
+        .unwrap()
+        .0
+    };
+
+    let models = load(false);
+    assert_eq!(models[0].mesh.smoothing_groups, vec![1, 0, 2]);
+
+    // The quad is split into two triangles which both keep its group.
+    let models = load(true);
+    assert_eq!(models[0].mesh.smoothing_groups, vec![1, 1, 0, 2]);
+
+    // Without any `s` statements the smoothing groups are empty.
+    let m = crate::load_obj::<_, f64>("obj/quad.obj", &crate::GPU_LOAD_OPTIONS).unwrap();
+    assert!(m.0.iter().all(|m| m.mesh.smoothing_groups.is_empty()));
+
+    // A missing or malformed group is an error, which turns smoothing off
+    // when loading leniently.
+    let obj =
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\ns 1\nf 1 2 3\ns\nf 1 2 3\ns 1\nf 1 2 3\ns maybe\nf 1 2 3\n";
+    let error = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &crate::LoadOptions::default(),
+        |_| unreachable!(),
+    )
+    .unwrap_err();
+    assert_eq!(error.kind(), crate::LoadErrorKind::SmoothingGroupParseError);
+    assert_eq!(error.line(), Some(6));
+
+    let (models, _, diagnostics) = crate::load_obj_buf_with_diagnostics::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &crate::LoadOptions {
+            lenient: true,
+            ..Default::default()
+        },
+        |_| unreachable!(),
+    )
+    .unwrap();
+    assert_eq!(models[0].mesh.smoothing_groups, vec![1, 0, 1, 0]);
+    assert_eq!(diagnostics.len(), 2);
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.severity == crate::Severity::Warning));
+}
+
+#[test]
+fn generate_normals() {
+    // Two triangles meeting at a right angle along the edge from v1 to v2.
+    let obj = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 0 0 1
+f 1 2 3
+f 2 1 4
+";
+    let load = |single_index, mode, crease_angle| {
+        let mut models = crate::load_obj_buf::<_, _, f64>(
+            &mut Cursor::new(obj),
+            &crate::LoadOptions {
+                single_index,
+                generate_normals: Some(crate::NormalOptions { mode, crease_angle }),
+                ..Default::default()
+            },
+            |_| unreachable!(),
+        )
+        .unwrap()
+        .0;
+        models.remove(0).mesh
+    };
+    let s = 1.0 / 2f64.sqrt();
+
+    // Smooth: the shared edge gets the average of both face normals.
+    let mesh = load(true, crate::NormalMode::AreaWeighted, None);
+    assert_eq!(mesh.positions.len(), 12);
+    #[rustfmt::skip]
+    let expect_normals = vec![
+        0.0, s, s,
+        0.0, s, s,
+        0.0, 0.0, 1.0,
+        0.0, 1.0, 0.0,
+    ];
+    assert_eq!(mesh.normals, expect_normals);
+    assert_eq!(mesh.indices, vec![0, 1, 2, 1, 0, 3]);
+
+    // The angle between the faces exceeds the crease angle so the shared
+    // vertices are split.
+    let mesh = load(true, crate::NormalMode::AngleWeighted, Some(45.0));
+    assert_eq!(mesh.positions.len(), 18);
+    assert_eq!(mesh.indices, vec![0, 1, 2, 4, 5, 3]);
+    assert_eq!(&mesh.normals[12..], &[0.0, 1.0, 0.0, 0.0, 1.0, 0.0]);
+
+    let flat = load(true, crate::NormalMode::Flat, None);
+    assert_eq!(flat.indices, mesh.indices);
+    assert_eq!(flat.normals, mesh.normals);
+
+    // With multiple indices the topology is left untouched.
+    let mesh = load(false, crate::NormalMode::AreaWeighted, None);
+    assert_eq!(mesh.positions.len(), 12);
+    assert_eq!(mesh.normals, vec![0.0, s, s, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0]);
+    assert_eq!(mesh.normal_indices, vec![0, 0, 1, 0, 0, 2]);
+
+    let mesh = load(false, crate::NormalMode::Flat, None);
+    assert_eq!(mesh.normals, vec![0.0, 0.0, 1.0, 0.0, 1.0, 0.0]);
+    assert_eq!(mesh.normal_indices, vec![0, 0, 0, 1, 1, 1]);
+
+    // Faces after `s off` are flat shaded even though no face is part of a
+    // smoothing group.
+    let obj = format!("s off\n{}", obj);
+    let (models, _) = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &crate::LoadOptions {
+            single_index: true,
+            generate_normals: Some(Default::default()),
+            ..Default::default()
+        },
+        |_| unreachable!(),
+    )
+    .unwrap();
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.smoothing_groups, vec![0, 0]);
+    assert_eq!(mesh.indices, flat.indices);
+    assert_eq!(mesh.normals, flat.normals);
+
+    // Saving keeps the `s off`.
+    let mut saved = Vec::new();
+    crate::save_obj_buf(&mut saved, &models, &[], None).unwrap();
+    let (reloaded, _) = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(saved),
+        &crate::LoadOptions::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+    assert_eq!(reloaded[0].mesh.smoothing_groups, vec![0, 0]);
+}
+
+#[test]
+fn ear_clipping() {
+    // An L-shaped hexagon in the xz-plane. A fan around its first vertex
+    // would cover the notch of the L.
+    let obj = "
+v 2 0 1
+v 1 0 1
+v 1 0 2
+v 0 0 2
+v 0 0 0
+v 2 0 0
+f 1 2 3 4 5 6
+";
+    let load = |triangulation_method| {
+        let (models, _) = crate::load_obj_buf::<_, _, f64>(
+            &mut Cursor::new(obj),
+            &crate::LoadOptions {
+                triangulate: true,
+                triangulation_method,
+                single_index: true,
+                ..Default::default()
+            },
+            |_| unreachable!(),
+        )
+        .unwrap();
+        models[0].mesh.clone()
+    };
+    // Twice the signed area of each triangle in the xz-plane.
+    let signed_areas = |mesh: &crate::Mesh<f64>| {
+        mesh.indices
+            .chunks(3)
+            .map(|t| {
+                let p = |i: u32| {
+                    let i = i as usize * 3;
+                    [mesh.positions[i], mesh.positions[i + 2]]
+                };
+                let (a, b, c) = (p(t[0]), p(t[1]), p(t[2]));
+                (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let fan = load(crate::TriangulationMethod::Fan);
+    assert!(signed_areas(&fan).iter().any(|&area| area < 0.0));
+
+    let mesh = load(crate::TriangulationMethod::EarClipping);
+    assert_eq!(mesh.indices.len(), 12);
+    let areas = signed_areas(&mesh);
+    // All triangles have the winding of the polygon and cover its area.
+    assert!(areas.iter().all(|&area| area > 0.0));
+    assert_eq!(areas.iter().sum::<f64>(), 6.0);
+
+    // Convex polygons are triangulated exactly like a fan.
+    let options = crate::LoadOptions {
+        triangulation_method: crate::TriangulationMethod::EarClipping,
+        ..crate::GPU_LOAD_OPTIONS
+    };
+    let (ear_clipped, _) = crate::load_obj::<_, f64>("obj/quad.obj", &options).unwrap();
+    let (fan, _) = crate::load_obj::<_, f64>("obj/quad.obj", &crate::GPU_LOAD_OPTIONS).unwrap();
+    for (a, b) in ear_clipped.iter().zip(&fan) {
+        assert_eq!(a.mesh.indices, b.mesh.indices);
+    }
+
+    // Faces without vertices are rejected like they are by the fan.
+    for single_index in [false, true] {
+        let err = crate::load_obj_buf::<_, _, f64>(
+            &mut Cursor::new("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3\nf\n"),
+            &crate::LoadOptions {
+                single_index,
+                ..options
+            },
+            |_| unreachable!(),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), crate::LoadErrorKind::InvalidPolygon);
+    }
+}
+
+fn assert_same_models(a: &[crate::Model<f64>], b: &[crate::Model<f64>]) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(a.free_form, b.free_form);
+        let (a, b) = (&a.mesh, &b.mesh);
+        assert_eq!(a.positions, b.positions);
+        assert_eq!(a.vertex_color, b.vertex_color);
+        assert_eq!(a.weights, b.weights);
+        assert_eq!(a.vertex_mask, b.vertex_mask);
+        assert_eq!(a.normals, b.normals);
+        assert_eq!(a.texcoords, b.texcoords);
+        assert_eq!(a.texcoord_dimension, b.texcoord_dimension);
+        assert_eq!(a.indices, b.indices);
+        assert_eq!(a.face_arities, b.face_arities);
+        assert_eq!(a.smoothing_groups, b.smoothing_groups);
+        assert_eq!(a.face_materials, b.face_materials);
+        assert_eq!(a.line_indices, b.line_indices);
+        assert_eq!(a.line_arities, b.line_arities);
+        assert_eq!(a.point_indices, b.point_indices);
+        assert_eq!(a.texcoord_indices, b.texcoord_indices);
+        assert_eq!(a.normal_indices, b.normal_indices);
+        assert_eq!(a.material_id, b.material_id);
+    }
+}
+
+#[test]
+fn save_and_reload() {
+    let (models, mats) =
+        crate::load_obj::<_, f64>("obj/cornell_box.obj", &crate::GPU_LOAD_OPTIONS).unwrap();
+    let mats = mats.unwrap();
+
+    let mut obj = Vec::new();
+    crate::save_obj_buf(&mut obj, &models, &mats, Some("saved.mtl")).unwrap();
+    let mut mtl = Vec::new();
+    crate::save_mtl_buf(&mut mtl, &mats).unwrap();
+
+    let (reloaded, reloaded_mats) =
+        crate::load_obj_buf(&mut Cursor::new(&obj), &crate::GPU_LOAD_OPTIONS, |p| {
+            assert_eq!(p.to_str(), Some("saved.mtl"));
+            crate::load_mtl_buf(&mut Cursor::new(&mtl))
+        })
+        .unwrap();
+    assert_same_models(&models, &reloaded);
+    validate_cornell(reloaded, reloaded_mats.unwrap());
+
+    // Models without a material following ones with a material.
+    let mut models = models;
+    for model in models.iter_mut().skip(1).step_by(2) {
+        model.mesh.material_id = None;
+    }
+    let mut obj = Vec::new();
+    crate::save_obj_buf(&mut obj, &models, &mats, Some("saved.mtl")).unwrap();
+    let (reloaded, _) =
+        crate::load_obj_buf(&mut Cursor::new(&obj), &crate::GPU_LOAD_OPTIONS, |_| {
+            crate::load_mtl_buf(&mut Cursor::new(&mtl))
+        })
+        .unwrap();
+    assert_same_models(&models, &reloaded);
+
+    // Separate normal and texture coordinate indices, n-gons, vertex colors
+    // and smoothing groups.
+    let source = "
+o Quads
+v 0 0 0 1 0 0
+v 1 0 0 0 1 0
+v 1 1 0 0 0 1
+v 0 1 0 1 1 1
+v 2 0 0 0 0 0
+vt 0 0
+vt 1 0
+vt 1 1
+vn 0 0 1
+s 1
+f 1/1/1 2/2/1 3/3/1 4/1/1
+s off
+f 2/2/1 5/3/1 3/1/1
+o Tri
+f 1//1 2//1 5//1
+";
+    for options in [
+        crate::LoadOptions::default(),
+        crate::LoadOptions {
+            single_index: true,
+            ..Default::default()
+        },
+    ] {
+        let (models, _) = crate::load_obj_buf::<_, _, f64>(
+            &mut Cursor::new(source),
+            &options,
+            |_| unreachable!(),
+        )
+        .unwrap();
+        let mut obj = Vec::new();
+        crate::save_obj_buf(&mut obj, &models, &[], None).unwrap();
+        let (reloaded, _) =
+            crate::load_obj_buf(&mut Cursor::new(&obj), &options, |_| unreachable!()).unwrap();
+        assert_same_models(&models, &reloaded);
+    }
+
+    let mut model = crate::Model::new(crate::Mesh::<f32>::default(), "bad".to_string());
+    model.mesh.texcoord_dimension = 0;
+    let mut obj = Vec::new();
+    let err = crate::save_obj_buf(&mut obj, &[model], &[], None).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(obj.is_empty());
+}
+
+#[test]
+fn pbr_materials() {
+    let mtl = "newmtl metal
+Kd 0.8 0.8 0.8
+Pr 0.25
+Pm 1
+Ps 0.1
+Pc 0.5
+Pcr 0.03
+Ke 1 0.5 0
+aniso 0.2
+anisor 0.75
+map_Pr metal_roughness.png
+map_Pm metal metallic.png
+map_Ps metal_sheen.png
+map_Ke metal_emissive.png
+norm metal_normal.png
+map_Bump metal_bump.png
+";
+    let (mats, _) = crate::load_mtl_buf(&mut Cursor::new(mtl)).unwrap();
+    let mat = &mats[0];
+    assert_eq!(mat.roughness, Some(0.25));
+    assert_eq!(mat.metallic, Some(1.0));
+    assert_eq!(mat.sheen, Some(0.1));
+    assert_eq!(mat.clearcoat_thickness, Some(0.5));
+    assert_eq!(mat.clearcoat_roughness, Some(0.03));
+    assert_eq!(mat.emissive, Some([1.0, 0.5, 0.0]));
+    assert_eq!(mat.anisotropy, Some(0.2));
+    assert_eq!(mat.anisotropy_rotation, Some(0.75));
+    assert_eq!(mat.roughness_texture, "metal_roughness.png");
+    assert_eq!(mat.metallic_texture, "metal metallic.png");
+    assert_eq!(mat.sheen_texture, "metal_sheen.png");
+    assert_eq!(mat.emissive_texture, "metal_emissive.png");
+    assert_eq!(mat.pbr_normal_texture, "metal_normal.png");
+    assert_eq!(mat.normal_texture, "metal_bump.png");
+    assert_eq!(mat.textures().count(), 6);
+    assert!(mat.unknown_param.is_empty());
+
+    let mut saved = Vec::new();
+    crate::save_mtl_buf(&mut saved, &mats).unwrap();
+    let (reloaded, _) = crate::load_mtl_buf(&mut Cursor::new(&saved)).unwrap();
+    assert_eq!(reloaded[0].roughness, mat.roughness);
+    assert_eq!(reloaded[0].emissive, mat.emissive);
+    assert_eq!(reloaded[0].anisotropy_rotation, mat.anisotropy_rotation);
+    assert_eq!(reloaded[0].metallic_texture, mat.metallic_texture);
+    assert_eq!(reloaded[0].normal_texture, mat.normal_texture);
+    assert_eq!(reloaded[0].pbr_normal_texture, mat.pbr_normal_texture);
+
+    let err = crate::load_mtl_buf(&mut Cursor::new("newmtl bad\nPm shiny\n")).unwrap_err();
+    assert_eq!(err.kind(), crate::LoadErrorKind::MaterialParseError);
+    assert_eq!(err.line(), Some(2));
+}
+
+#[test]
+fn classic_materials() {
+    let mtl = "newmtl glass
+Tf 0.9 1 0.8
+Tr 0.75
+sharpness 200
+disp -bm 0.1 glass_disp.png
+decal glass_decal.png
+refl -type sphere glass_sphere.png
+refl -type cube_top top.png
+refl -type cube_left left.png
+map_aat on
+newmtl halo
+d -halo 0.5
+Tr 0.75
+";
+    let (mats, _) = crate::load_mtl_buf(&mut Cursor::new(mtl)).unwrap();
+    let glass = &mats[0];
+    assert_eq!(glass.transmission_filter, Some([0.9, 1.0, 0.8]));
+    assert_eq!(glass.dissolve, 0.25);
+    assert!(!glass.dissolve_halo);
+    assert_eq!(glass.sharpness, Some(200.0));
+    assert_eq!(glass.displacement_texture, "glass_disp.png");
+    assert_eq!(
+        glass.displacement_texture.options.bump_multiplier,
+        Some(0.1)
+    );
+    assert_eq!(glass.decal_texture, "glass_decal.png");
+    assert_eq!(glass.reflection_texture, "glass_sphere.png");
+    assert_eq!(glass.reflection_cube.top, "top.png");
+    assert_eq!(glass.reflection_cube.left, "left.png");
+    assert!(glass.reflection_cube.bottom.is_empty());
+    assert!(glass.texture_anti_aliasing);
+    assert!(glass.unknown_param.is_empty());
+
+    // `d` takes precedence over `Tr`, wherever it is.
+    let halo = &mats[1];
+    assert_eq!(halo.dissolve, 0.5);
+    assert!(halo.dissolve_halo);
+
+    let mut saved = Vec::new();
+    crate::save_mtl_buf(&mut saved, &mats).unwrap();
+    let (reloaded, _) = crate::load_mtl_buf(&mut Cursor::new(&saved)).unwrap();
+    for (a, b) in reloaded.iter().zip(&mats) {
+        assert_eq!(a.dissolve, b.dissolve);
+        assert_eq!(a.dissolve_halo, b.dissolve_halo);
+        assert_eq!(a.transmission_filter, b.transmission_filter);
+        assert_eq!(a.sharpness, b.sharpness);
+        assert_eq!(a.displacement_texture, b.displacement_texture);
+        assert_eq!(a.decal_texture, b.decal_texture);
+        assert_eq!(a.reflection_texture, b.reflection_texture);
+        assert_eq!(a.reflection_cube, b.reflection_cube);
+        assert_eq!(a.texture_anti_aliasing, b.texture_anti_aliasing);
+    }
+
+    for mtl in [
+        "newmtl bad\nrefl -type cube_up up.png\n",
+        "newmtl bad\nmap_aat yes\n",
+    ] {
+        let err = crate::load_mtl_buf(&mut Cursor::new(mtl)).unwrap_err();
+        assert_eq!(err.kind(), crate::LoadErrorKind::MaterialParseError);
+    }
+}
+
+#[test]
+fn color_sources() {
+    let mtl = "newmtl legacy
+Ka spectral ambient.rfl 0.5
+Kd xyz 0.9505 1 1.089
+Ks xyz 0.2
+Tf spectral filter.rfl
+Ke xyz 0.5 0.25 0
+newmtl rgb
+Kd spectral diffuse.rfl
+Kd 0.5 0.5 0.5
+";
+    let (mats, _) = crate::load_mtl_buf(&mut Cursor::new(mtl)).unwrap();
+    let legacy = &mats[0];
+    assert_eq!(
+        legacy.ambient_source,
+        Some(crate::ColorSource::Spectral {
+            file_name: "ambient.rfl".to_string(),
+            factor: 0.5
+        })
+    );
+    assert_eq!(legacy.ambient, [0.0; 3]);
+    assert_eq!(
+        legacy.specular_source,
+        Some(crate::ColorSource::Xyz([0.2; 3]))
+    );
+    assert_eq!(legacy.transmission_filter, None);
+    assert_eq!(
+        legacy.transmission_filter_source,
+        Some(crate::ColorSource::Spectral {
+            file_name: "filter.rfl".to_string(),
+            factor: 1.0
+        })
+    );
+
+    assert_eq!(legacy.emissive, None);
+    assert_eq!(
+        legacy.emissive_source,
+        Some(crate::ColorSource::Xyz([0.5, 0.25, 0.0]))
+    );
+
+    // The D65 white point is white in linear RGB, spectral curves are not
+    // converted.
+    let white = legacy.diffuse_source.as_ref().unwrap().to_rgb().unwrap();
+    for component in white {
+        assert!((component - 1.0).abs() < 1e-3);
+    }
+    assert_eq!(legacy.ambient_source.as_ref().unwrap().to_rgb(), None);
+
+    // A later RGB statement replaces the source.
+    assert_eq!(mats[1].diffuse, [0.5; 3]);
+    assert_eq!(mats[1].diffuse_source, None);
+
+    let mut saved = Vec::new();
+    crate::save_mtl_buf(&mut saved, &mats).unwrap();
+    let (reloaded, _) = crate::load_mtl_buf(&mut Cursor::new(&saved)).unwrap();
+    assert_eq!(reloaded[0].ambient_source, legacy.ambient_source);
+    assert_eq!(reloaded[0].diffuse_source, legacy.diffuse_source);
+    assert_eq!(reloaded[0].specular_source, legacy.specular_source);
+    assert_eq!(
+        reloaded[0].transmission_filter_source,
+        legacy.transmission_filter_source
+    );
+    assert_eq!(reloaded[0].emissive_source, legacy.emissive_source);
+
+    for mtl in [
+        "newmtl bad\nKd xyz 1 1\n",
+        "newmtl bad\nKd spectral\n",
+        "newmtl bad\nKe spectral\n",
+    ] {
+        let err = crate::load_mtl_buf(&mut Cursor::new(mtl)).unwrap_err();
+        assert_eq!(err.kind(), crate::LoadErrorKind::MaterialParseError);
+    }
+}
+
+#[test]
+fn texture_options() {
+    let mtl = "newmtl options
+map_Bump -bm 0.5 normal.png
+map_Kd -blendu off -blendv on -boost 2 -mm 0.1 0.9 -o 0.5 -s 2 2 -t 0 0 1 diffuse map.png
+map_Ks -texres 512 -clamp on -cc off specular.png
+map_d -imfchan m alpha.png
+map_Ka -type sphere -o 1 2 3 -1.png
+";
+    let (mats, _) = crate::load_mtl_buf(&mut Cursor::new(mtl)).unwrap();
+    let mat = &mats[0];
+
+    assert_eq!(mat.normal_texture.file_name, "normal.png");
+    assert_eq!(mat.normal_texture.options.bump_multiplier, Some(0.5));
+
+    let diffuse = &mat.diffuse_texture;
+    assert_eq!(diffuse, "diffuse map.png");
+    assert_eq!(diffuse.options.blend_u, Some(false));
+    assert_eq!(diffuse.options.blend_v, Some(true));
+    assert_eq!(diffuse.options.boost, Some(2.0));
+    assert_eq!(diffuse.options.value_modifier, Some([0.1, 0.9]));
+    assert_eq!(diffuse.options.offset, Some([0.5, 0.0, 0.0]));
+    assert_eq!(diffuse.options.scale, Some([2.0, 2.0, 1.0]));
+    assert_eq!(diffuse.options.turbulence, Some([0.0, 0.0, 1.0]));
+
+    let specular = &mat.specular_texture.options;
+    assert_eq!(specular.resolution, Some(512));
+    assert_eq!(specular.clamp, Some(true));
+    assert_eq!(specular.color_correction, Some(false));
+
+    assert_eq!(
+        mat.dissolve_texture.options.channel,
+        Some(crate::TextureChannel::Matte)
+    );
+
+    let ambient = &mat.ambient_texture;
+    assert_eq!(ambient, "-1.png");
+    assert_eq!(ambient.options.map_type.as_deref(), Some("sphere"));
+    assert_eq!(ambient.options.offset, Some([1.0, 2.0, 3.0]));
+    assert!(mat.shininess_texture.is_empty());
+
+    // Options are written back in front of the file name.
+    assert_eq!(mat.normal_texture.to_string(), "-bm 0.5 normal.png");
+    let mut saved = Vec::new();
+    crate::save_mtl_buf(&mut saved, &mats).unwrap();
+    let (reloaded, _) = crate::load_mtl_buf(&mut Cursor::new(&saved)).unwrap();
+    assert_eq!(reloaded[0].diffuse_texture, mat.diffuse_texture);
+    assert_eq!(reloaded[0].ambient_texture, mat.ambient_texture);
+
+    for bad in [
+        "map_Kd -bm",
+        "map_Kd -clamp maybe tex.png",
+        "map_Kd -imfchan x tex.png",
+    ] {
+        let mtl = format!("newmtl bad\n{}\n", bad);
+        let err = crate::load_mtl_buf(&mut Cursor::new(mtl)).unwrap_err();
+        assert_eq!(err.kind(), crate::LoadErrorKind::MaterialParseError);
+    }
+}
+
+#[test]
+fn load_from_bytes() {
+    for file in [
+        "cornell_box",
+        "empty_name_triangle",
+        "lines",
+        "quad",
+        "quad_colored_merge",
+        "triangle",
+        "triangle_colored",
+    ] {
+        let path = format!("obj/{}.obj", file);
+        let bytes = std::fs::read(&path).unwrap();
+        for options in [
+            crate::GPU_LOAD_OPTIONS,
+            crate::OFFLINE_RENDERING_LOAD_OPTIONS,
+        ] {
+            let load_mtl = |p: &Path| {
+                let bytes = std::fs::read(Path::new("obj").join(p)).unwrap();
+                crate::load_mtl_bytes(&bytes)
+            };
+            let (models, mats) = crate::load_obj::<_, f64>(&path, &options).unwrap();
+            let (from_bytes, mats_from_bytes) =
+                crate::load_obj_bytes(&bytes, &options, load_mtl).unwrap();
+            assert_same_models(&models, &from_bytes);
+            assert_eq!(
+                mats.map(|m| m.len()).ok(),
+                mats_from_bytes.map(|m| m.len()).ok()
+            );
+        }
+    }
+
+    // Windows line endings and a missing final line ending.
+    let (models, _) = crate::load_obj_bytes::<_, f64>(
+        b"o Tri\r\nv 0 0 0\r\nv 1 0 0\r\nv 0 1 0\r\nf 1 2 3",
+        &crate::LoadOptions::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+    assert_eq!(models[0].name, "Tri");
+    assert_eq!(models[0].mesh.indices, [0, 1, 2]);
+
+    // Errors report the same lines as when loading from a reader.
+    for obj in [
+        &b"v 0 0 0\n\nf 1 x\n"[..],
+        b"v 0 0 0\r\n\xff\n",
+        b"v 0 0 0\nv 1 \xc3 0\nv 2 0 0\n",
+        b"o A\no B\n\xff",
+    ] {
+        let options = crate::LoadOptions::default();
+        let from_buf =
+            crate::load_obj_buf::<_, _, f64>(&mut Cursor::new(obj), &options, |_| unreachable!());
+        let from_bytes = crate::load_obj_bytes::<_, f64>(obj, &options, |_| unreachable!());
+        assert_eq!(from_buf.unwrap_err(), from_bytes.unwrap_err());
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn load_parallel() {
+    let mixed = "mtllib cornell_box.mtl
+v 0 0 0
+v 1 0 0\r
+v 1 1 0 1 0 0
+v 0 1 0
+#MRGB 80ff000000ffffff
+vt 0 0
+vn 0 0 1
+o First
+usemtl white
+s 1
+f 1/1/1 2/1/1 3/1/1
+f -4//-1 -2//-1 -1//-1
+g Second
+usemtl red
+s off
+f 1 2 3 4
+v 0 0 1 0.5
+v one 0 1
+l 5 6
+f 1 2 9
+o Third
+f -1 -2 -3
+f 1 2 \\
+  3
+cstype bezier
+deg 1
+curv 0 1 1 \\
+  -1
+end
+";
+    let mut inputs = vec![
+        mixed.as_bytes().to_vec(),
+        b"v 0 0 0\n\xff\nf 1 1 1\n".to_vec(),
+    ];
+    for file in ["cornell_box", "quad_colored_merge", "lines"] {
+        inputs.push(std::fs::read(format!("obj/{}.obj", file)).unwrap());
+    }
+    let load_mtl = |p: &Path| crate::load_mtl(Path::new("obj").join(p));
+
+    for bytes in &inputs {
+        for options in [
+            crate::GPU_LOAD_OPTIONS,
+            crate::OFFLINE_RENDERING_LOAD_OPTIONS,
+            crate::LoadOptions {
+                lenient: true,
+                ..Default::default()
+            },
+        ] {
+            let expected =
+                crate::load_obj_bytes_with_diagnostics::<_, f64>(bytes, &options, load_mtl);
+            for chunk_size in [1, 16, 100, 1 << 20] {
+                let result = crate::parallel::load_chunked(bytes, &options, load_mtl, chunk_size);
+                match (&expected, result) {
+                    (Ok((models, mats, diagnostics)), Ok(result)) => {
+                        assert_same_models(models, &result.0);
+                        assert_eq!(
+                            mats.as_ref().map(|m| m.len()).ok(),
+                            result.1.map(|m| m.len()).ok()
+                        );
+                        assert_eq!(diagnostics, &result.2);
+                    }
+                    (Err(expected), Err(err)) => assert_eq!(expected, &err),
+                    (expected, result) => panic!("{:?} != {:?}", expected.is_ok(), result.is_ok()),
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "reordering")]
+    {
+        // More normals than positions, so reordering expands them per face vertex.
+        let obj = b"v 0 0 0\nv 1 0 0\nv 1 1 0\nvn 1 0 0\nvn 0 1 0\nvn 0 0 1\nvn 0 0 -1
+f 1//4 2//3 3//2\nf 1//1 2//1 3//1\n";
+        for chunk_size in [1, 16, 1 << 20] {
+            let (models, _, _) = crate::parallel::load_chunked::<_, f64>(
+                obj,
+                &crate::OFFLINE_RENDERING_LOAD_OPTIONS,
+                |_| unreachable!(),
+                chunk_size,
+            )
+            .unwrap();
+            assert_eq!(
+                models[0].mesh.normals,
+                [
+                    0.0, 0.0, -1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+                    1.0, 0.0, 0.0,
+                ]
+            );
+        }
+    }
+}
+
+#[test]
+fn per_face_materials() {
+    let obj = "mtllib two.mtl
+o Mixed
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+f 1 2 3
+usemtl red
+f 1 2 3 4
+f 1 3 4
+usemtl blue
+f 2 3 4
+usemtl red
+f 1 2 4
+o Plain
+usemtl blue
+f 1 2 3
+";
+    let mtl = "newmtl red\nKd 1 0 0\n\nnewmtl blue\nKd 0 0 1\n";
+    let load = |options: &crate::LoadOptions| {
+        crate::load_obj_buf::<_, _, f64>(&mut Cursor::new(obj), options, |_| {
+            crate::load_mtl_buf(&mut Cursor::new(mtl))
+        })
+        .unwrap()
+    };
+
+    // By default each material switch starts a new model.
+    let (models, _) = load(&crate::LoadOptions::default());
+    assert_eq!(models.len(), 5);
+
+    let options = crate::LoadOptions {
+        per_face_materials: true,
+        single_index: true,
+        triangulate: true,
+        ..Default::default()
+    };
+    let (models, mats) = load(&options);
+    let mats = mats.unwrap();
+    assert_eq!(models.len(), 2);
+
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.positions.len(), 4 * 3);
+    assert_eq!(
+        mesh.face_materials,
+        [None, Some(0), Some(0), Some(0), Some(1), Some(0)]
+    );
+    assert_eq!(mesh.material_id, None);
+    assert_eq!(
+        mesh.material_ranges(),
+        [
+            (None, 0..3),
+            (Some(0), 3..12),
+            (Some(1), 12..15),
+            (Some(0), 15..18)
+        ]
+    );
+
+    // A mesh using a single material also has it as its `material_id`.
+    assert_eq!(models[1].mesh.material_id, Some(1));
+    assert_eq!(models[1].mesh.material_ranges(), [(Some(1), 0..3)]);
+
+    let options = crate::LoadOptions {
+        triangulate: false,
+        ..options
+    };
+    let (models, _) = load(&options);
+    assert_eq!(
+        models[0].mesh.face_materials,
+        [None, Some(0), Some(0), Some(1), Some(0)]
+    );
+    assert_eq!(
+        models[0].mesh.material_ranges(),
+        [
+            (None, 0..3),
+            (Some(0), 3..10),
+            (Some(1), 10..13),
+            (Some(0), 13..16)
+        ]
+    );
+
+    let mut saved = Vec::new();
+    crate::save_obj_buf(&mut saved, &models, &mats, Some("two.mtl")).unwrap();
+    let (reloaded, _) = crate::load_obj_buf(&mut Cursor::new(&saved), &options, |_| {
+        crate::load_mtl_buf(&mut Cursor::new(mtl))
+    })
+    .unwrap();
+    assert_same_models(&models, &reloaded);
+
+    // Faces without a material following ones with a material.
+    let mut models = models;
+    models[0].mesh.face_materials[3] = None;
+    let mut saved = Vec::new();
+    crate::save_obj_buf(&mut saved, &models, &mats, Some("two.mtl")).unwrap();
+    let (reloaded, _) = crate::load_obj_buf(&mut Cursor::new(&saved), &options, |_| {
+        crate::load_mtl_buf(&mut Cursor::new(mtl))
+    })
+    .unwrap();
+    assert_eq!(
+        reloaded[0].mesh.face_materials,
+        [None, Some(0), Some(0), None, Some(0)]
+    );
+    assert_same_models(&models, &reloaded);
+
+    // Faces without vertices have per-face values, too.
+    let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\ns 1\nf\ns 2\nf 1 2 3\n";
+    for single_index in [false, true] {
+        let options = crate::LoadOptions {
+            single_index,
+            ..options
+        };
+        let (models, _) =
+            crate::load_obj_buf::<_, _, f64>(&mut Cursor::new(obj), &options, |_| unreachable!())
+                .unwrap();
+        let mesh = &models[0].mesh;
+        assert_eq!(mesh.face_arities, [0, 3]);
+        assert_eq!(mesh.face_materials, [None, None]);
+        assert_eq!(mesh.smoothing_groups, [1, 2]);
+    }
+    let (models, _, _) = crate::load_obj_buf_with_attributes::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &crate::LoadOptions {
+            per_face_materials: true,
+            ..Default::default()
+        },
+        |_| unreachable!(),
+    )
+    .unwrap();
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.face_arities, [0, 3]);
+    assert_eq!(mesh.face_materials, [None, None]);
+    assert_eq!(mesh.smoothing_groups, [1, 2]);
+}
+
+#[test]
+#[cfg(feature = "reordering")]
+fn reorder_normals() {
+    let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nvn 0 0 1\nvn 0 1 0\nf 1//2 2//1 3//1\n";
+    let (models, _) = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &crate::LoadOptions {
+            reorder_data: true,
+            ..Default::default()
+        },
+        |_| unreachable!(),
+    )
+    .unwrap();
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.normals, [0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+    assert!(mesh.normal_indices.is_empty());
+}
+
+#[test]
+#[cfg(feature = "reordering")]
+fn reorder_without_texcoords() {
+    let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nvn 0 0 1\nvn 0 1 0\nvn 1 0 0\nf 1//1 2//2 3//3\n";
+    let (models, _) = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &crate::LoadOptions {
+            reorder_data: true,
+            ..Default::default()
+        },
+        |_| unreachable!(),
+    )
+    .unwrap();
+    let mesh = &models[0].mesh;
+    assert!(mesh.texcoords.is_empty());
+    assert!(mesh.texcoord_indices.is_empty());
+}
+
+#[test]
+fn shared_attributes() {
+    let obj = "v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 1
+o First
+f 1/1 2/2 3/1 4/2
+o Second
+v 5 5 5
+f 3 4 5
+f -1 1 2
+";
+    let load = |options: &crate::LoadOptions| {
+        crate::load_obj_buf_with_attributes::<_, _, f32>(
+            &mut Cursor::new(obj),
+            options,
+            |_| unreachable!(),
+        )
+    };
+
+    let (models, _, attributes) = load(&crate::LoadOptions::default()).unwrap();
+    assert_eq!(attributes.positions.len(), 5 * 3);
+    assert_eq!(&attributes.positions[12..], [5.0, 5.0, 5.0]);
+    assert_eq!(attributes.texcoords, [0.0, 0.0, 1.0, 1.0]);
+    assert!(attributes.normals.is_empty());
+    assert_eq!(models.len(), 2);
+
+    // Indices refer to the vertices in file order, shared between the models.
+    let first = &models[0].mesh;
+    assert!(first.positions.is_empty() && first.texcoords.is_empty());
+    assert_eq!(first.indices, [0, 1, 2, 3]);
+    assert_eq!(first.texcoord_indices, [0, 1, 0, 1]);
+    assert_eq!(first.face_arities, [4]);
+
+    let second = &models[1].mesh;
+    assert_eq!(second.indices, [2, 3, 4, 4, 0, 1]);
+    // Faces without texture coordinates continue to use the previous ones.
+    assert_eq!(second.texcoord_indices, [0, 0, 0, 0, 0, 0]);
+    assert!(second.face_arities.is_empty());
+
+    let options = crate::LoadOptions {
+        triangulate: true,
+        ..Default::default()
+    };
+    let (models, _, _) = load(&options).unwrap();
+    assert_eq!(models[0].mesh.indices, [0, 1, 2, 0, 2, 3]);
+    assert_eq!(models[0].mesh.texcoord_indices, [0, 1, 0, 0, 0, 1]);
+
+    // Faces without vertices cannot be triangulated by either method.
+    for triangulation_method in [
+        crate::TriangulationMethod::Fan,
+        crate::TriangulationMethod::EarClipping,
+    ] {
+        let err = crate::load_obj_buf_with_attributes::<_, _, f32>(
+            &mut Cursor::new("v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3\nf\n"),
+            &crate::LoadOptions {
+                triangulation_method,
+                ..options
+            },
+            |_| unreachable!(),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), crate::LoadErrorKind::InvalidPolygon);
+    }
+
+    // Options modifying the vertex data are not supported.
+    let options = crate::LoadOptions {
+        single_index: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        load(&options).unwrap_err(),
+        crate::LoadErrorKind::InvalidLoadOptionConfig
+    );
+}
+
+#[test]
+fn keep_unreferenced_vertices() {
+    let obj = "o Cloud
+v 0 0 0 1 0 0
+v 1 0 0 0 1 0
+v 2 0 0 0 0 1
+o Mesh
+v 0 1 0 1 1 1
+v 1 1 0 1 1 1
+v 0 2 0 1 1 1
+v 9 9 9 0 0 0
+f 4 5 6
+";
+    let load = |keep_unreferenced_vertices| {
+        let options = crate::LoadOptions {
+            keep_unreferenced_vertices,
+            ..Default::default()
+        };
+        crate::load_obj_buf::<_, _, f32>(&mut Cursor::new(obj), &options, |_| unreachable!())
+            .unwrap()
+            .0
+    };
+
+    // By default vertices without faces are dropped, as is the point cloud.
+    let models = load(false);
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0].mesh.positions.len(), 3 * 3);
+
+    let models = load(true);
+    assert_eq!(models.len(), 2);
+    let cloud = &models[0].mesh;
+    assert_eq!(models[0].name, "Cloud");
+    assert_eq!(cloud.positions, [0., 0., 0., 1., 0., 0., 2., 0., 0.]);
+    assert_eq!(
+        cloud.vertex_color,
+        [1., 0., 0., 1., 0., 1., 0., 1., 0., 0., 1., 1.]
+    );
+    assert!(cloud.indices.is_empty());
+
+    // Unused vertices are appended after the ones used by faces.
+    let mesh = &models[1].mesh;
+    assert_eq!(mesh.indices, [0, 1, 2]);
+    assert_eq!(mesh.positions.len(), 4 * 3);
+    assert_eq!(&mesh.positions[9..], [9., 9., 9.]);
+    assert_eq!(&mesh.vertex_color[12..], [0., 0., 0., 1.]);
+
+    // Normals and texture coordinates sharing the indices of the positions,
+    // generated or not, stay aligned with them.
+    let obj = "v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 1
+v 9 9 9
+vt 0 0
+vn 0 0 1
+f 1/1/1 2/1/1 3/1/1
+f 1/1/1 3/1/1 4/1/1
+";
+    let options = crate::LoadOptions {
+        keep_unreferenced_vertices: true,
+        single_index: true,
+        generate_normals: Some(Default::default()),
+        ..Default::default()
+    };
+    let (models, _) =
+        crate::load_obj_buf::<_, _, f32>(&mut Cursor::new(obj), &options, |_| unreachable!())
+            .unwrap();
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.positions.len(), 5 * 3);
+    assert_eq!(&mesh.positions[12..], [9., 9., 9.]);
+    assert_eq!(mesh.normals.len(), mesh.positions.len());
+    assert_eq!(&mesh.normals[12..], [0., 0., 0.]);
+    assert_eq!(mesh.texcoords.len(), 5 * 2);
+
+    let (models, _) = crate::load_obj_buf::<_, _, f32>(
+        &mut Cursor::new("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 1\nv 9 9 9\nf 1 2 3\nf 1 3 4\n"),
+        &options,
+        |_| unreachable!(),
+    )
+    .unwrap();
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.positions.len(), 5 * 3);
+    assert_eq!(mesh.normals.len(), mesh.positions.len());
+}
+
+#[test]
+fn separate_lines_and_points() {
+    let obj = "v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 5 5 5
+f 1 2 3
+l 1 2 3 4
+l 4 1
+p 5 3
+";
+    let load = |options: &crate::LoadOptions| {
+        crate::load_obj_buf::<_, _, f64>(&mut Cursor::new(obj), options, |_| unreachable!())
+            .unwrap()
+            .0
+    };
+
+    // By default lines are faces and points are skipped.
+    let models = load(&crate::LoadOptions::default());
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.face_arities, [3, 4, 2]);
+    assert!(mesh.line_indices.is_empty() && mesh.point_indices.is_empty());
+
+    let options = crate::LoadOptions {
+        separate_lines_and_points: true,
+        triangulate: true,
+        single_index: true,
+        ..Default::default()
+    };
+    let models = load(&options);
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.indices, [0, 1, 2]);
+    assert!(mesh.face_arities.is_empty());
+    assert_eq!(mesh.line_indices, [0, 1, 2, 3, 3, 0]);
+    assert_eq!(mesh.line_arities, [4, 2]);
+    assert_eq!(mesh.point_indices, [4, 2]);
+    assert_eq!(mesh.positions.len(), 5 * 3);
+
+    // Lines and points survive saving.
+    let mut saved = Vec::new();
+    crate::save_obj_buf(&mut saved, &models, &[], None).unwrap();
+    let reloaded = crate::load_obj_buf(&mut Cursor::new(&saved), &options, |_| unreachable!())
+        .unwrap()
+        .0;
+    assert_same_models(&models, &reloaded);
+
+    let options = crate::LoadOptions {
+        separate_lines_and_points: true,
+        ignore_lines: true,
+        ignore_points: true,
+        ..Default::default()
+    };
+    let models = load(&options);
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.indices, [0, 1, 2]);
+    assert!(mesh.line_indices.is_empty() && mesh.point_indices.is_empty());
+}
+
+#[test]
+fn free_form_geometry() {
+    let obj = "v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+vt 0 0
+vp 0 0
+vp 1 0
+vp 1 1 0.5
+vp 0 1
+o Patch
+cstype rat bspline
+deg 1 1
+surf 0 1 0 1 1/1 2/1 \\
+  3/1 4/1
+parm u 0 0 1 1
+parm v 0 0 1 1
+trim 0 1 1 0 1 2
+hole 0.5 1 2
+end
+curv2 1 2 3
+parm u 0 0 0 1 1 1
+end
+curv2 3 4 1
+end
+o Curve
+cstype bezier
+deg 3
+curv 0 1 -1 2 3 1
+parm u 0 1
+end
+f 1 2 3
+";
+    let (models, _) = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+    assert_eq!(models.len(), 2);
+
+    let patch = &models[0].free_form;
+    assert!(models[0].mesh.indices.is_empty());
+    assert_eq!(
+        patch.positions,
+        [0., 0., 0., 1., 0., 0., 0., 1., 0., 1., 1., 0.]
+    );
+    assert_eq!(patch.texcoords, [0., 0.]);
+    let surface = &patch.surfaces[0];
+    assert_eq!(surface.basis, crate::Basis::BSpline);
+    assert!(surface.rational);
+    assert_eq!(surface.degree, [1, 1]);
+    assert_eq!(surface.range_u, [0., 1.]);
+    assert_eq!(surface.control_points, [0, 1, 2, 3]);
+    assert_eq!(surface.texcoord_indices, [0, 0, 0, 0]);
+    assert!(surface.normal_indices.is_empty());
+    assert_eq!(surface.knots_u, [0., 0., 1., 1.]);
+    assert_eq!(surface.knots_v, [0., 0., 1., 1.]);
+
+    // Trimming curves may be defined after the surface that uses them.
+    assert_eq!(patch.curves_2d.len(), 2);
+    assert_eq!(
+        surface.trims,
+        [vec![
+            crate::TrimCurve {
+                range: [0., 1.],
+                curve: 0,
+            },
+            crate::TrimCurve {
+                range: [0., 1.],
+                curve: 1,
+            },
+        ]]
+    );
+    assert_eq!(surface.holes[0][0].curve, 1);
+    assert_eq!(patch.curves_2d[0].control_points, [0, 1, 2]);
+    assert_eq!(patch.curves_2d[0].knots, [0., 0., 0., 1., 1., 1.]);
+    assert_eq!(patch.curves_2d[1].control_points, [2, 3, 0]);
+    assert_eq!(&patch.parameter_vertices[6..9], [1., 1., 0.5]);
+    assert_eq!(&patch.parameter_vertices[9..], [0., 1., 1.]);
+
+    // Free-form geometry and faces can be mixed.
+    let curve = &models[1].free_form.curves[0];
+    assert_eq!(curve.basis, crate::Basis::Bezier);
+    assert!(!curve.rational);
+    assert_eq!(curve.degree, 3);
+    assert_eq!(curve.control_points, [0, 1, 2, 3]);
+    assert_eq!(&models[1].free_form.positions[..3], [1., 1., 0.]);
+    assert_eq!(models[1].mesh.indices, [0, 1, 2]);
+
+    // Curves and surfaces require `cstype` and `deg`.
+    let error = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new("v 0 0 0\nv 1 0 0\ncurv 0 1 1 2\n"),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap_err();
+    assert_eq!(error.kind(), crate::LoadErrorKind::FreeFormParseError);
+    assert_eq!(error.line(), Some(3));
+}
+
+#[test]
+fn line_continuation() {
+    // Not just free-form statements continue after a backslash.
+    let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\no a\\\nb\nf 1 \\\n  2 3\n# comment \\\nf 3 2 1\n";
+    let (models, _) = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+    assert_eq!(models.len(), 1);
+    assert_eq!(models[0].name, "a b");
+    assert_eq!(models[0].mesh.indices, [0, 1, 2, 2, 1, 0]);
+
+    // Errors are reported at the line the statement starts on, both when
+    // parsing it and when exporting its faces.
+    for obj in [
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 \\\n  2 \\\n  x\n",
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 \\\n  2 \\\n  9\n",
+    ] {
+        let err = crate::load_obj_buf::<_, _, f64>(
+            &mut Cursor::new(obj),
+            &Default::default(),
+            |_| unreachable!(),
+        )
+        .unwrap_err();
+        assert_eq!(err.line(), Some(4));
+    }
+}
+
+#[test]
+fn tessellate_free_form() {
+    let obj = "v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+v 0 0 1
+v 1 0 1
+v 2 2 1
+vp 0.25 0.25
+vp 0.75 0.25
+vp 0.75 0.75
+vp 0.25 0.75
+vp 0.25 0.25
+o Patch
+cstype bezier
+deg 1 1
+surf 0 1 0 1 1 2 3 4
+hole 0 4 1
+end
+cstype bezier
+deg 1
+curv2 1 2 3 4 5
+parm u 0 1 2 3 4
+end
+o Curve
+cstype bspline
+deg 2
+ctech cparm 2
+curv 0 1 5 6 7
+parm u 0 0 0 1 1 1
+end
+";
+    let tessellate = |load_options: &crate::LoadOptions| {
+        crate::load_obj_buf::<_, _, f64>(&mut Cursor::new(obj), load_options, |_| unreachable!())
+            .unwrap()
+            .0
+    };
+
+    let models = tessellate(&crate::LoadOptions {
+        single_index: true,
+        tessellate: Some(crate::TessellationOptions {
+            resolution: 4,
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    assert_eq!(models.len(), 2);
+
+    // The 4×4 cells of the patch without the 2×2 ones of the hole.
+    let patch = &models[0].mesh;
+    assert_eq!(models[0].name, "Patch");
+    assert_eq!(patch.indices.len(), 3 * 2 * (16 - 4));
+    assert!(patch.face_arities.is_empty());
+    // The vertex in the middle of the hole is left out.
+    assert_eq!(patch.positions.len(), 3 * 24);
+    assert_eq!(patch.texcoords.len(), 2 * 24);
+    assert_eq!(&patch.positions[3..6], [0.25, 0., 0.]);
+    assert_eq!(&patch.texcoords[2..4], [0.25, 0.]);
+    assert_eq!(models[0].free_form.surfaces.len(), 1);
+
+    // `ctech cparm 2` asks for 2 × degree steps.
+    let curve = &models[1].mesh;
+    assert!(curve.indices.is_empty());
+    assert_eq!(curve.line_arities, [5]);
+    assert_eq!(curve.line_indices, [0, 1, 2, 3, 4]);
+    assert_eq!(&curve.positions[..3], [0., 0., 1.]);
+    assert_eq!(&curve.positions[6..9], [1., 0.5, 1.]);
+    assert_eq!(&curve.positions[12..], [2., 2., 1.]);
+
+    // Without a single index the tessellated meshes get separate indices like
+    // any other.
+    let models = tessellate(&crate::LoadOptions {
+        tessellate: Some(crate::TessellationOptions {
+            resolution: 2,
+            ignore_file_techniques: true,
+        }),
+        ..Default::default()
+    });
+    let patch = &models[0].mesh;
+    // Two of the 8 triangles have their centroid in the hole.
+    assert_eq!(patch.indices.len(), 3 * 6);
+    assert_eq!(patch.texcoord_indices, patch.indices);
+    assert_eq!(models[1].mesh.line_arities, [3]);
+
+    // Free-form geometry is left as is by default.
+    let models = tessellate(&Default::default());
+    assert!(models.iter().all(|model| model.mesh.positions.is_empty()));
+}
+
+#[test]
+fn texcoord_dimensions() {
+    let obj = "v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0.5
+vt 0.25
+o Ramp
+f 1/1 2/2 3/1
+o Volume
+vt 0 0.5 1
+vt 1 0.5
+f 1/3 2/4 3/3
+";
+    for options in [
+        crate::LoadOptions::default(),
+        crate::LoadOptions {
+            single_index: true,
+            ..Default::default()
+        },
+    ] {
+        let (models, _) =
+            crate::load_obj_buf::<_, _, f64>(&mut Cursor::new(obj), &options, |_| unreachable!())
+                .unwrap();
+
+        // Texture coordinates are only padded once a `vt` with more
+        // components comes along.
+        let ramp = &models[0].mesh;
+        assert_eq!(ramp.texcoord_dimension, 1);
+        let volume = &models[1].mesh;
+        assert_eq!(volume.texcoord_dimension, 3);
+        if options.single_index {
+            assert_eq!(ramp.texcoords, [0.5, 0.25, 0.5]);
+            assert_eq!(volume.texcoords, [0., 0.5, 1., 1., 0.5, 0., 0., 0.5, 1.]);
+        } else {
+            assert_eq!(ramp.texcoords, [0.5, 0.25]);
+            assert_eq!(volume.texcoords, [0., 0.5, 1., 1., 0.5, 0.]);
+        }
+
+        let mut saved = Vec::new();
+        crate::save_obj_buf(&mut saved, &models[1..], &[], None).unwrap();
+        let (reloaded, _) =
+            crate::load_obj_buf(&mut Cursor::new(&saved), &options, |_| unreachable!()).unwrap();
+        assert_same_models(&models[1..], &reloaded);
+    }
+
+    let (_, _, attributes) = crate::load_obj_buf_with_attributes::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+    assert_eq!(attributes.texcoord_dimension, 3);
+    assert_eq!(attributes.texcoords[..6], [0.5, 0., 0., 0.25, 0., 0.]);
+
+    // A texture coordinate needs at least one component.
+    let error = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new("vt\n"),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap_err();
+    assert_eq!(error.kind(), crate::LoadErrorKind::TexcoordParseError);
+}
+
+#[test]
+fn vertex_weights_and_colors() {
+    let obj = "v 0 0 0
+v 1 0 0 0.5
+v 0 1 0 1 0 0
+v 1 1 0 0 1 0 0.5
+f 1 2 3 4
+o Weighted
+v 0 0 2 0.25
+v 1 0 2
+v 0 1 2 2
+f 5 6 7
+";
+    let (models, _) = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+
+    // Vertices without a color or weight get a default one as soon as any
+    // vertex of the file has one.
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.weights, [1., 0.5, 1., 1.]);
+    assert_eq!(
+        mesh.vertex_color,
+        [1., 1., 1., 1., 1., 1., 1., 1., 1., 0., 0., 1., 0., 1., 0., 0.5]
+    );
+    assert_eq!(models[1].mesh.weights, [0.25, 1., 2.]);
+    assert_eq!(models[1].mesh.vertex_color.len(), 12);
+
+    let mut saved = Vec::new();
+    let weighted = [crate::Model::new(
+        crate::Mesh {
+            vertex_color: Vec::new(),
+            ..models[1].mesh.clone()
+        },
+        "Weighted".to_string(),
+    )];
+    crate::save_obj_buf(&mut saved, &weighted, &[], None).unwrap();
+    assert!(String::from_utf8_lossy(&saved).contains("v 0 0 2 0.25\n"));
+    let (reloaded, _) = crate::load_obj_buf(
+        &mut Cursor::new(&saved),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+    assert_same_models(&weighted, &reloaded);
+
+    // Two extra values are neither a weight nor a color, and neither are five,
+    // so they are an error that is ignored with a warning when loading
+    // leniently.
+    for obj in [
+        "v 0 0 0 1 1\nv 1 0 0\nv 0 1 0\nf 1 2 3\n",
+        "v 0 0 0 1 0 0 0.5 7\nv 1 0 0\nv 0 1 0\nf 1 2 3\n",
+    ] {
+        let err = crate::load_obj_buf::<_, _, f64>(
+            &mut Cursor::new(obj),
+            &Default::default(),
+            |_| unreachable!(),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), crate::LoadErrorKind::VertexColorParseError);
+        assert_eq!(err.line(), Some(1));
+
+        let (models, _, diagnostics) = crate::load_obj_buf_with_diagnostics::<_, _, f64>(
+            &mut Cursor::new(obj),
+            &crate::LoadOptions {
+                lenient: true,
+                ..Default::default()
+            },
+            |_| unreachable!(),
+        )
+        .unwrap();
+        assert_eq!(models[0].mesh.positions.len(), 9);
+        assert!(models[0].mesh.vertex_color.is_empty());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, crate::Severity::Warning);
+        assert_eq!(
+            diagnostics[0].error.kind(),
+            crate::LoadErrorKind::VertexColorParseError
+        );
+        assert_eq!(diagnostics[0].error.line(), Some(1));
+    }
+
+    // Extras end at a trailing comment.
+    let (models, _) = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new("v 0 0 0 # origin\nv 1 0 0 0.5 # weighted\nv 0 1 0\nf 1 2 3\n"),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+    assert_eq!(
+        models[0].mesh.positions,
+        [0., 0., 0., 1., 0., 0., 0., 1., 0.]
+    );
+    assert_eq!(models[0].mesh.weights, [1., 0.5, 1.]);
+    assert!(models[0].mesh.vertex_color.is_empty());
+
+    // Rational curves use the weights of their control points, here for an
+    // exact quarter circle.
+    let obj = "v 1 0 0
+v 1 1 0 0.7071067811865476
+v 0 1 0
+cstype rat bspline
+deg 2
+curv 0 1 1 2 3
+parm u 0 0 0 1 1 1
+end
+";
+    let (models, _) = crate::load_obj_buf::<_, _, f64>(
+        &mut Cursor::new(obj),
+        &crate::LoadOptions {
+            tessellate: Some(Default::default()),
+            ..Default::default()
+        },
+        |_| unreachable!(),
+    )
+    .unwrap();
+    let positions = &models[0].mesh.positions;
+    assert_eq!(positions.len(), 3 * 9);
+    for p in positions.chunks_exact(3) {
+        assert!((p[0].hypot(p[1]) - 1.).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn integer_vertex_colors() {
+    // Colors are parsed as floats whatever the precision of the mesh.
+    let obj = "v 1 2 3 0.5 0.5 0.5
+v 4 5 6 1 0.5 0 1
+v 7 8 9 2
+#MRGB 80ff00ff
+f 1 2 3
+";
+    let (models, _) = crate::load_obj_buf::<_, _, i32>(
+        &mut Cursor::new(obj),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+    let mesh = &models[0].mesh;
+    assert_eq!(mesh.positions, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    assert_eq!(mesh.weights, [1, 1, 2]);
+
+    // `#MRGB` bytes are kept unscaled in integer precision.
+    #[rustfmt::skip]
+    assert_eq!(
+        mesh.vertex_color,
+        [
+            255, 0, 255, 255,
+            1, 0, 0, 1,
+            1, 1, 1, 1,
+        ]
+    );
+    assert_eq!(mesh.vertex_mask, [128, 0, 0]);
+}
+
+#[test]
+fn zbrush_mrgb_colors() {
+    let obj = "v 0 0 0
+v 1 0 0
+v 0 1 0 0.5 0.5 0.5 0.25
+#MRGB ff0000ff00ff0000
+#MRGB 80ffffff
+v 1 1 0
+f 1 2 3 4
+";
+    let (models, _) = crate::load_obj_buf::<_, _, f32>(
+        &mut Cursor::new(obj),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+
+    // Each entry replaces the color of the next vertex of the file and sets
+    // its mask. Vertices after the blocks keep their own color.
+    let mesh = &models[0].mesh;
+    #[rustfmt::skip]
+    assert_eq!(
+        mesh.vertex_color,
+        [
+            0., 0., 1., 1.,
+            1., 0., 0., 1.,
+            1., 1., 1., 1.,
+            1., 1., 1., 1.,
+        ]
+    );
+    assert_eq!(mesh.vertex_mask, [1., 0., 128. / 255., 0.]);
+
+    let (_, _, attributes) = crate::load_obj_buf_with_attributes::<_, _, f32>(
+        &mut Cursor::new(obj),
+        &Default::default(),
+        |_| unreachable!(),
+    )
+    .unwrap();
+    assert_eq!(attributes.vertex_color, mesh.vertex_color);
+    assert_eq!(attributes.vertex_mask, mesh.vertex_mask);
+
+    // Entries must be eight hex digits and refer to a vertex read before.
+    for obj in [
+        "v 0 0 0\n#MRGB ff0000\n",
+        "v 0 0 0\n#MRGB ff0000ffff0000ff\n",
+    ] {
+        let error = crate::load_obj_buf::<_, _, f32>(
+            &mut Cursor::new(obj),
+            &Default::default(),
+            |_| unreachable!(),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), crate::LoadErrorKind::VertexColorParseError);
+    }
 }
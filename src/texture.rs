@@ -0,0 +1,444 @@
+//! Texture references in `MTL` files and the options that can precede them.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{loader::find_case_insensitive, LoadError, LoadErrorKind, Material};
+
+/// A texture referenced by a [`Material`](crate::Material), e.g. via `map_Kd`.
+///
+/// `MTL` files may specify options before the file name of a texture, as in
+/// `map_Bump -bm 0.5 normal.png`. These are parsed into [`options`](Texture::options)
+/// and stripped from the [`file_name`](Texture::file_name).
+///
+/// The [`Display`](fmt::Display) implementation writes the texture back the
+/// way it is specified in an `MTL` file: options first, then the file name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Texture {
+    /// File name of the texture as specified in the `MTL` file. May contain
+    /// spaces. Empty if the material does not use the texture.
+    pub file_name: String,
+    /// Options applying to the texture.
+    pub options: TextureOptions,
+}
+
+/// Options of a [`Texture`]. Options not specified in the `MTL` file are
+/// `None`.
+///
+/// See the [`MTL` spec](http://paulbourke.net/dataformats/mtl/) for their
+/// meaning.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextureOptions {
+    /// Horizontal texture blending, `-blendu on|off`.
+    pub blend_u: Option<bool>,
+    /// Vertical texture blending, `-blendv on|off`.
+    pub blend_v: Option<bool>,
+    /// Mip-map sharpness boost, `-boost value`.
+    pub boost: Option<f32>,
+    /// Base value and gain applied to the texture values, `-mm base gain`.
+    pub value_modifier: Option<[f32; 2]>,
+    /// Offset of the texture origin, `-o u [v [w]]`. Components left out
+    /// default to `0`.
+    pub offset: Option<[f32; 3]>,
+    /// Scale of the texture, `-s u [v [w]]`. Components left out default to
+    /// `1`.
+    pub scale: Option<[f32; 3]>,
+    /// Turbulence applied to the texture, `-t u [v [w]]`. Components left out
+    /// default to `0`.
+    pub turbulence: Option<[f32; 3]>,
+    /// Resolution of the texture to create, `-texres value`.
+    pub resolution: Option<u32>,
+    /// Clamp texture coordinates to `0..=1`, `-clamp on|off`.
+    pub clamp: Option<bool>,
+    /// Color correction, `-cc on|off`.
+    pub color_correction: Option<bool>,
+    /// Multiplier for the values of a bump map, `-bm value`.
+    pub bump_multiplier: Option<f32>,
+    /// Channel of the texture file to use for scalar textures,
+    /// `-imfchan r|g|b|m|l|z`.
+    pub channel: Option<TextureChannel>,
+    /// Type of a reflection map, `-type sphere|cube_top|...`.
+    pub map_type: Option<String>,
+}
+
+/// The channel of a texture file used by a scalar texture.
+///
+/// Used by [`TextureOptions::channel`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureChannel {
+    Red,
+    Green,
+    Blue,
+    Matte,
+    Luminance,
+    Depth,
+}
+
+/// The six faces of a cube reflection map, each given by a
+/// `refl -type cube_*` statement.
+///
+/// Used by [`Material::reflection_cube`](crate::Material::reflection_cube).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CubeMap {
+    /// `refl -type cube_top`.
+    pub top: Texture,
+    /// `refl -type cube_bottom`.
+    pub bottom: Texture,
+    /// `refl -type cube_front`.
+    pub front: Texture,
+    /// `refl -type cube_back`.
+    pub back: Texture,
+    /// `refl -type cube_left`.
+    pub left: Texture,
+    /// `refl -type cube_right`.
+    pub right: Texture,
+}
+
+impl CubeMap {
+    /// The face for the `-type` of a `refl` statement, `None` if it is not
+    /// one of the `cube_*` types.
+    pub fn face_mut(&mut self, map_type: &str) -> Option<&mut Texture> {
+        Some(match map_type {
+            "cube_top" => &mut self.top,
+            "cube_bottom" => &mut self.bottom,
+            "cube_front" => &mut self.front,
+            "cube_back" => &mut self.back,
+            "cube_left" => &mut self.left,
+            "cube_right" => &mut self.right,
+            _ => return None,
+        })
+    }
+
+    /// The faces in the order top, bottom, front, back, left, right.
+    pub fn faces(&self) -> [&Texture; 6] {
+        [
+            &self.top,
+            &self.bottom,
+            &self.front,
+            &self.back,
+            &self.left,
+            &self.right,
+        ]
+    }
+}
+
+impl Texture {
+    /// Whether no texture is set, i.e. the [`file_name`](Texture::file_name)
+    /// is empty.
+    pub fn is_empty(&self) -> bool {
+        self.file_name.is_empty()
+    }
+
+    /// The [`file_name`](Texture::file_name) as a path, with Windows
+    /// backslashes turned into forward slashes.
+    pub fn normalized_path(&self) -> PathBuf {
+        PathBuf::from(self.file_name.replace('\\', "/"))
+    }
+}
+
+/// Finds the files of the textures referenced by [`Material`]s.
+///
+/// A texture is looked for, in order:
+///
+/// 1. At its path, if it is absolute.
+/// 2. Relative to the directory of the [`mtl_file`](Material::mtl_file) of
+///    the material.
+/// 3. Relative to each of the [`search_directories`](Self::search_directories).
+///
+/// Paths are [normalized](Texture::normalized_path) first and names are
+/// matched case-insensitively if there is no exact match. If a texture cannot
+/// be found at its path relative to a directory, its file name alone is tried
+/// there, which finds textures referenced by absolute paths from another
+/// machine.
+#[derive(Debug, Clone, Default)]
+pub struct TextureResolver {
+    /// Directories to look for textures in if they are not next to the `MTL`
+    /// file, in order.
+    pub search_directories: Vec<PathBuf>,
+}
+
+/// A texture whose file a [`TextureResolver`] cannot find.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingTexture {
+    /// The index of the material using the texture.
+    pub material: usize,
+    /// The file name of the texture, as specified in the `MTL` file.
+    pub file_name: String,
+}
+
+impl TextureResolver {
+    /// Create a resolver falling back to `search_directories` in order.
+    pub fn new<I>(search_directories: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<PathBuf>,
+    {
+        Self {
+            search_directories: search_directories.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Find the file of a `texture` used by `material`. Returns `None` if it
+    /// does not exist anywhere.
+    pub fn resolve(&self, material: &Material, texture: &Texture) -> Option<PathBuf> {
+        if texture.is_empty() {
+            return None;
+        }
+        let path = texture.normalized_path();
+        if path.is_absolute() && path.is_file() {
+            return Some(path);
+        }
+
+        let mtl_directory = material
+            .mtl_file
+            .as_deref()
+            .map(|mtl_file| mtl_file.parent().unwrap_or_else(|| Path::new("")));
+        let file_name = path.file_name().map(Path::new);
+        mtl_directory
+            .into_iter()
+            .chain(self.search_directories.iter().map(PathBuf::as_path))
+            .find_map(|directory| {
+                // A path with a root or drive letter is only useful for its
+                // file name.
+                let relative = if path.has_root() || is_drive_path(&path) {
+                    None
+                } else {
+                    find_case_insensitive(directory, &path)
+                };
+                relative.or_else(|| find_case_insensitive(directory, file_name?))
+            })
+    }
+
+    /// The textures of `materials` that cannot be found.
+    pub fn missing(&self, materials: &[Material]) -> Vec<MissingTexture> {
+        let mut missing = Vec::new();
+        for (index, material) in materials.iter().enumerate() {
+            for texture in material.textures() {
+                if self.resolve(material, texture).is_none() {
+                    missing.push(MissingTexture {
+                        material: index,
+                        file_name: texture.file_name.clone(),
+                    });
+                }
+            }
+        }
+        missing
+    }
+}
+
+/// Whether a path starts with a Windows drive letter such as `C:`, which is
+/// not a prefix on other platforms.
+fn is_drive_path(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+impl From<&str> for Texture {
+    fn from(file_name: &str) -> Self {
+        file_name.to_owned().into()
+    }
+}
+
+impl From<String> for Texture {
+    fn from(file_name: String) -> Self {
+        Self {
+            file_name,
+            options: TextureOptions::default(),
+        }
+    }
+}
+
+/// Compares the [`file_name`](Texture::file_name) only.
+impl PartialEq<str> for Texture {
+    fn eq(&self, file_name: &str) -> bool {
+        self.file_name == file_name
+    }
+}
+
+/// Compares the [`file_name`](Texture::file_name) only.
+impl PartialEq<&str> for Texture {
+    fn eq(&self, file_name: &&str) -> bool {
+        self.file_name == *file_name
+    }
+}
+
+/// Compares the [`file_name`](Texture::file_name) only.
+impl PartialEq<String> for Texture {
+    fn eq(&self, file_name: &String) -> bool {
+        self.file_name == *file_name
+    }
+}
+
+impl FromStr for Texture {
+    type Err = LoadError;
+
+    /// Parse the arguments of a texture statement, e.g.
+    /// `-bm 0.5 normal.png` for `map_Bump -bm 0.5 normal.png`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut options = TextureOptions::default();
+        let mut rest = s.trim();
+
+        loop {
+            let (option, args) = split_word(rest);
+            let parsed = match option {
+                "-blendu" => parse_on_off(args, &mut options.blend_u),
+                "-blendv" => parse_on_off(args, &mut options.blend_v),
+                "-clamp" => parse_on_off(args, &mut options.clamp),
+                "-cc" => parse_on_off(args, &mut options.color_correction),
+                "-boost" => parse_value(args, &mut options.boost),
+                "-bm" => parse_value(args, &mut options.bump_multiplier),
+                "-texres" => parse_value(args, &mut options.resolution),
+                "-mm" => {
+                    let (base, args) = split_word(args);
+                    let (gain, args) = split_word(args);
+                    match (base.parse(), gain.parse()) {
+                        (Ok(base), Ok(gain)) => {
+                            options.value_modifier = Some([base, gain]);
+                            Some(args)
+                        }
+                        _ => None,
+                    }
+                }
+                "-o" => parse_vector(args, [0.0; 3], &mut options.offset),
+                "-s" => parse_vector(args, [1.0; 3], &mut options.scale),
+                "-t" => parse_vector(args, [0.0; 3], &mut options.turbulence),
+                "-imfchan" => {
+                    let (channel, args) = split_word(args);
+                    options.channel = Some(match channel {
+                        "r" => TextureChannel::Red,
+                        "g" => TextureChannel::Green,
+                        "b" => TextureChannel::Blue,
+                        "m" => TextureChannel::Matte,
+                        "l" => TextureChannel::Luminance,
+                        "z" => TextureChannel::Depth,
+                        _ => return Err(LoadErrorKind::MaterialParseError.into()),
+                    });
+                    Some(args)
+                }
+                "-type" => {
+                    let (map_type, args) = split_word(args);
+                    options.map_type = Some(map_type.to_owned());
+                    Some(args)
+                }
+                // Anything else is the start of the file name, which may
+                // contain spaces.
+                _ => break,
+            };
+            rest = parsed.ok_or(LoadErrorKind::MaterialParseError)?;
+        }
+
+        if rest.is_empty() {
+            return Err(LoadErrorKind::MaterialParseError.into());
+        }
+
+        Ok(Self {
+            file_name: rest.to_owned(),
+            options,
+        })
+    }
+}
+
+impl fmt::Display for Texture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let o = &self.options;
+        let on_off = |value: bool| if value { "on" } else { "off" };
+
+        if let Some(value) = o.blend_u {
+            write!(f, "-blendu {} ", on_off(value))?;
+        }
+        if let Some(value) = o.blend_v {
+            write!(f, "-blendv {} ", on_off(value))?;
+        }
+        if let Some(value) = o.boost {
+            write!(f, "-boost {} ", value)?;
+        }
+        if let Some([base, gain]) = o.value_modifier {
+            write!(f, "-mm {} {} ", base, gain)?;
+        }
+        for (option, value) in [("-o", o.offset), ("-s", o.scale), ("-t", o.turbulence)] {
+            if let Some([u, v, w]) = value {
+                write!(f, "{} {} {} {} ", option, u, v, w)?;
+            }
+        }
+        if let Some(value) = o.resolution {
+            write!(f, "-texres {} ", value)?;
+        }
+        if let Some(value) = o.clamp {
+            write!(f, "-clamp {} ", on_off(value))?;
+        }
+        if let Some(value) = o.color_correction {
+            write!(f, "-cc {} ", on_off(value))?;
+        }
+        if let Some(value) = o.bump_multiplier {
+            write!(f, "-bm {} ", value)?;
+        }
+        if let Some(channel) = o.channel {
+            let channel = match channel {
+                TextureChannel::Red => "r",
+                TextureChannel::Green => "g",
+                TextureChannel::Blue => "b",
+                TextureChannel::Matte => "m",
+                TextureChannel::Luminance => "l",
+                TextureChannel::Depth => "z",
+            };
+            write!(f, "-imfchan {} ", channel)?;
+        }
+        if let Some(map_type) = &o.map_type {
+            write!(f, "-type {} ", map_type)?;
+        }
+
+        f.write_str(&self.file_name)
+    }
+}
+
+/// Split off the first word of `s`, returning it and the rest of `s` with
+/// leading whitespace removed.
+fn split_word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    (&s[..end], s[end..].trim_start())
+}
+
+/// Parse an `on`/`off` option argument.
+fn parse_on_off<'a>(args: &'a str, option: &mut Option<bool>) -> Option<&'a str> {
+    let (value, args) = split_word(args);
+    *option = Some(match value {
+        "on" => true,
+        "off" => false,
+        _ => return None,
+    });
+    Some(args)
+}
+
+/// Parse a single value option argument.
+fn parse_value<'a, V: FromStr>(args: &'a str, option: &mut Option<V>) -> Option<&'a str> {
+    let (value, args) = split_word(args);
+    *option = Some(value.parse().ok()?);
+    Some(args)
+}
+
+/// Parse the one to three components of a vector option argument. Components
+/// that are left out keep their value from `default`.
+fn parse_vector<'a>(
+    mut args: &'a str,
+    default: [f32; 3],
+    option: &mut Option<[f32; 3]>,
+) -> Option<&'a str> {
+    let mut vector = default;
+    for (i, component) in vector.iter_mut().enumerate() {
+        let (value, rest) = split_word(args);
+        match value.parse() {
+            Ok(value) => *component = value,
+            // The first component is required.
+            Err(_) if i == 0 => return None,
+            Err(_) => break,
+        }
+        args = rest;
+    }
+    *option = Some(vector);
+    Some(args)
+}
@@ -0,0 +1,147 @@
+//! Triangulation of polygons that are not trivially convertible to triangle
+//! fans.
+
+use crate::{
+    normals::{cross, dot, newell_normal, normalize},
+    ParseableV, VertexIndices,
+};
+
+/// The algorithm used to split polygons into triangles when
+/// [`triangulate`](crate::LoadOptions::triangulate) is set to `true`.
+#[cfg_attr(feature = "arb", derive(arbitrary::Arbitrary))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TriangulationMethod {
+    /// Split each polygon into a fan of triangles around its first vertex.
+    ///
+    /// This is fast but only gives correct results for convex polygons.
+    #[default]
+    Fan,
+    /// Project each polygon onto its best-fit plane and clip ears off it.
+    ///
+    /// This handles concave and slightly non-planar polygons. Convex polygons
+    /// result in the same triangles as [`Fan`](TriangulationMethod::Fan).
+    EarClipping,
+}
+
+/// Triangulate a polygon via ear clipping.
+///
+/// Returns the triangles as indices into `polygon`, wound the same way as the
+/// polygon itself. Falls back to a triangle fan if the polygon is degenerate or
+/// refers to positions that do not exist. Polygons with fewer than three
+/// vertices yield no triangles.
+///
+/// Candidate ears are only tested against the reflex vertices left, as no
+/// other vertex can lie inside an ear of a simple polygon. This takes O(n²)
+/// time for polygons with few reflex vertices and O(n³) in the worst case.
+///
+/// If no ear is left, e.g. for self-intersecting polygons, the most convex
+/// corner is clipped regardless, even if it is reflex. The triangles may
+/// overlap then.
+pub(crate) fn ear_clip<T: ParseableV>(pos: &[T], polygon: &[VertexIndices]) -> Vec<[usize; 3]> {
+    let n = polygon.len();
+    if n < 3 {
+        return Vec::new();
+    }
+    let fan = || (1..n - 1).map(|i| [0, i, i + 1]).collect();
+
+    let mut points = Vec::with_capacity(n);
+    for vertex in polygon {
+        let v = vertex.v;
+        if v.saturating_mul(3).saturating_add(2) >= pos.len() {
+            return fan();
+        }
+        points.push([
+            pos[v * 3].to_f64().unwrap_or_default(),
+            pos[v * 3 + 1].to_f64().unwrap_or_default(),
+            pos[v * 3 + 2].to_f64().unwrap_or_default(),
+        ]);
+    }
+
+    // The best-fit plane is given by the polygon's Newell normal. Project onto
+    // it using a basis (u, w) with u × w = normal so that the projected polygon
+    // is wound counter-clockwise.
+    let normal = newell_normal(n, |i| points[i]);
+    let length = dot(&normal, &normal).sqrt();
+    if length == 0.0 || !length.is_finite() {
+        return fan();
+    }
+    let normal = normalize(&normal);
+    let helper = if normal[0].abs() < 0.9 {
+        [1.0, 0.0, 0.0]
+    } else {
+        [0.0, 1.0, 0.0]
+    };
+    let u = normalize(&cross(&helper, &normal));
+    let w = cross(&normal, &u);
+
+    let projected: Vec<[f64; 2]> = points.iter().map(|p| [dot(p, &u), dot(p, &w)]).collect();
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let corner = |i: usize| {
+            (
+                remaining[(i + m - 1) % m],
+                remaining[i],
+                remaining[(i + 1) % m],
+            )
+        };
+        let convexity = |i: usize| {
+            let (a, b, c) = corner(i);
+            orientation(&projected[a], &projected[b], &projected[c])
+        };
+        // Collinear vertices may lie on the boundary of an ear, so they count
+        // as reflex, too.
+        let reflex: Vec<usize> = (0..m)
+            .filter(|&i| convexity(i) <= 0.0)
+            .map(|i| remaining[i])
+            .collect();
+        let is_ear = |i: usize| {
+            let (a, b, c) = corner(i);
+            convexity(i) > 0.0
+                && reflex.iter().all(|&p| {
+                    p == a
+                        || p == b
+                        || p == c
+                        // Coincident vertices, e.g. where a hole was bridged.
+                        || projected[p] == projected[a]
+                        || projected[p] == projected[b]
+                        || projected[p] == projected[c]
+                        || !in_triangle(&projected[p], &projected[a], &projected[b], &projected[c])
+                })
+        };
+
+        // Starting the search at the second vertex yields the same triangles
+        // as a fan for convex polygons.
+        let ear = (1..m).chain(0..1).find(|&i| is_ear(i)).unwrap_or_else(|| {
+            // Self-intersecting or numerically degenerate input. Clip the most
+            // convex corner, which need not be an ear, and carry on.
+            (0..m)
+                .max_by(|&a, &b| convexity(a).total_cmp(&convexity(b)))
+                .unwrap_or(0)
+        });
+
+        let (a, b, c) = corner(ear);
+        triangles.push([a, b, c]);
+        remaining.remove(ear);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    triangles
+}
+
+/// Twice the signed area of the 2D triangle `abc`. Positive if it is wound
+/// counter-clockwise.
+#[inline]
+fn orientation(a: &[f64; 2], b: &[f64; 2], c: &[f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Whether `p` lies inside or on the boundary of the counter-clockwise
+/// triangle `abc`.
+#[inline]
+fn in_triangle(p: &[f64; 2], a: &[f64; 2], b: &[f64; 2], c: &[f64; 2]) -> bool {
+    orientation(a, b, p) >= 0.0 && orientation(b, c, p) >= 0.0 && orientation(c, a, p) >= 0.0
+}
@@ -0,0 +1,412 @@
+//! Serialization of [`Model`]s and [`Material`]s to `OBJ` and `MTL` files.
+
+use std::{
+    fs::File,
+    io::{self, prelude::*, BufWriter},
+    path::Path,
+};
+
+use crate::{Material, Mesh, Model, ParseableV, NO_MATERIAL};
+
+/// Save models to an `OBJ` file.
+///
+/// If `materials` is not empty they are saved to an `MTL` file next to the
+/// `OBJ` file, with the same name but an `.mtl` extension, which is then
+/// referenced via `mtllib`.
+///
+/// See [`save_obj_buf()`] for details on how the models are written.
+pub fn save_obj<P, T>(file_name: P, models: &[Model<T>], materials: &[Material]) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    T: ParseableV,
+{
+    let file_name = file_name.as_ref();
+    let mtl_file_name = if materials.is_empty() {
+        None
+    } else {
+        let mtl_path = file_name.with_extension("mtl");
+        save_mtl(&mtl_path, materials)?;
+        mtl_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    };
+
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    save_obj_buf(&mut writer, models, materials, mtl_file_name.as_deref())?;
+    writer.flush()
+}
+
+/// Save the materials to an `MTL` file.
+pub fn save_mtl<P: AsRef<Path>>(file_name: P, materials: &[Material]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(file_name)?);
+    save_mtl_buf(&mut writer, materials)?;
+    writer.flush()
+}
+
+/// Write models as `OBJ` to a buffer.
+///
+/// Each model is written as an `o` statement followed by its vertex data and
+/// faces. Both meshes loaded with
+/// [`single_index`](crate::LoadOptions::single_index) and meshes with separate
+/// [`normal_indices`](Mesh::normal_indices) and
+/// [`texcoord_indices`](Mesh::texcoord_indices) are supported. Vertex colors
+/// are written inline with the positions, with an alpha only if it is not
+/// `1`, and [`weights`](Mesh::weights) only if there are no colors as a `v`
+/// statement cannot have both. Per-face
+/// [`face_materials`](Mesh::face_materials) are written as `usemtl` statements
+/// between the faces. Faces without a material that follow ones with a
+/// material are preceded by `usemtl (null)`.
+/// [`line_indices`](Mesh::line_indices) and
+/// [`point_indices`](Mesh::point_indices) are written as `l` and `p`
+/// statements after the faces. [`free_form`](Model::free_form) geometry and
+/// [`vertex_mask`](Mesh::vertex_mask)s are not written.
+///
+/// # Arguments
+///
+/// * `materials` – Used to look up the names of the materials referenced by
+///   [`material_id`](Mesh::material_id) for the `usemtl` statements.
+///
+/// * `mtl_file_name` – If given, an `mtllib` statement referencing it is
+///   written at the start of the file.
+///
+/// Loading the result with [`load_obj_buf()`](crate::load_obj_buf) and the
+/// same [`LoadOptions`](crate::LoadOptions) the models were loaded with
+/// reproduces them.
+///
+/// Fails with [`io::ErrorKind::InvalidInput`] without writing anything if a
+/// mesh has a [`texcoord_dimension`](Mesh::texcoord_dimension) outside of `1`
+/// to `3`.
+pub fn save_obj_buf<W, T>(
+    writer: &mut W,
+    models: &[Model<T>],
+    materials: &[Material],
+    mtl_file_name: Option<&str>,
+) -> io::Result<()>
+where
+    W: Write,
+    T: ParseableV,
+{
+    if let Some(model) = models
+        .iter()
+        .find(|model| !(1..=3).contains(&model.mesh.texcoord_dimension))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "model {:?} has a texture coordinate dimension of {}",
+                model.name, model.mesh.texcoord_dimension
+            ),
+        ));
+    }
+
+    if let Some(mtl_file_name) = mtl_file_name {
+        writeln!(writer, "mtllib {}", mtl_file_name)?;
+    }
+
+    // `OBJ` indices are global to the file and start at 1.
+    let mut position_offset = 1;
+    let mut texcoord_offset = 1;
+    let mut normal_offset = 1;
+    // Smoothing groups and materials also carry over from one object to the
+    // next.
+    let mut smoothing_group = None;
+    let mut material_id = None;
+
+    for model in models {
+        let mesh = &model.mesh;
+        writeln!(writer, "o {}", model.name)?;
+
+        write_mesh_data(writer, mesh)?;
+
+        if mesh.face_materials.is_empty() {
+            write_material(writer, materials, &mut material_id, mesh.material_id)?;
+        }
+
+        // Without separate indices normals and texture coordinates share the
+        // position index.
+        let texcoord_indices = if !mesh.texcoord_indices.is_empty() {
+            Some(&mesh.texcoord_indices[..])
+        } else if !mesh.texcoords.is_empty() {
+            Some(&mesh.indices[..])
+        } else {
+            None
+        };
+        let normal_indices = if !mesh.normal_indices.is_empty() {
+            Some(&mesh.normal_indices[..])
+        } else if !mesh.normals.is_empty() {
+            Some(&mesh.indices[..])
+        } else {
+            None
+        };
+
+        let offsets = [position_offset, texcoord_offset, normal_offset];
+        let mut start = 0;
+        for face in 0..face_count(mesh) {
+            let arity = mesh
+                .face_arities
+                .get(face)
+                .map_or(3, |&arity| arity as usize);
+
+            if let Some(&face_material) = mesh.face_materials.get(face) {
+                write_material(writer, materials, &mut material_id, face_material)?;
+            }
+
+            // Meshes without smoothing groups only turn off the group of the
+            // previous mesh.
+            let group = match mesh.smoothing_groups.get(face) {
+                Some(&group) => Some(group),
+                None => smoothing_group.map(|_| 0),
+            };
+            if group != smoothing_group {
+                smoothing_group = group;
+                match group {
+                    Some(0) => writeln!(writer, "s off")?,
+                    Some(group) => writeln!(writer, "s {}", group)?,
+                    None => {}
+                }
+            }
+
+            writer.write_all(if arity == 2 { b"l" } else { b"f" })?;
+            for i in start..start + arity {
+                write_vertex(
+                    writer,
+                    mesh.indices[i],
+                    texcoord_indices.map(|vt| vt[i]),
+                    normal_indices.map(|vn| vn[i]),
+                    offsets,
+                )?;
+            }
+            writeln!(writer)?;
+
+            start += arity;
+        }
+
+        // Lines and points only have their own position indices.
+        let shared_texcoords = mesh.texcoord_indices.is_empty() && !mesh.texcoords.is_empty();
+        let shared_normals = mesh.normal_indices.is_empty() && !mesh.normals.is_empty();
+        let write_shared_vertex = |writer: &mut W, index: u32| {
+            write_vertex(
+                writer,
+                index,
+                if shared_texcoords { Some(index) } else { None },
+                if shared_normals { Some(index) } else { None },
+                offsets,
+            )
+        };
+
+        let mut start = 0;
+        for &arity in &mesh.line_arities {
+            writer.write_all(b"l")?;
+            for &index in &mesh.line_indices[start..start + arity as usize] {
+                write_shared_vertex(writer, index)?;
+            }
+            writeln!(writer)?;
+            start += arity as usize;
+        }
+        if !mesh.point_indices.is_empty() {
+            writer.write_all(b"p")?;
+            for &index in &mesh.point_indices {
+                write_shared_vertex(writer, index)?;
+            }
+            writeln!(writer)?;
+        }
+
+        position_offset += mesh.positions.len() / 3;
+        texcoord_offset += mesh.texcoords.len() / mesh.texcoord_dimension;
+        normal_offset += mesh.normals.len() / 3;
+    }
+
+    Ok(())
+}
+
+/// Write a `usemtl` statement if `material_id` differs from the `current` one.
+///
+/// Ids without a material in `materials` are treated as no material.
+fn write_material<W: Write>(
+    writer: &mut W,
+    materials: &[Material],
+    current: &mut Option<usize>,
+    material_id: Option<usize>,
+) -> io::Result<()> {
+    let material_id = material_id.filter(|&id| id < materials.len());
+    if material_id != *current {
+        *current = material_id;
+        match material_id {
+            Some(id) => writeln!(writer, "usemtl {}", materials[id].name)?,
+            None => writeln!(writer, "usemtl {}", NO_MATERIAL)?,
+        }
+    }
+    Ok(())
+}
+
+/// Write a vertex of a face, line or points statement.
+fn write_vertex<W: Write>(
+    writer: &mut W,
+    position: u32,
+    texcoord: Option<u32>,
+    normal: Option<u32>,
+    offsets: [usize; 3],
+) -> io::Result<()> {
+    let [position_offset, texcoord_offset, normal_offset] = offsets;
+    write!(writer, " {}", position as usize + position_offset)?;
+    match (texcoord, normal) {
+        (Some(vt), Some(vn)) => write!(
+            writer,
+            "/{}/{}",
+            vt as usize + texcoord_offset,
+            vn as usize + normal_offset
+        ),
+        (Some(vt), None) => write!(writer, "/{}", vt as usize + texcoord_offset),
+        (None, Some(vn)) => write!(writer, "//{}", vn as usize + normal_offset),
+        (None, None) => Ok(()),
+    }
+}
+
+/// Write the `v`, `vt` and `vn` statements of a mesh.
+fn write_mesh_data<W: Write, T: ParseableV>(writer: &mut W, mesh: &Mesh<T>) -> io::Result<()> {
+    let has_color = mesh.vertex_color.len() / 4 == mesh.positions.len() / 3;
+    let has_weight = mesh.weights.len() * 3 == mesh.positions.len();
+    for (i, p) in mesh.positions.chunks_exact(3).enumerate() {
+        write!(writer, "v {} {} {}", p[0], p[1], p[2])?;
+        if has_color {
+            let c = &mesh.vertex_color[i * 4..i * 4 + 4];
+            write!(writer, " {} {} {}", c[0], c[1], c[2])?;
+            if c[3] != T::one() {
+                write!(writer, " {}", c[3])?;
+            }
+        } else if has_weight {
+            write!(writer, " {}", mesh.weights[i])?;
+        }
+        writeln!(writer)?;
+    }
+    for t in mesh.texcoords.chunks_exact(mesh.texcoord_dimension) {
+        write!(writer, "vt")?;
+        for component in t {
+            write!(writer, " {}", component)?;
+        }
+        writeln!(writer)?;
+    }
+    for n in mesh.normals.chunks_exact(3) {
+        writeln!(writer, "vn {} {} {}", n[0], n[1], n[2])?;
+    }
+    Ok(())
+}
+
+/// The number of faces in a mesh. All faces are triangles if the
+/// [`face_arities`](Mesh::face_arities) are empty.
+fn face_count<T: ParseableV>(mesh: &Mesh<T>) -> usize {
+    if mesh.face_arities.is_empty() {
+        mesh.indices.len() / 3
+    } else {
+        mesh.face_arities.len()
+    }
+}
+
+/// Write materials as `MTL` to a buffer.
+///
+/// Unrecognized parameters stored in
+/// [`unknown_param`](Material::unknown_param) are written back as they were
+/// read, sorted by name.
+pub fn save_mtl_buf<W: Write>(writer: &mut W, materials: &[Material]) -> io::Result<()> {
+    for (i, material) in materials.iter().enumerate() {
+        if i != 0 {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "newmtl {}", material.name)?;
+
+        for (keyword, rgb, source) in [
+            ("Ka", material.ambient, &material.ambient_source),
+            ("Kd", material.diffuse, &material.diffuse_source),
+            ("Ks", material.specular, &material.specular_source),
+        ] {
+            match source {
+                Some(source) => writeln!(writer, "{} {}", keyword, source)?,
+                None => writeln!(writer, "{} {} {} {}", keyword, rgb[0], rgb[1], rgb[2])?,
+            }
+        }
+        writeln!(writer, "Ns {}", material.shininess)?;
+        writeln!(writer, "Ni {}", material.optical_density)?;
+        if material.dissolve_halo {
+            writeln!(writer, "d -halo {}", material.dissolve)?;
+        } else {
+            writeln!(writer, "d {}", material.dissolve)?;
+        }
+        if let Some(source) = &material.transmission_filter_source {
+            writeln!(writer, "Tf {}", source)?;
+        } else if let Some([r, g, b]) = material.transmission_filter {
+            writeln!(writer, "Tf {} {} {}", r, g, b)?;
+        }
+        if let Some(sharpness) = material.sharpness {
+            writeln!(writer, "sharpness {}", sharpness)?;
+        }
+        if let Some(illumination_model) = material.illumination_model {
+            writeln!(writer, "illum {}", illumination_model)?;
+        }
+        for (keyword, value) in [
+            ("Pr", material.roughness),
+            ("Pm", material.metallic),
+            ("Ps", material.sheen),
+            ("Pc", material.clearcoat_thickness),
+            ("Pcr", material.clearcoat_roughness),
+            ("aniso", material.anisotropy),
+            ("anisor", material.anisotropy_rotation),
+        ] {
+            if let Some(value) = value {
+                writeln!(writer, "{} {}", keyword, value)?;
+            }
+        }
+        if let Some(source) = &material.emissive_source {
+            writeln!(writer, "Ke {}", source)?;
+        } else if let Some([r, g, b]) = material.emissive {
+            writeln!(writer, "Ke {} {} {}", r, g, b)?;
+        }
+
+        for (keyword, texture) in [
+            ("map_Ka", &material.ambient_texture),
+            ("map_Kd", &material.diffuse_texture),
+            ("map_Ks", &material.specular_texture),
+            ("map_Bump", &material.normal_texture),
+            ("norm", &material.pbr_normal_texture),
+            ("map_Ns", &material.shininess_texture),
+            ("map_d", &material.dissolve_texture),
+            ("map_Pr", &material.roughness_texture),
+            ("map_Pm", &material.metallic_texture),
+            ("map_Ps", &material.sheen_texture),
+            ("map_Ke", &material.emissive_texture),
+            ("disp", &material.displacement_texture),
+            ("decal", &material.decal_texture),
+            ("refl", &material.reflection_texture),
+        ] {
+            if !texture.is_empty() {
+                writeln!(writer, "{} {}", keyword, texture)?;
+            }
+        }
+        let cube_types = [
+            "cube_top",
+            "cube_bottom",
+            "cube_front",
+            "cube_back",
+            "cube_left",
+            "cube_right",
+        ];
+        for (map_type, face) in cube_types.iter().zip(material.reflection_cube.faces()) {
+            if !face.is_empty() {
+                // The type tells the faces apart, so it must not be missing.
+                let mut face = face.clone();
+                face.options.map_type = Some(map_type.to_string());
+                writeln!(writer, "refl {}", face)?;
+            }
+        }
+        if material.texture_anti_aliasing {
+            writeln!(writer, "map_aat on")?;
+        }
+
+        let mut unknown_param: Vec<_> = material.unknown_param.iter().collect();
+        unknown_param.sort();
+        for (key, value) in unknown_param {
+            writeln!(writer, "{} {}", key, value)?;
+        }
+    }
+
+    Ok(())
+}